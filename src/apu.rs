@@ -0,0 +1,629 @@
+//! The four sound channels and the mixer (0xFF10-0xFF3F), sampled at
+//! `SAMPLE_RATE`. Samples are only collected while capturing, see
+//! `Apu::set_capture`.
+
+use crate::state::{Reader, Writer};
+
+pub const SAMPLE_RATE: u32 = 44100;
+pub const CHANNELS: [&str; 4] = ["square1", "square2", "wave", "noise"];
+
+const CLOCK: u64 = 4194304;
+// The frame sequencer runs at 512 Hz.
+const SEQUENCER_PERIOD: u32 = 8192;
+
+const NR52_POWER: u8 = 1 << 7;
+const NRX4_TRIGGER: u8 = 1 << 7;
+const NRX4_LENGTH: u8 = 1 << 6;
+
+const DUTIES: [u8; 4] = [0b0000_0001, 0b1000_0001, 0b1000_0111, 0b0111_1110];
+const NOISE_DIVISORS: [u32; 8] = [8, 16, 32, 48, 64, 80, 96, 112];
+
+// Bits that always read as 1, for 0xFF10-0xFF2F.
+#[rustfmt::skip]
+const READ_MASKS: [u8; 0x20] = [
+    0x80, 0x3F, 0x00, 0xFF, 0xBF, // NR10-NR14
+    0xFF, 0x3F, 0x00, 0xFF, 0xBF, // NR20-NR24
+    0x7F, 0xFF, 0x9F, 0xFF, 0xBF, // NR30-NR34
+    0xFF, 0xFF, 0x00, 0x00, 0xBF, // NR40-NR44
+    0x00, 0x00, 0x70,             // NR50-NR52
+    0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF,
+];
+
+/// One output sample: the stereo mix and each channel on its own.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Sample {
+    pub left: i16,
+    pub right: i16,
+    pub channels: [i16; 4],
+}
+
+#[derive(Default, Clone)]
+struct Envelope {
+    volume: u8,
+    timer: u8,
+}
+
+impl Envelope {
+    fn trigger(&mut self, nrx2: u8) {
+        self.volume = nrx2 >> 4;
+        self.timer = nrx2 & 0b111;
+    }
+
+    fn clock(&mut self, nrx2: u8) {
+        let period = nrx2 & 0b111;
+
+        if period == 0 {
+            return;
+        }
+
+        self.timer = self.timer.saturating_sub(1);
+
+        if self.timer == 0 {
+            self.timer = period;
+
+            if nrx2 & 0b1000 != 0 && self.volume < 15 {
+                self.volume += 1;
+            } else if nrx2 & 0b1000 == 0 && self.volume > 0 {
+                self.volume -= 1;
+            }
+        }
+    }
+
+    fn save(&self, w: &mut Writer) {
+        w.u8(self.volume);
+        w.u8(self.timer);
+    }
+
+    fn load(&mut self, r: &mut Reader) -> Result<(), String> {
+        self.volume = r.u8()?;
+        self.timer = r.u8()?;
+
+        Ok(())
+    }
+}
+
+/// State shared by every channel; the rest lives in the registers.
+#[derive(Default, Clone)]
+struct Channel {
+    enabled: bool,
+    length: u16,
+    timer: u32,
+    position: u8,
+    envelope: Envelope,
+}
+
+impl Channel {
+    fn clock_length(&mut self, nrx4: u8) {
+        if nrx4 & NRX4_LENGTH != 0 && self.length > 0 {
+            self.length -= 1;
+
+            if self.length == 0 {
+                self.enabled = false;
+            }
+        }
+    }
+
+    fn save(&self, w: &mut Writer) {
+        w.bool(self.enabled);
+        w.u16(self.length);
+        w.u32(self.timer);
+        w.u8(self.position);
+        self.envelope.save(w);
+    }
+
+    fn load(&mut self, r: &mut Reader) -> Result<(), String> {
+        self.enabled = r.bool()?;
+        self.length = r.u16()?;
+        self.timer = r.u32()?;
+        self.position = r.u8()?;
+        self.envelope.load(r)
+    }
+}
+
+#[derive(Clone)]
+pub struct Apu {
+    // 0xFF10-0xFF2F as written, then wave RAM.
+    regs: [u8; 0x30],
+    channels: [Channel; 4],
+    sweep_enabled: bool,
+    sweep_timer: u8,
+    sweep_shadow: u16,
+    lfsr: u16,
+    sequencer_clock: u32,
+    sequencer_step: u8,
+    sample_clock: u64,
+    capture: bool,
+    samples: Vec<Sample>,
+}
+
+impl Default for Apu {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Apu {
+    pub fn new() -> Self {
+        Self {
+            regs: [0; 0x30],
+            channels: Default::default(),
+            sweep_enabled: false,
+            sweep_timer: 0,
+            sweep_shadow: 0,
+            lfsr: 0x7FFF,
+            sequencer_clock: 0,
+            sequencer_step: 0,
+            sample_clock: 0,
+            capture: false,
+            samples: Vec::new(),
+        }
+    }
+
+    /// Starts or stops collecting samples for `take_samples`.
+    pub fn set_capture(&mut self, capture: bool) {
+        self.capture = capture;
+
+        if !capture {
+            self.samples.clear();
+        }
+    }
+
+    /// Samples produced since the last call.
+    pub fn take_samples(&mut self) -> Vec<Sample> {
+        std::mem::take(&mut self.samples)
+    }
+
+    fn reg(&self, address: u16) -> u8 {
+        self.regs[address as usize - 0xFF10]
+    }
+
+    fn frequency(&self, channel: usize) -> u16 {
+        let base = 0xFF13 + channel as u16 * 5;
+
+        ((self.reg(base + 1) as u16 & 0b111) << 8) | self.reg(base) as u16
+    }
+
+    fn set_frequency(&mut self, channel: usize, frequency: u16) {
+        let base = 0xFF13 - 0xFF10 + channel * 5;
+
+        self.regs[base] = frequency as u8;
+        self.regs[base + 1] =
+            (self.regs[base + 1] & !0b111) | (frequency >> 8) as u8;
+    }
+
+    fn dac_on(&self, channel: usize) -> bool {
+        match channel {
+            2 => self.reg(0xFF1A) & 0x80 != 0,
+            _ => self.reg(0xFF12 + channel as u16 * 5) & 0xF8 != 0,
+        }
+    }
+
+    pub fn read_reg(&self, address: u16) -> u8 {
+        match address {
+            0xFF26 => {
+                let status = self
+                    .channels
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, c)| c.enabled)
+                    .fold(0, |status, (i, _)| status | 1 << i);
+
+                0x70 | (self.reg(0xFF26) & NR52_POWER) | status
+            }
+            0xFF10..=0xFF2F => {
+                self.reg(address) | READ_MASKS[address as usize - 0xFF10]
+            }
+            0xFF30..=0xFF3F => self.reg(address),
+            _ => 0xFF,
+        }
+    }
+
+    pub fn write_reg(&mut self, address: u16, byte: u8) {
+        let powered = self.reg(0xFF26) & NR52_POWER != 0;
+
+        match address {
+            0xFF26 => {
+                if byte & NR52_POWER == 0 {
+                    let wave_ram = self.regs[0x20..].to_vec();
+
+                    *self = Self {
+                        capture: self.capture,
+                        samples: std::mem::take(&mut self.samples),
+                        sample_clock: self.sample_clock,
+                        ..Self::new()
+                    };
+
+                    self.regs[0x20..].copy_from_slice(&wave_ram);
+                }
+
+                self.regs[0x16] = byte & NR52_POWER;
+            }
+            0xFF30..=0xFF3F => self.regs[address as usize - 0xFF10] = byte,
+            0xFF10..=0xFF25 if powered => {
+                self.regs[address as usize - 0xFF10] = byte;
+
+                let channel = (address as usize - 0xFF10) / 5;
+
+                match address {
+                    0xFF11 | 0xFF16 | 0xFF20 => {
+                        self.channels[channel].length =
+                            64 - (byte & 0x3F) as u16
+                    }
+                    0xFF1B => self.channels[2].length = 256 - byte as u16,
+                    0xFF1A | 0xFF12 | 0xFF17 | 0xFF21
+                        if !self.dac_on(channel) =>
+                    {
+                        self.channels[channel].enabled = false
+                    }
+                    0xFF14 | 0xFF19 | 0xFF1E | 0xFF23
+                        if byte & NRX4_TRIGGER != 0 =>
+                    {
+                        self.trigger(channel)
+                    }
+                    _ => {}
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn trigger(&mut self, channel: usize) {
+        let nrx2 = self.reg(0xFF12 + channel as u16 * 5);
+        let c = &mut self.channels[channel];
+
+        c.enabled = true;
+
+        if c.length == 0 {
+            c.length = if channel == 2 { 256 } else { 64 };
+        }
+
+        c.position = 0;
+        c.envelope.trigger(nrx2);
+
+        match channel {
+            0 => {
+                let nr10 = self.reg(0xFF10);
+
+                self.sweep_shadow = self.frequency(0);
+                self.sweep_timer = sweep_period(nr10);
+                self.sweep_enabled = nr10 & 0b0111_0111 != 0;
+
+                if nr10 & 0b111 != 0 {
+                    self.sweep_frequency();
+                }
+            }
+            3 => self.lfsr = 0x7FFF,
+            _ => {}
+        }
+
+        self.channels[channel].timer = self.period(channel);
+
+        if !self.dac_on(channel) {
+            self.channels[channel].enabled = false;
+        }
+    }
+
+    /// The next swept frequency; disables channel 1 on overflow.
+    fn sweep_frequency(&mut self) -> u16 {
+        let nr10 = self.reg(0xFF10);
+        let delta = self.sweep_shadow >> (nr10 & 0b111);
+
+        let frequency = if nr10 & 0b1000 != 0 {
+            self.sweep_shadow - delta
+        } else {
+            self.sweep_shadow + delta
+        };
+
+        if frequency > 2047 {
+            self.channels[0].enabled = false;
+        }
+
+        frequency
+    }
+
+    fn clock_sweep(&mut self) {
+        let nr10 = self.reg(0xFF10);
+
+        self.sweep_timer = self.sweep_timer.saturating_sub(1);
+
+        if self.sweep_timer > 0 {
+            return;
+        }
+
+        self.sweep_timer = sweep_period(nr10);
+
+        if !self.sweep_enabled || nr10 & 0b0111_0000 == 0 {
+            return;
+        }
+
+        let frequency = self.sweep_frequency();
+
+        if frequency <= 2047 && nr10 & 0b111 != 0 {
+            self.sweep_shadow = frequency;
+            self.set_frequency(0, frequency);
+
+            self.sweep_frequency();
+        }
+    }
+
+    /// T-cycles between steps of the channel's waveform.
+    fn period(&self, channel: usize) -> u32 {
+        match channel {
+            0 | 1 => (2048 - self.frequency(channel) as u32) * 4,
+            2 => (2048 - self.frequency(channel) as u32) * 2,
+            _ => {
+                let nr43 = self.reg(0xFF22);
+
+                NOISE_DIVISORS[(nr43 & 0b111) as usize] << (nr43 >> 4)
+            }
+        }
+    }
+
+    fn step_waveform(&mut self, channel: usize) {
+        match channel {
+            0 | 1 => {
+                let c = &mut self.channels[channel];
+                c.position = (c.position + 1) % 8;
+            }
+            2 => {
+                let c = &mut self.channels[channel];
+                c.position = (c.position + 1) % 32;
+            }
+            _ => {
+                let bit = (self.lfsr ^ (self.lfsr >> 1)) & 1;
+
+                self.lfsr = (self.lfsr >> 1) | (bit << 14);
+
+                // 7-bit mode
+                if self.reg(0xFF22) & 0b1000 != 0 {
+                    self.lfsr = (self.lfsr & !(1 << 6)) | (bit << 6);
+                }
+            }
+        }
+    }
+
+    fn clock_sequencer(&mut self) {
+        if self.sequencer_step.is_multiple_of(2) {
+            for (channel, nrx4) in
+                [0xFF14, 0xFF19, 0xFF1E, 0xFF23].iter().enumerate()
+            {
+                let nrx4 = self.reg(*nrx4);
+                self.channels[channel].clock_length(nrx4);
+            }
+        }
+
+        if self.sequencer_step == 2 || self.sequencer_step == 6 {
+            self.clock_sweep();
+        }
+
+        if self.sequencer_step == 7 {
+            for channel in [0, 1, 3].iter() {
+                let nrx2 = self.reg(0xFF12 + *channel as u16 * 5);
+                self.channels[*channel].envelope.clock(nrx2);
+            }
+        }
+
+        self.sequencer_step = (self.sequencer_step + 1) % 8;
+    }
+
+    /// Digital output (0-15) of a channel.
+    fn output(&self, channel: usize) -> u8 {
+        let c = &self.channels[channel];
+
+        if !c.enabled {
+            return 0;
+        }
+
+        match channel {
+            0 | 1 => {
+                let duty = self.reg(0xFF11 + channel as u16 * 5) >> 6;
+
+                (DUTIES[duty as usize] >> (7 - c.position) & 1)
+                    * c.envelope.volume
+            }
+            2 => {
+                let byte = self.reg(0xFF30 + c.position as u16 / 2);
+                let nibble = if c.position.is_multiple_of(2) {
+                    byte >> 4
+                } else {
+                    byte & 0x0F
+                };
+
+                match (self.reg(0xFF1C) >> 5) & 0b11 {
+                    0 => 0,
+                    shift => nibble >> (shift - 1),
+                }
+            }
+            _ => (!self.lfsr & 1) as u8 * c.envelope.volume,
+        }
+    }
+
+    fn sample(&self) -> Sample {
+        let nr50 = self.reg(0xFF24);
+        let nr51 = self.reg(0xFF25);
+
+        let mut sample = Sample {
+            left: 0,
+            right: 0,
+            channels: [0; 4],
+        };
+
+        let (mut left, mut right) = (0, 0);
+
+        for channel in 0..4 {
+            // Each DAC maps 0-15 to -15..15; a disabled one is silent
+            let analog = if self.dac_on(channel) {
+                self.output(channel) as i16 * 2 - 15
+            } else {
+                0
+            };
+
+            sample.channels[channel] = analog * 2048;
+
+            if nr51 & (0x10 << channel) != 0 {
+                left += analog;
+            }
+
+            if nr51 & (1 << channel) != 0 {
+                right += analog;
+            }
+        }
+
+        // Up to 4 channels at 15, times a master volume of up to 8
+        sample.left = left * ((nr50 >> 4 & 0b111) as i16 + 1) * 64;
+        sample.right = right * ((nr50 & 0b111) as i16 + 1) * 64;
+
+        sample
+    }
+
+    pub fn step(&mut self, ticks: u8) {
+        if self.reg(0xFF26) & NR52_POWER != 0 {
+            for channel in 0..4 {
+                let mut remaining = ticks as u32;
+
+                while remaining >= self.channels[channel].timer {
+                    remaining -= self.channels[channel].timer;
+
+                    self.channels[channel].timer = self.period(channel);
+                    self.step_waveform(channel);
+                }
+
+                self.channels[channel].timer -= remaining;
+            }
+
+            self.sequencer_clock += ticks as u32;
+
+            if self.sequencer_clock >= SEQUENCER_PERIOD {
+                self.sequencer_clock -= SEQUENCER_PERIOD;
+                self.clock_sequencer();
+            }
+        }
+
+        if !self.capture {
+            return;
+        }
+
+        self.sample_clock += ticks as u64 * SAMPLE_RATE as u64;
+
+        while self.sample_clock >= CLOCK {
+            self.sample_clock -= CLOCK;
+
+            let sample = self.sample();
+            self.samples.push(sample);
+        }
+    }
+
+    /// Capturing is frontend state and not saved.
+    pub fn save(&self, w: &mut Writer) {
+        w.bytes(&self.regs);
+
+        for channel in &self.channels {
+            channel.save(w);
+        }
+
+        w.bool(self.sweep_enabled);
+        w.u8(self.sweep_timer);
+        w.u16(self.sweep_shadow);
+        w.u16(self.lfsr);
+        w.u32(self.sequencer_clock);
+        w.u8(self.sequencer_step);
+    }
+
+    pub fn load(&mut self, r: &mut Reader) -> Result<(), String> {
+        r.fill(&mut self.regs)?;
+
+        for channel in &mut self.channels {
+            channel.load(r)?;
+        }
+
+        self.sweep_enabled = r.bool()?;
+        self.sweep_timer = r.u8()?;
+        self.sweep_shadow = r.u16()?;
+        self.lfsr = r.u16()?;
+        self.sequencer_clock = r.u32()?;
+        self.sequencer_step = r.u8()?;
+
+        Ok(())
+    }
+}
+
+/// Sweep period from NR10, where 0 counts as 8.
+fn sweep_period(nr10: u8) -> u8 {
+    match (nr10 >> 4) & 0b111 {
+        0 => 8,
+        period => period,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn powered() -> Apu {
+        let mut apu = Apu::new();
+        apu.write_reg(0xFF26, NR52_POWER);
+        apu.write_reg(0xFF24, 0x77);
+        apu.write_reg(0xFF25, 0xFF);
+        apu.set_capture(true);
+
+        apu
+    }
+
+    #[test]
+    fn it_plays_a_square_wave() {
+        let mut apu = powered();
+
+        // 50% duty, full volume, 2048 - 1792 = 256 * 4 ticks per step
+        apu.write_reg(0xFF16, 0b1000_0000);
+        apu.write_reg(0xFF17, 0xF0);
+        apu.write_reg(0xFF18, 0x00);
+        apu.write_reg(0xFF19, NRX4_TRIGGER | 0x07);
+
+        assert_eq!(apu.read_reg(0xFF26), 0xF2);
+
+        for _ in 0..CLOCK / 100 / 4 {
+            apu.step(4);
+        }
+
+        // 41940 ticks
+        let samples = apu.take_samples();
+        assert_eq!(samples.len(), 440);
+
+        // 512 Hz, so a period is just over 86 samples
+        let highs = samples.iter().filter(|s| s.channels[1] > 0).count();
+        assert!((200..240).contains(&highs), "{} high samples", highs);
+        assert!(samples.iter().all(|s| s.channels[0] == 0));
+        assert!(samples.iter().all(|s| s.left == s.right));
+    }
+
+    #[test]
+    fn it_stops_channels_when_the_length_expires() {
+        let mut apu = powered();
+
+        apu.write_reg(0xFF21, 0xF0);
+        apu.write_reg(0xFF20, 0x3F);
+        apu.write_reg(0xFF23, NRX4_TRIGGER | NRX4_LENGTH);
+        assert_eq!(apu.read_reg(0xFF26) & 0x0F, 0b1000);
+
+        // One length clock every other sequencer step
+        for _ in 0..SEQUENCER_PERIOD * 2 / 4 {
+            apu.step(4);
+        }
+
+        assert_eq!(apu.read_reg(0xFF26) & 0x0F, 0);
+    }
+
+    #[test]
+    fn it_clears_registers_on_power_off() {
+        let mut apu = powered();
+        apu.write_reg(0xFF30, 0x12);
+        apu.write_reg(0xFF26, 0);
+
+        assert_eq!(apu.read_reg(0xFF25), 0);
+        assert_eq!(apu.read_reg(0xFF26), 0x70);
+        assert_eq!(apu.read_reg(0xFF30), 0x12);
+
+        apu.write_reg(0xFF25, 0xFF);
+        assert_eq!(apu.read_reg(0xFF25), 0);
+    }
+}
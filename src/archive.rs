@@ -0,0 +1,274 @@
+//! Loading ROMs straight from .zip and .gz archives, told apart from raw
+//! ROMs by their magic bytes.
+
+use crate::{checksum::crc32, inflate};
+use std::{fs, path::Path};
+
+const ZIP_MAGIC: &[u8] = b"PK\x03\x04";
+const GZIP_MAGIC: &[u8] = &[0x1F, 0x8B];
+
+const ZIP_END: u32 = 0x0605_4B50;
+const ZIP_CENTRAL: u32 = 0x0201_4B50;
+const ZIP_LOCAL: u32 = 0x0403_4B50;
+const ZIP_END_SIZE: usize = 22;
+const ZIP_ENCRYPTED: u16 = 1 << 0;
+
+const STORED: u16 = 0;
+const DEFLATED: u16 = 8;
+
+pub const ROM_EXTENSIONS: [&str; 2] = ["gb", "gbc"];
+
+/// Reads the ROM at `path`, unpacking it if it's an archive. From a zip,
+/// `entry` picks the file by its path or name; otherwise the first .gb or
+/// .gbc is used. Other files can't take an `entry`.
+pub fn load_rom<P: AsRef<Path>>(
+    path: P,
+    entry: Option<&str>,
+) -> Result<Vec<u8>, String> {
+    let path = path.as_ref();
+
+    fs::read(path)
+        .map_err(|e| e.to_string())
+        .and_then(|data| unpack(data, entry))
+        .map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+pub fn unpack(data: Vec<u8>, entry: Option<&str>) -> Result<Vec<u8>, String> {
+    if data.starts_with(ZIP_MAGIC) {
+        zip(&data, entry)
+    } else if entry.is_some() {
+        Err("--entry only applies to zip archives".to_string())
+    } else if data.starts_with(GZIP_MAGIC) {
+        inflate::gzip(&data)
+    } else {
+        Ok(data)
+    }
+}
+
+fn u16_at(data: &[u8], pos: usize) -> Result<u16, String> {
+    data.get(pos..pos + 2)
+        .map(|b| u16::from_le_bytes([b[0], b[1]]))
+        .ok_or_else(|| "zip is truncated".to_string())
+}
+
+fn u32_at(data: &[u8], pos: usize) -> Result<u32, String> {
+    data.get(pos..pos + 4)
+        .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+        .ok_or_else(|| "zip is truncated".to_string())
+}
+
+struct Entry {
+    name: String,
+    method: u16,
+    flags: u16,
+    crc: u32,
+    compressed_size: usize,
+    offset: usize,
+}
+
+/// The entries listed in the central directory.
+fn entries(data: &[u8]) -> Result<Vec<Entry>, String> {
+    // The end record is last, before a comment of up to 64 KiB
+    let end = (0..=data.len().saturating_sub(ZIP_END_SIZE))
+        .rev()
+        .take(0x10000)
+        .find(|pos| u32_at(data, *pos) == Ok(ZIP_END))
+        .ok_or("no zip end of central directory")?;
+
+    let count = u16_at(data, end + 10)? as usize;
+    let mut pos = u32_at(data, end + 16)? as usize;
+    let mut entries = Vec::with_capacity(count);
+
+    for _ in 0..count {
+        if u32_at(data, pos)? != ZIP_CENTRAL {
+            return Err("corrupt zip central directory".to_string());
+        }
+
+        let name_len = u16_at(data, pos + 28)? as usize;
+        let extra_len = u16_at(data, pos + 30)? as usize;
+        let comment_len = u16_at(data, pos + 32)? as usize;
+
+        let name = data
+            .get(pos + 46..pos + 46 + name_len)
+            .ok_or("zip is truncated")?;
+
+        entries.push(Entry {
+            name: String::from_utf8_lossy(name).to_string(),
+            flags: u16_at(data, pos + 8)?,
+            method: u16_at(data, pos + 10)?,
+            crc: u32_at(data, pos + 16)?,
+            compressed_size: u32_at(data, pos + 20)? as usize,
+            offset: u32_at(data, pos + 42)? as usize,
+        });
+
+        pos += 46 + name_len + extra_len + comment_len;
+    }
+
+    Ok(entries)
+}
+
+fn is_rom(name: &str) -> bool {
+    Path::new(name)
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .is_some_and(|extension| {
+            ROM_EXTENSIONS
+                .iter()
+                .any(|rom| rom.eq_ignore_ascii_case(extension))
+        })
+}
+
+fn zip(data: &[u8], name: Option<&str>) -> Result<Vec<u8>, String> {
+    let entries = entries(data)?;
+
+    let entry = match name {
+        Some(name) => entries
+            .iter()
+            .find(|entry| {
+                entry.name == name
+                    || entry.name.rsplit('/').next() == Some(name)
+            })
+            .ok_or_else(|| format!("no {} in the archive", name))?,
+        None => entries
+            .iter()
+            .find(|entry| is_rom(&entry.name))
+            .ok_or("no .gb or .gbc ROM in the archive")?,
+    };
+
+    if entry.flags & ZIP_ENCRYPTED != 0 {
+        return Err(format!("{} is encrypted", entry.name));
+    }
+
+    if u32_at(data, entry.offset)? != ZIP_LOCAL {
+        return Err("corrupt zip local header".to_string());
+    }
+
+    let start = entry.offset
+        + 30
+        + u16_at(data, entry.offset + 26)? as usize
+        + u16_at(data, entry.offset + 28)? as usize;
+
+    let compressed = data
+        .get(start..start + entry.compressed_size)
+        .ok_or("zip is truncated")?;
+
+    let out = match entry.method {
+        STORED => compressed.to_vec(),
+        DEFLATED => inflate::inflate(compressed)?.0,
+        method => {
+            return Err(format!(
+                "{} uses unsupported compression method {}",
+                entry.name, method
+            ))
+        }
+    };
+
+    if crc32(&out) != entry.crc {
+        return Err(format!("{} is corrupt (CRC-32 mismatch)", entry.name));
+    }
+
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Raw DEFLATE of b"Hello, Hello, Hello, rboy!"
+    const DEFLATED_HELLO: [u8; 16] = [
+        0xF3, 0x48, 0xCD, 0xC9, 0xC9, 0xD7, 0x51, 0xF0, 0x40, 0xA1, 0x8A, 0x92,
+        0xF2, 0x2B, 0x15, 0x01,
+    ];
+
+    /// A zip of `(name, method, data, uncompressed)` entries.
+    fn zip_of(files: &[(&str, u16, &[u8], &[u8])]) -> Vec<u8> {
+        let mut out = Vec::new();
+        let mut central = Vec::new();
+
+        for (name, method, data, uncompressed) in files {
+            let offset = out.len() as u32;
+
+            let mut header = Vec::new();
+            header.extend_from_slice(&method.to_le_bytes());
+            header.extend_from_slice(&[0; 4]);
+            header.extend_from_slice(&crc32(uncompressed).to_le_bytes());
+            header.extend_from_slice(&(data.len() as u32).to_le_bytes());
+            header
+                .extend_from_slice(&(uncompressed.len() as u32).to_le_bytes());
+            header.extend_from_slice(&(name.len() as u16).to_le_bytes());
+            header.extend_from_slice(&[0; 2]);
+
+            out.extend_from_slice(&ZIP_LOCAL.to_le_bytes());
+            out.extend_from_slice(&[20, 0, 0, 0]);
+            out.extend_from_slice(&header);
+            out.extend_from_slice(name.as_bytes());
+            out.extend_from_slice(data);
+
+            central.extend_from_slice(&ZIP_CENTRAL.to_le_bytes());
+            central.extend_from_slice(&[20, 0, 20, 0, 0, 0]);
+            central.extend_from_slice(&header);
+            central.extend_from_slice(&[0; 10]);
+            central.extend_from_slice(&offset.to_le_bytes());
+            central.extend_from_slice(name.as_bytes());
+        }
+
+        let central_offset = out.len() as u32;
+
+        out.extend_from_slice(&central);
+        out.extend_from_slice(&ZIP_END.to_le_bytes());
+        out.extend_from_slice(&[0; 6]);
+        out.extend_from_slice(&(files.len() as u16).to_le_bytes());
+        out.extend_from_slice(&(central.len() as u32).to_le_bytes());
+        out.extend_from_slice(&central_offset.to_le_bytes());
+        out.extend_from_slice(&[0; 2]);
+
+        out
+    }
+
+    #[test]
+    fn it_picks_roms_from_zips() {
+        let hello = b"Hello, Hello, Hello, rboy!";
+
+        let zip = zip_of(&[
+            ("readme.txt", STORED, b"hi", b"hi"),
+            ("Game.GB", DEFLATED, &DEFLATED_HELLO, hello),
+            ("other.gbc", STORED, b"other", b"other"),
+        ]);
+
+        assert_eq!(unpack(zip.clone(), None), Ok(hello.to_vec()));
+        assert_eq!(
+            unpack(zip.clone(), Some("other.gbc")),
+            Ok(b"other".to_vec())
+        );
+        assert!(unpack(zip, Some("missing.gb")).is_err());
+
+        let zip = zip_of(&[("readme.txt", STORED, b"hi", b"hi")]);
+        assert_eq!(
+            unpack(zip, None),
+            Err("no .gb or .gbc ROM in the archive".to_string())
+        );
+    }
+
+    #[test]
+    fn it_checks_zip_entries() {
+        let mut zip = zip_of(&[("game.gb", STORED, b"rom", b"rom")]);
+        zip[30 + 7] ^= 1;
+
+        assert!(unpack(zip, None).unwrap_err().contains("CRC-32"));
+
+        // Not an archive: the data as it is
+        assert_eq!(unpack(b"raw".to_vec(), None), Ok(b"raw".to_vec()));
+    }
+
+    #[test]
+    fn it_rejects_entries_outside_zips() {
+        let gzip = [GZIP_MAGIC, &[8, 0]].concat();
+
+        for data in [gzip, b"raw".to_vec()] {
+            assert_eq!(
+                unpack(data, Some("game.gb")),
+                Err("--entry only applies to zip archives".to_string())
+            );
+        }
+    }
+}
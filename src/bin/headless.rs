@@ -0,0 +1,281 @@
+use rboy::{
+    archive, header,
+    headless::{Headless, Outcome},
+    movie::Movie,
+    patch, screenshot,
+    symbols::Symbols,
+    vram, wav, Cpu,
+};
+use std::{convert::TryFrom, env, fs, path::PathBuf, process};
+
+const USAGE: &str = "\
+usage: rboy-headless <rom> [options]
+       rboy-headless info [--json] <rom>...
+
+The ROM may be in a .zip or .gz archive.
+
+  --entry NAME        the ROM to run from a zip (default: the first .gb/.gbc)
+  --boot-rom PATH     run the boot ROM first instead of starting at 0x0100
+  --patch PATH        apply an IPS, BPS or UPS patch (default: <rom>.ips etc.)
+  --movie PATH        replay the inputs of a movie, from its start state
+  --frames N          frame budget (default 3600)
+  --serial TEXT       stop once the serial output contains TEXT
+  --break TARGET      stop at a label, bank:addr or address (repeatable)
+  --memory ADDR=VAL   stop once memory at ADDR holds VAL (hex)
+  --ld-b-b            stop after LD B, B (Mooneye test ROMs)
+  --png PATH          write the final frame as PNG
+  --vram PREFIX       write the tiles and BG maps as PREFIX-tiles.png etc.
+  --wav PATH          write the sound as a 16-bit stereo WAV file
+  --wav-stems         also write each channel to PATH.<channel>.wav
+  --trace             log every executed instruction
+
+Exits with 0 once a stop condition holds (or after the frame budget when no
+condition is given), 1 if the frame budget runs out first and 2 on errors.
+
+info decodes the cartridge headers, as one JSON object per line with
+--json, and exits with 1 if a checksum or the logo is wrong.";
+
+struct Options {
+    rom: String,
+    entry: Option<String>,
+    boot_rom: Option<String>,
+    patch: Option<String>,
+    movie: Option<String>,
+    frames: u64,
+    serial: Option<String>,
+    breaks: Vec<String>,
+    memory: Option<(u16, u8)>,
+    ld_b_b: bool,
+    png: Option<String>,
+    vram: Option<String>,
+    wav: Option<String>,
+    wav_stems: bool,
+    trace: bool,
+}
+
+fn main() {
+    let info = env::args().nth(1).is_some_and(|arg| arg == "info");
+
+    let result = if info { info_command() } else { run() };
+
+    let code = match result {
+        Ok(code) => code,
+        Err(e) => {
+            eprintln!("{}", e);
+
+            2
+        }
+    };
+
+    process::exit(code);
+}
+
+fn hex<T: TryFrom<u32>>(s: &str) -> Option<T> {
+    let s = s.trim_start_matches("0x").trim_start_matches('$');
+
+    T::try_from(u32::from_str_radix(s, 16).ok()?).ok()
+}
+
+fn parse_args() -> Result<Options, String> {
+    let mut args = env::args().skip(1);
+
+    let mut options = Options {
+        rom: String::new(),
+        entry: None,
+        boot_rom: None,
+        patch: None,
+        movie: None,
+        frames: 3600,
+        serial: None,
+        breaks: Vec::new(),
+        memory: None,
+        ld_b_b: false,
+        png: None,
+        vram: None,
+        wav: None,
+        wav_stems: false,
+        trace: false,
+    };
+
+    while let Some(arg) = args.next() {
+        let mut value = || {
+            args.next()
+                .ok_or_else(|| format!("{} needs a value\n\n{}", arg, USAGE))
+        };
+
+        match arg.as_str() {
+            "--entry" => options.entry = Some(value()?),
+            "--boot-rom" => options.boot_rom = Some(value()?),
+            "--patch" => options.patch = Some(value()?),
+            "--movie" => options.movie = Some(value()?),
+            "--frames" => {
+                options.frames = value()?
+                    .parse()
+                    .map_err(|_| "--frames needs a number".to_string())?
+            }
+            "--serial" => options.serial = Some(value()?),
+            "--break" => options.breaks.push(value()?),
+            "--memory" => {
+                let value = value()?;
+                let mut parts = value.splitn(2, '=');
+
+                let address = parts.next().and_then(hex);
+                let val = parts.next().and_then(hex);
+
+                match (address, val) {
+                    (Some(address), Some(val)) => {
+                        options.memory = Some((address, val))
+                    }
+                    _ => return Err(format!("invalid --memory: {}", value)),
+                }
+            }
+            "--ld-b-b" => options.ld_b_b = true,
+            "--png" => options.png = Some(value()?),
+            "--vram" => options.vram = Some(value()?),
+            "--wav" => options.wav = Some(value()?),
+            "--wav-stems" => options.wav_stems = true,
+            "--trace" => options.trace = true,
+            "--help" | "-h" => return Err(USAGE.to_string()),
+            _ if arg.starts_with("--") => {
+                return Err(format!("unknown option {}\n\n{}", arg, USAGE))
+            }
+            _ => options.rom = arg,
+        }
+    }
+
+    if options.rom.is_empty() {
+        return Err(USAGE.to_string());
+    }
+
+    if options.wav_stems && options.wav.is_none() {
+        return Err("--wav-stems needs --wav".to_string());
+    }
+
+    Ok(options)
+}
+
+fn info_command() -> Result<i32, String> {
+    let mut json = false;
+    let mut paths = Vec::new();
+
+    for arg in env::args().skip(2) {
+        match arg.as_str() {
+            "--json" => json = true,
+            _ if arg.starts_with("--") => {
+                return Err(format!("unknown option {}\n\n{}", arg, USAGE))
+            }
+            _ => paths.push(arg),
+        }
+    }
+
+    if paths.is_empty() {
+        return Err(USAGE.to_string());
+    }
+
+    let mut code = 0;
+
+    for path in &paths {
+        let (text, result) = header::report(path, json);
+
+        println!("{}", text);
+        code = code.max(result);
+    }
+
+    Ok(code)
+}
+
+fn read(path: &str) -> Result<Vec<u8>, String> {
+    fs::read(path).map_err(|e| format!("{}: {}", path, e))
+}
+
+fn run() -> Result<i32, String> {
+    let options = parse_args()?;
+
+    let mut rom = archive::load_rom(&options.rom, options.entry.as_deref())?;
+
+    let patch = options
+        .patch
+        .as_ref()
+        .map(PathBuf::from)
+        .or_else(|| patch::for_rom(&options.rom));
+
+    if let Some(path) = patch {
+        rom = patch::apply_file(&rom, path)?;
+    }
+
+    let movie = match &options.movie {
+        Some(path) => {
+            Some(Movie::load(path).map_err(|e| format!("{}: {}", path, e))?)
+        }
+        None => None,
+    };
+
+    let mut cpu = match (&movie, &options.boot_rom) {
+        (Some(movie), _) => movie.start(rom)?,
+        (None, Some(path)) => Cpu::new(read(path)?, rom, None)
+            .map_err(|e| format!("{}: {}", path, e))?,
+        (None, None) => Cpu::post_boot(rom),
+    };
+
+    cpu.symbols = Symbols::for_rom(&options.rom);
+    cpu.trace = options.trace;
+
+    let mut headless = Headless::new(options.frames);
+    headless.serial = options.serial;
+    headless.memory = options.memory;
+    headless.ld_b_b = options.ld_b_b;
+
+    if let Some(movie) = movie {
+        headless.inputs = movie.inputs;
+    }
+
+    if let Some(path) = &options.wav {
+        headless.audio = Some(
+            wav::Dump::create(path, options.wav_stems)
+                .map_err(|e| format!("{}: {}", path, e))?,
+        );
+    }
+
+    for target in &options.breaks {
+        if !headless.debugger.break_on(&cpu, target) {
+            return Err(format!("unknown breakpoint target: {}", target));
+        }
+    }
+
+    let conditions = headless.serial.is_some()
+        || headless.memory.is_some()
+        || headless.ld_b_b
+        || !options.breaks.is_empty();
+
+    let outcome = headless.run(&mut cpu);
+
+    print!("{}", String::from_utf8_lossy(cpu.bus.serial_output()));
+
+    let outcome = outcome?;
+
+    eprintln!(
+        "{:?} after {} frames at {}",
+        outcome,
+        cpu.bus.gpu.frames(),
+        cpu.symbols.format_address(&cpu.bus, cpu.pc.get())
+    );
+
+    if let Some(path) = &options.png {
+        fs::write(path, screenshot::encode(&cpu.bus.gpu, 1))
+            .map_err(|e| format!("{}: {}", path, e))?;
+    }
+
+    if let Some(prefix) = &options.vram {
+        vram::save(&cpu.bus.gpu, prefix)
+            .map_err(|e| format!("{}: {}", prefix, e))?;
+    }
+
+    if let (Some(audio), Some(path)) = (headless.audio, &options.wav) {
+        audio.finish().map_err(|e| format!("{}: {}", path, e))?;
+    }
+
+    Ok(match outcome {
+        Outcome::Timeout if conditions => 1,
+        _ => 0,
+    })
+}
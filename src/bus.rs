@@ -0,0 +1,106 @@
+use std::cell::RefCell;
+
+/// Memory as the CPU sees it, plus whatever runs alongside the CPU.
+pub trait Bus {
+    fn read_byte(&self, address: u16) -> u8;
+
+    /// Reads an opcode or operand, which doesn't count as a data read for
+    /// watchpoints.
+    fn fetch_byte(&self, address: u16) -> u8 {
+        self.read_byte(address)
+    }
+
+    fn write_byte(&mut self, address: u16, byte: u8);
+
+    /// ROM or RAM bank mapped at `address`, for symbol lookups.
+    fn bank_at(&self, _address: u16) -> u16 {
+        0
+    }
+
+    /// Advances everything but the CPU by `ticks`.
+    fn tick(&mut self, _ticks: u8) {}
+
+    /// CPU cycles the CPU was held up for since the last call, e.g. by a
+    /// DMA started during the instruction.
+    fn take_stall(&mut self) -> u32 {
+        0
+    }
+
+    /// Called for STOP, which switches speed on the CGB.
+    fn stop(&mut self) {}
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Access {
+    Read(u16, u8),
+    Write(u16, u8),
+}
+
+/// 64 KiB of plain RAM with no hardware behind it, for testing the CPU on its
+/// own. Every access is recorded.
+pub struct FlatBus {
+    pub memory: Vec<u8>,
+    accesses: RefCell<Vec<Access>>,
+}
+
+impl Default for FlatBus {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FlatBus {
+    pub fn new() -> Self {
+        Self {
+            memory: vec![0; 0x10000],
+            accesses: RefCell::new(Vec::new()),
+        }
+    }
+
+    pub fn accesses(&self) -> Vec<Access> {
+        self.accesses.borrow().clone()
+    }
+
+    pub fn clear_accesses(&mut self) {
+        self.accesses.get_mut().clear();
+    }
+}
+
+impl Bus for FlatBus {
+    fn read_byte(&self, address: u16) -> u8 {
+        let byte = self.memory[address as usize];
+
+        self.accesses.borrow_mut().push(Access::Read(address, byte));
+
+        byte
+    }
+
+    fn write_byte(&mut self, address: u16, byte: u8) {
+        self.memory[address as usize] = byte;
+
+        self.accesses.get_mut().push(Access::Write(address, byte));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_records_accesses_in_order() {
+        let mut bus = FlatBus::new();
+
+        bus.write_byte(0xC000, 0x12);
+        assert_eq!(bus.read_byte(0xC000), 0x12);
+        assert_eq!(bus.read_byte(0xFFFF), 0);
+
+        assert_eq!(
+            bus.accesses(),
+            vec![
+                Access::Write(0xC000, 0x12),
+                Access::Read(0xC000, 0x12),
+                Access::Read(0xFFFF, 0),
+            ]
+        );
+    }
+}
@@ -0,0 +1,365 @@
+//! Game Genie and GameShark codes. Game Genie codes patch ROM reads and
+//! GameShark codes write RAM once a frame; the ROM itself is never changed.
+//!
+//! Cheat files sit next to the ROM as `.cht`, one cheat per section. Several
+//! codes are separated by `+`.
+//!
+//! ```text
+//! [Infinite lives]
+//! code = 010399C0
+//!
+//! [Moon jump]
+//! code = 00A-17B-C49+01FF3AD1
+//! enabled = false
+//! ```
+
+use crate::ini;
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Code {
+    /// Returns `value` for reads of `address`, if it holds `compare`.
+    GameGenie {
+        address: u16,
+        value: u8,
+        compare: Option<u8>,
+    },
+    /// Writes `value` to `address` every frame; with a bank, into that WRAM
+    /// bank whichever is mapped.
+    GameShark {
+        bank: Option<u8>,
+        address: u16,
+        value: u8,
+    },
+}
+
+impl Code {
+    /// `ABC-DEF` or `ABC-DEF-GHI` for the Game Genie, `TTVVAAAA` for the
+    /// GameShark.
+    pub fn parse(code: &str) -> Result<Self, String> {
+        let invalid = || format!("invalid code: {}", code);
+
+        let digits: Vec<u8> = code
+            .chars()
+            .filter(|c| *c != '-')
+            .map(|c| c.to_digit(16).map(|d| d as u8))
+            .collect::<Option<_>>()
+            .ok_or_else(invalid)?;
+
+        let byte = |i: usize| (digits[i] << 4) | digits[i + 1];
+
+        match (digits.len(), code.contains('-')) {
+            (6, true) | (9, true) => {
+                let address = u16::from_be_bytes([
+                    (digits[5] ^ 0xF) << 4 | digits[2],
+                    byte(3),
+                ]);
+
+                // GI holds the compare byte, rotated left by 2 and XORed
+                // with 0xBA; H is unused
+                let compare = if digits.len() == 9 {
+                    let gi = (digits[6] << 4) | digits[8];
+
+                    Some(gi.rotate_right(2) ^ 0xBA)
+                } else {
+                    None
+                };
+
+                Ok(Code::GameGenie {
+                    address,
+                    value: byte(0),
+                    compare,
+                })
+            }
+            (8, false) => {
+                let bank = match byte(0) {
+                    0x00 | 0x01 | 0x80 => None,
+                    ty @ 0x90..=0x97 => Some(ty & 0b111),
+                    ty => {
+                        return Err(format!(
+                            "unsupported GameShark type {:02X}: {}",
+                            ty, code
+                        ))
+                    }
+                };
+
+                Ok(Code::GameShark {
+                    bank,
+                    address: u16::from_le_bytes([byte(4), byte(6)]),
+                    value: byte(2),
+                })
+            }
+            _ => Err(invalid()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cheat {
+    pub name: String,
+    /// The codes as written.
+    pub code: String,
+    pub enabled: bool,
+    codes: Vec<Code>,
+}
+
+impl Cheat {
+    pub fn new(name: &str, code: &str) -> Result<Self, String> {
+        let codes = code
+            .split('+')
+            .map(|code| Code::parse(code.trim()))
+            .collect::<Result<_, _>>()?;
+
+        Ok(Self {
+            name: name.to_string(),
+            code: code.to_string(),
+            enabled: true,
+            codes,
+        })
+    }
+
+    pub fn codes(&self) -> &[Code] {
+        &self.codes
+    }
+}
+
+/// A ROM's cheats, with a switch for all of them.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cheats {
+    cheats: Vec<Cheat>,
+    active: bool,
+}
+
+impl Default for Cheats {
+    fn default() -> Self {
+        Self {
+            cheats: Vec::new(),
+            active: true,
+        }
+    }
+}
+
+impl Cheats {
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut cheats = Self::default();
+
+        for section in ini::parse(text)? {
+            let mut code = None;
+            let mut enabled = true;
+
+            for entry in &section.entries {
+                match entry.key.as_str() {
+                    "code" => code = Some(entry.value.as_str()),
+                    "enabled" => enabled = entry.value != "false",
+                    key => {
+                        return Err(format!(
+                            "line {}: unknown key {:?}",
+                            entry.line, key
+                        ))
+                    }
+                }
+            }
+
+            match code {
+                Some(code) => {
+                    let mut cheat = Cheat::new(&section.name, code)?;
+                    cheat.enabled = enabled;
+
+                    cheats.add(cheat);
+                }
+                None if section.name.is_empty() => {}
+                None => {
+                    return Err(format!("cheat {} has no code", section.name))
+                }
+            }
+        }
+
+        Ok(cheats)
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Self::parse(&fs::read_to_string(path)?)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+
+    /// Where the cheats for `rom_path` are kept: `<rom>.cht`.
+    pub fn path_for_rom<P: AsRef<Path>>(rom_path: P) -> PathBuf {
+        rom_path.as_ref().with_extension("cht")
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let sections: Vec<String> = self
+            .cheats
+            .iter()
+            .map(|cheat| {
+                format!(
+                    "[{}]\ncode = {}\nenabled = {}\n",
+                    cheat.name, cheat.code, cheat.enabled
+                )
+            })
+            .collect();
+
+        fs::write(path, sections.join("\n"))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cheats.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Cheat> {
+        self.cheats.iter()
+    }
+
+    pub fn add(&mut self, cheat: Cheat) {
+        self.cheats.push(cheat);
+    }
+
+    pub fn remove(&mut self, index: usize) -> Option<Cheat> {
+        if index < self.cheats.len() {
+            Some(self.cheats.remove(index))
+        } else {
+            None
+        }
+    }
+
+    /// Returns `false` if there's no cheat `index`.
+    pub fn set_enabled(&mut self, index: usize, enabled: bool) -> bool {
+        match self.cheats.get_mut(index) {
+            Some(cheat) => {
+                cheat.enabled = enabled;
+
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.active
+    }
+
+    /// Turns all cheats off without forgetting which are enabled.
+    pub fn set_active(&mut self, active: bool) {
+        self.active = active;
+    }
+
+    fn codes(&self) -> impl Iterator<Item = &Code> {
+        let active = self.active;
+
+        self.cheats
+            .iter()
+            .filter(move |cheat| active && cheat.enabled)
+            .flat_map(|cheat| cheat.codes.iter())
+    }
+
+    /// `byte` read from ROM at `address`, as the Game Genie codes change it.
+    pub fn patch_rom(&self, address: u16, byte: u8) -> u8 {
+        for code in self.codes() {
+            if let Code::GameGenie {
+                address: at,
+                value,
+                compare,
+            } = *code
+            {
+                if at == address && compare.is_none_or(|c| c == byte) {
+                    return value;
+                }
+            }
+        }
+
+        byte
+    }
+
+    /// Writes the GameShark codes make this frame, as `(bank, address,
+    /// value)`.
+    pub fn ram_writes(&self) -> Vec<(Option<u8>, u16, u8)> {
+        self.codes()
+            .filter_map(|code| match *code {
+                Code::GameShark {
+                    bank,
+                    address,
+                    value,
+                } => Some((bank, address, value)),
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_decodes_codes() {
+        assert_eq!(
+            Code::parse("01FF3AD1"),
+            Ok(Code::GameShark {
+                bank: None,
+                address: 0xD13A,
+                value: 0xFF,
+            })
+        );
+
+        assert_eq!(
+            Code::parse("920AD1D0"),
+            Ok(Code::GameShark {
+                bank: Some(2),
+                address: 0xD0D1,
+                value: 0x0A,
+            })
+        );
+
+        assert_eq!(
+            Code::parse("00A-17B"),
+            Ok(Code::GameGenie {
+                address: 0x4A17,
+                value: 0x00,
+                compare: None,
+            })
+        );
+
+        // 0xC4 rotated right by 2 is 0x31, XOR 0xBA
+        assert_eq!(
+            Code::parse("3EA-17B-C44"),
+            Ok(Code::GameGenie {
+                address: 0x4A17,
+                value: 0x3E,
+                compare: Some(0x8B),
+            })
+        );
+
+        assert!(Code::parse("00A17B").is_err());
+        assert!(Code::parse("05FF3AD1").is_err());
+        assert!(Code::parse("XYZ-123").is_err());
+    }
+
+    #[test]
+    fn it_patches_rom_reads_and_writes_ram() {
+        let mut cheats = Cheats::parse(
+            "[Lives]\n\
+             code = 3EA-17B-C44+01633AD1\n\
+             \n\
+             [Off]\n\
+             code = 0101C0C0\n\
+             enabled = false\n",
+        )
+        .unwrap();
+
+        assert_eq!(cheats.patch_rom(0x4A17, 0x8B), 0x3E);
+        assert_eq!(cheats.patch_rom(0x4A17, 0x00), 0x00);
+        assert_eq!(cheats.ram_writes(), vec![(None, 0xD13A, 0x63)]);
+
+        assert!(cheats.set_enabled(1, true));
+        assert_eq!(cheats.ram_writes().len(), 2);
+
+        cheats.set_active(false);
+        assert_eq!(cheats.patch_rom(0x4A17, 0x8B), 0x8B);
+        assert!(cheats.ram_writes().is_empty());
+
+        assert!(Cheats::parse("[x]\nenabled = true").is_err());
+    }
+}
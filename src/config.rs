@@ -0,0 +1,209 @@
+//! User configuration, `rboy/rboy.ini` in the user config directory by
+//! default. Keys in a section are addressed as `section.key`. `[rom NAME]`
+//! sections override settings for ROMs whose header title is NAME, or whose
+//! global checksum is NAME in hex, and take the same keys.
+//!
+//! ```text
+//! scale = 4
+//! palette = classic
+//!
+//! [keys]
+//! a = Z
+//!
+//! [rom TETRIS]
+//! palette = gbc
+//! keys.a = A
+//! ```
+
+use crate::{header, ini};
+use std::{
+    collections::BTreeMap,
+    env, fs, io,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+const ROM_SECTION: &str = "rom ";
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Settings {
+    values: BTreeMap<String, String>,
+}
+
+impl Settings {
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.values.get(key).map(String::as_str)
+    }
+
+    pub fn set(&mut self, key: &str, value: &str) {
+        self.values.insert(key.to_string(), value.to_string());
+    }
+
+    /// Settings from `other` replace these.
+    pub fn merge(&mut self, other: &Settings) {
+        for (key, value) in &other.values {
+            self.set(key, value);
+        }
+    }
+
+    pub fn parse<T: FromStr>(&self, key: &str) -> Result<Option<T>, String> {
+        match self.get(key) {
+            Some(value) => value
+                .parse()
+                .map(Some)
+                .map_err(|_| format!("invalid {}: {}", key, value)),
+            None => Ok(None),
+        }
+    }
+
+    pub fn flag(&self, key: &str) -> Result<Option<bool>, String> {
+        match self.get(key) {
+            Some("true") | Some("yes") | Some("on") | Some("1") => {
+                Ok(Some(true))
+            }
+            Some("false") | Some("no") | Some("off") | Some("0") => {
+                Ok(Some(false))
+            }
+            Some(value) => Err(format!("invalid {}: {}", key, value)),
+            None => Ok(None),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Config {
+    global: Settings,
+    roms: Vec<(String, Settings)>,
+}
+
+impl Config {
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut config = Self::default();
+
+        for section in ini::parse(text)? {
+            let (prefix, rom) = if section.name.starts_with(ROM_SECTION) {
+                ("", Some(section.name[ROM_SECTION.len()..].trim()))
+            } else {
+                (section.name.as_str(), None)
+            };
+
+            let mut settings = Settings::default();
+
+            for entry in &section.entries {
+                let key = if prefix.is_empty() {
+                    entry.key.clone()
+                } else {
+                    format!("{}.{}", prefix, entry.key)
+                };
+
+                settings.set(&key, &entry.value);
+            }
+
+            match rom {
+                Some(rom) => config.roms.push((rom.to_string(), settings)),
+                None => config.global.merge(&settings),
+            }
+        }
+
+        Ok(config)
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Self::parse(&fs::read_to_string(path)?)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+
+    /// The settings for `rom`: the global ones, then any matching overrides
+    /// in file order.
+    pub fn settings(&self, rom: &[u8]) -> Settings {
+        let title = header::title(rom);
+        let checksum = header::global_checksum(rom);
+
+        let mut settings = self.global.clone();
+
+        for (name, overrides) in &self.roms {
+            let by_checksum = u16::from_str_radix(name, 16).ok();
+
+            if name.eq_ignore_ascii_case(&title)
+                || (name.len() == 4 && by_checksum == checksum)
+            {
+                settings.merge(overrides);
+            }
+        }
+
+        settings
+    }
+}
+
+/// `rboy/rboy.ini` in the platform's user config directory.
+pub fn default_path() -> Option<PathBuf> {
+    let home = env::var_os("HOME").map(PathBuf::from);
+
+    let dir = if cfg!(windows) {
+        env::var_os("APPDATA").map(PathBuf::from)
+    } else if cfg!(target_os = "macos") {
+        home.map(|home| home.join("Library/Application Support"))
+    } else {
+        env::var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .or_else(|| home.map(|home| home.join(".config")))
+    };
+
+    dir.map(|dir| dir.join("rboy").join("rboy.ini"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONFIG: &str = "\
+scale = 4
+palette = classic
+
+[keys]
+a = Z
+
+[rom TETRIS]
+palette = gbc
+keys.a = A
+
+[rom 16BF]
+speed = 2
+";
+
+    fn rom(title: &[u8], checksum: u16) -> Vec<u8> {
+        let mut rom = vec![0; 0x150];
+        rom[0x134..0x134 + title.len()].copy_from_slice(title);
+        rom[0x14E..0x150].copy_from_slice(&checksum.to_be_bytes());
+
+        rom
+    }
+
+    #[test]
+    fn it_applies_rom_overrides() {
+        let config = Config::parse(CONFIG).unwrap();
+
+        let tetris = config.settings(&rom(b"TETRIS", 0x16BF));
+        assert_eq!(tetris.get("palette"), Some("gbc"));
+        assert_eq!(tetris.get("keys.a"), Some("A"));
+        assert_eq!(tetris.parse::<u32>("speed"), Ok(Some(2)));
+        assert_eq!(tetris.parse::<usize>("scale"), Ok(Some(4)));
+
+        let other = config.settings(&rom(b"ZELDA", 0x1234));
+        assert_eq!(other.get("palette"), Some("classic"));
+        assert_eq!(other.get("keys.a"), Some("Z"));
+        assert_eq!(other.get("speed"), None);
+    }
+
+    #[test]
+    fn it_parses_values() {
+        let mut settings = Settings::default();
+        settings.set("blend", "on");
+        settings.set("scale", "big");
+
+        assert_eq!(settings.flag("blend"), Ok(Some(true)));
+        assert_eq!(settings.flag("missing"), Ok(None));
+        assert!(settings.parse::<usize>("scale").is_err());
+        assert!(settings.flag("scale").is_err());
+    }
+}
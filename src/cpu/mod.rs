@@ -1,12 +1,25 @@
 use crate::{
-    instr::{Instr, InstrKind},
+    bus::Bus,
+    header,
+    instr::{Instr, InstrKind, CB_OPCODES, OPCODES},
     microcode,
-    mmu::Mmu,
-    registers::{Reg16Kind, Registers},
+    mmu::{self, Mmu},
+    registers::{FlagsRegister, Reg16Kind, Registers},
+    state::{Reader, Writer},
+    symbols::Symbols,
 };
 
+const STATE_MAGIC: &[u8; 4] = b"RBST";
+const STATE_VERSION: u8 = 5;
+
 pub struct Pc(u16);
 
+impl Default for Pc {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Pc {
     pub fn new() -> Self {
         Self(0)
@@ -43,12 +56,15 @@ impl Clock {
     }
 }
 
-pub struct Cpu {
+pub struct Cpu<B: Bus = Mmu> {
     pub registers: Registers,
     pub pc: Pc,
     pub sp: u16,
-    pub bus: Mmu,
+    pub bus: B,
     pub clock: Clock,
+    pub symbols: Symbols,
+    /// Log every executed instruction to stdout.
+    pub trace: bool,
     state: State,
 }
 
@@ -57,72 +73,206 @@ impl Cpu {
         boot_rom_buffer: Vec<u8>,
         game_rom_buffer: Vec<u8>,
         registers: Option<Registers>,
-    ) -> Self {
+    ) -> Result<Self, String> {
+        let boot_rom = mmu::boot_rom(boot_rom_buffer)?;
+
+        Ok(Self::with_bus(
+            Mmu::with_boot_rom(boot_rom, game_rom_buffer),
+            registers,
+        ))
+    }
+
+    /// Starts at 0x0100 in the state the DMG or CGB boot ROM hands over in,
+    /// for running without a boot ROM image.
+    pub fn post_boot(game_rom_buffer: Vec<u8>) -> Self {
+        let mut registers = Registers::new();
+
+        if header::cgb(&game_rom_buffer) {
+            registers.a = 0x11;
+            registers.f = FlagsRegister::from(0x80);
+            registers.set_bc(0x0000);
+            registers.set_de(0xFF56);
+            registers.set_hl(0x000D);
+        } else {
+            registers.a = 0x01;
+            registers.f = FlagsRegister::from(0xB0);
+            registers.set_bc(0x0013);
+            registers.set_de(0x00D8);
+            registers.set_hl(0x014D);
+        }
+
+        let mut cpu = Self::with_bus(
+            Mmu::with_boot_rom(None, game_rom_buffer),
+            Some(registers),
+        );
+
+        cpu.pc.set(0x0100);
+        cpu.sp = 0xFFFE;
+        cpu.bus.post_boot();
+
+        cpu
+    }
+
+    pub fn save_state(&self) -> Vec<u8> {
+        let mut w = Writer::new();
+
+        w.bytes(STATE_MAGIC);
+        w.u8(STATE_VERSION);
+        w.u32(self.bus.rom_checksum());
+
+        let r = &self.registers;
+
+        for reg in &[r.a, r.b, r.c, r.d, r.e, u8::from(r.f), r.h, r.l] {
+            w.u8(*reg);
+        }
+
+        w.u16(self.pc.get());
+        w.u16(self.sp);
+        w.u32(self.clock.0);
+        w.bool(self.state == State::Halted);
+
+        self.bus.save(&mut w);
+
+        w.finish()
+    }
+
+    /// Restores a state saved with the same ROM loaded.
+    pub fn load_state(&mut self, data: &[u8]) -> Result<(), String> {
+        let mut r = Reader::new(data);
+
+        if r.bytes(4)? != STATE_MAGIC || r.u8()? != STATE_VERSION {
+            return Err("not a save state of this version".to_string());
+        }
+
+        if r.u32()? != self.bus.rom_checksum() {
+            return Err("save state is for a different ROM".to_string());
+        }
+
+        let mut registers = Registers::new();
+
+        registers.a = r.u8()?;
+        registers.b = r.u8()?;
+        registers.c = r.u8()?;
+        registers.d = r.u8()?;
+        registers.e = r.u8()?;
+        registers.f = FlagsRegister::from(r.u8()?);
+        registers.h = r.u8()?;
+        registers.l = r.u8()?;
+
+        let pc = r.u16()?;
+        let sp = r.u16()?;
+        let clock = r.u32()?;
+        let halted = r.bool()?;
+
+        // Loaded into a copy, so a bad state leaves the machine untouched
+        let mut bus = self.bus.clone();
+        bus.load(&mut r)?;
+
+        if !r.is_empty() {
+            return Err("trailing data in save state".to_string());
+        }
+
+        self.bus = bus;
+        self.registers = registers;
+        self.pc.set(pc);
+        self.sp = sp;
+        self.clock = Clock(clock);
+        self.state = if halted {
+            State::Halted
+        } else {
+            State::Running
+        };
+
+        Ok(())
+    }
+}
+
+impl<B: Bus> Cpu<B> {
+    pub fn with_bus(bus: B, registers: Option<Registers>) -> Self {
         Self {
-            registers: registers.unwrap_or(Registers::new()),
+            registers: registers.unwrap_or_default(),
             pc: Pc::new(),
             sp: 0,
-            bus: Mmu::new(boot_rom_buffer, game_rom_buffer),
+            bus,
             state: State::Running,
             clock: Clock(0),
+            symbols: Symbols::new(),
+            trace: false,
         }
     }
 
-    pub fn step(&mut self) {
-        let instruction = self.bus.read_byte(self.pc.get());
+    pub fn is_halted(&self) -> bool {
+        self.state == State::Halted
+    }
+
+    /// Executes one instruction and returns the ticks it took, including
+    /// any DMA it waited on. Fails, with nothing executed, on instructions
+    /// that aren't implemented yet.
+    pub fn step(&mut self) -> Result<u32, String> {
+        // TODO: Leave HALT once interrupts are implemented
+        if self.state == State::Halted {
+            self.clock.add(4);
+            self.bus.tick(4);
+
+            return Ok(4);
+        }
 
-        let instruction = if instruction == 0xCB {
-            let instruction =
-                0xCB00 | self.bus.read_byte(self.pc.peek()) as u16;
+        let pc = self.pc.get();
+        let opcode = self.bus.fetch_byte(self.pc.get());
 
-            Instr::from(instruction)
+        let instruction = if opcode == 0xCB {
+            CB_OPCODES[self.bus.fetch_byte(self.pc.peek()) as usize]
         } else {
-            Instr::from(instruction)
+            OPCODES[opcode as usize]
         };
 
-        let res = self.execute(instruction);
+        let res = self.execute(instruction)?;
 
-        let res = res.unwrap();
+        self.bus.tick(res.ticks);
 
-        self.bus.gpu.step(res.ticks);
+        let stall = self.bus.take_stall();
+        self.clock = Clock(self.clock.0.wrapping_add(stall));
 
-        println!("{}, {}, {}", res.instr, res.ticks, self.clock.0);
-    }
+        if self.trace {
+            let mut instr = res.instr;
 
-    fn execute(&mut self, instr: Instr) -> Option<microcode::ExecRes> {
-        use microcode::*;
+            if let Some(trace) = res.trace {
+                instr.trace(trace);
+            }
 
-        if self.state == State::Halted {
-            return None;
+            println!(
+                "{:<20} {}, {}, {}",
+                self.symbols.format_address(&self.bus, pc),
+                instr,
+                res.ticks,
+                self.clock.0
+            );
         }
 
-        match instr.id {
+        Ok(res.ticks as u32 + stall)
+    }
+
+    fn execute(&mut self, instr: Instr) -> Result<microcode::ExecRes, String> {
+        use microcode::*;
+
+        let res = match instr.id {
             InstrKind::And => And(self).run(instr),
             InstrKind::Add => Add(self).run(instr),
 
             InstrKind::Adc => Adc(self).run(instr),
 
-            InstrKind::Nop => {
-                self.pc.add(1);
-                self.clock.add(4);
-
-                Some(ExecRes {
-                    ticks: 4,
-                    length: 1,
-                    instr,
-                    trace: None,
-                })
-            }
+            InstrKind::Nop => Some(next(self, instr)),
 
             InstrKind::Halt => {
                 self.state = State::Halted;
 
-                Some(ExecRes {
-                    ticks: 4,
-                    length: 1,
-                    instr,
-                    trace: None,
-                })
+                Some(next(self, instr))
+            }
+
+            InstrKind::Stop => {
+                self.bus.stop();
+
+                Some(next(self, instr))
             }
 
             InstrKind::AddHl => AddHl(self).run(instr),
@@ -163,10 +313,16 @@ impl Cpu {
 
             InstrKind::Bit => Bit(self).run(instr),
 
-            _ => {
-                panic!("{}: UNIMPLEMENTED", instr);
-            }
-        }
+            _ => None,
+        };
+
+        res.ok_or_else(|| {
+            format!(
+                "unimplemented instruction {} at {:04X}",
+                instr.tag,
+                self.pc.get()
+            )
+        })
     }
 
     pub fn read_at_reg_16(&self, reg: &Reg16Kind) -> u8 {
@@ -176,13 +332,71 @@ impl Cpu {
     }
 
     pub fn read_next_byte(&self) -> u8 {
-        self.bus.read_byte(self.pc.peek())
+        self.bus.fetch_byte(self.pc.peek())
     }
 
     pub fn read_next_word(&self) -> u16 {
-        let lo = self.bus.read_byte(self.pc.get() + 1) as u16;
-        let hi = self.bus.read_byte(self.pc.get() + 2) as u16;
+        let lo = self.bus.fetch_byte(self.pc.get() + 1) as u16;
+        let hi = self.bus.fetch_byte(self.pc.get() + 2) as u16;
 
         (hi << 8) | lo
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mmu::ROM_BANK_0_SIZE;
+
+    fn cpu(program: &[u8]) -> Cpu {
+        let mut rom = vec![0; ROM_BANK_0_SIZE * 2];
+        rom[0x100..0x100 + program.len()].copy_from_slice(program);
+
+        Cpu::post_boot(rom)
+    }
+
+    #[test]
+    fn it_takes_lengths_and_cycles_from_the_tables() {
+        #[rustfmt::skip]
+        let mut cpu = cpu(&[
+            0xEA, 0x00, 0xC0, // LD (0xC000), A
+            0xFE, 0x01,       // CP A, 1
+            0x20, 0x02,       // JR NZ, +2
+            0x28, 0x02,       // JR Z, +2
+            0x00, 0x00,       // NOP, NOP
+            0x40,             // LD B, B
+        ]);
+
+        assert_eq!(cpu.step(), Ok(16));
+        assert_eq!(cpu.bus.peek_byte(0xC000), Some(0x01));
+
+        assert_eq!(cpu.step(), Ok(8));
+        assert!(cpu.registers.f.zero);
+
+        // Not taken, then taken
+        assert_eq!(cpu.step(), Ok(8));
+        assert_eq!(cpu.pc.get(), 0x107);
+        assert_eq!(cpu.step(), Ok(12));
+        assert_eq!(cpu.pc.get(), 0x10B);
+
+        assert_eq!(cpu.step(), Ok(4));
+        assert_eq!(cpu.pc.get(), 0x10C);
+    }
+
+    #[test]
+    fn it_leaves_the_machine_untouched_on_bad_states() {
+        let mut cpu = cpu(&[]);
+        let mut state = cpu.save_state();
+        state.push(0);
+
+        cpu.bus.write_byte(0xC000, 0x42);
+        let before = cpu.save_state();
+
+        assert_eq!(
+            cpu.load_state(&state),
+            Err("trailing data in save state".to_string())
+        );
+        assert_eq!(cpu.save_state(), before);
+        assert_eq!(cpu.bus.peek_byte(0xC000), Some(0x42));
+    }
+}
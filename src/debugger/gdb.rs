@@ -0,0 +1,750 @@
+use crate::{
+    debugger::{Debugger, StopReason},
+    mmu::{WatchKind, Watchpoint},
+    registers::FlagsRegister,
+    Cpu,
+};
+use std::{
+    io::{self, Read, Write},
+    net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs},
+};
+
+const TARGET_XML: &str = include_str!("target.xml");
+
+const PACKET_SIZE: usize = 0x1000;
+
+// a, f, b, c, d, e, h, l, sp, pc; in target.xml order.
+const REGISTER_COUNT: usize = 10;
+
+// Instructions executed between polls for a client interrupt (0x03).
+const INTERRUPT_POLL: u32 = 1024;
+
+pub struct GdbStub {
+    listener: TcpListener,
+}
+
+impl GdbStub {
+    pub fn bind<A: ToSocketAddrs>(addr: A) -> io::Result<Self> {
+        Ok(Self {
+            listener: TcpListener::bind(addr)?,
+        })
+    }
+
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.listener.local_addr()
+    }
+
+    /// Accepts a single client and serves it until it detaches or kills the
+    /// target.
+    pub fn serve(
+        &self,
+        cpu: &mut Cpu,
+        debugger: &mut Debugger,
+    ) -> io::Result<()> {
+        let (stream, _) = self.listener.accept()?;
+
+        stream.set_nodelay(true)?;
+
+        Session::new(stream).run(cpu, debugger)
+    }
+}
+
+enum Reply {
+    Packet(String),
+    Close,
+}
+
+struct Session {
+    stream: TcpStream,
+    buffer: Vec<u8>,
+    pos: usize,
+    no_ack: bool,
+    last_stop: StopReason,
+}
+
+impl Session {
+    fn new(stream: TcpStream) -> Self {
+        Self {
+            stream,
+            buffer: Vec::new(),
+            pos: 0,
+            no_ack: false,
+            last_stop: StopReason::Step,
+        }
+    }
+
+    fn run(mut self, cpu: &mut Cpu, debugger: &mut Debugger) -> io::Result<()> {
+        while let Some(packet) = self.read_packet()? {
+            match self.handle(&packet, cpu, debugger)? {
+                Reply::Packet(reply) => self.write_packet(&reply)?,
+                Reply::Close => break,
+            }
+        }
+
+        Ok(())
+    }
+
+    fn next_byte(&mut self) -> io::Result<Option<u8>> {
+        if self.pos == self.buffer.len() {
+            let mut chunk = [0; 1024];
+            let read = self.stream.read(&mut chunk)?;
+
+            if read == 0 {
+                return Ok(None);
+            }
+
+            self.buffer.clear();
+            self.buffer.extend_from_slice(&chunk[..read]);
+            self.pos = 0;
+        }
+
+        let byte = self.buffer[self.pos];
+        self.pos += 1;
+
+        Ok(Some(byte))
+    }
+
+    fn read_packet(&mut self) -> io::Result<Option<String>> {
+        loop {
+            // Acks and interrupts outside of a running target are dropped.
+            loop {
+                match self.next_byte()? {
+                    Some(b'$') => break,
+                    Some(_) => continue,
+                    None => return Ok(None),
+                }
+            }
+
+            let mut data = Vec::new();
+            let mut sum: u8 = 0;
+
+            loop {
+                match self.next_byte()? {
+                    Some(b'#') => break,
+                    Some(byte) => {
+                        sum = sum.wrapping_add(byte);
+                        data.push(byte);
+                    }
+                    None => return Ok(None),
+                }
+            }
+
+            let mut checksum = [0; 2];
+
+            for c in checksum.iter_mut() {
+                match self.next_byte()? {
+                    Some(byte) => *c = byte,
+                    None => return Ok(None),
+                }
+            }
+
+            let packet = String::from_utf8_lossy(&data).into_owned();
+
+            if self.no_ack {
+                return Ok(Some(packet));
+            }
+
+            let expected = std::str::from_utf8(&checksum)
+                .ok()
+                .and_then(|c| u8::from_str_radix(c, 16).ok());
+
+            if expected == Some(sum) {
+                self.stream.write_all(b"+")?;
+
+                return Ok(Some(packet));
+            }
+
+            self.stream.write_all(b"-")?;
+        }
+    }
+
+    fn write_packet(&mut self, data: &str) -> io::Result<()> {
+        let sum = data.bytes().fold(0u8, |sum, b| sum.wrapping_add(b));
+
+        write!(self.stream, "${}#{:02x}", data, sum)?;
+
+        self.stream.flush()
+    }
+
+    fn handle(
+        &mut self,
+        packet: &str,
+        cpu: &mut Cpu,
+        debugger: &mut Debugger,
+    ) -> io::Result<Reply> {
+        let args = packet.get(1..).unwrap_or("");
+
+        let reply = match packet.as_bytes().first() {
+            Some(b'?') => Some(stop_reply(self.last_stop)),
+            Some(b'g') => Some(read_registers(cpu)),
+            Some(b'G') => write_registers(cpu, args),
+            Some(b'p') => read_register(cpu, args),
+            Some(b'P') => write_register(cpu, args),
+            Some(b'm') => read_memory(cpu, args),
+            Some(b'M') => write_memory(cpu, args),
+            Some(b's') => {
+                resume_at(cpu, args);
+                self.last_stop = debugger.step(cpu);
+
+                Some(stop_reply(self.last_stop))
+            }
+            Some(b'c') => {
+                resume_at(cpu, args);
+                self.last_stop = self.resume(cpu, debugger)?;
+
+                Some(stop_reply(self.last_stop))
+            }
+            Some(b'Z') | Some(b'z') => breakpoint(cpu, debugger, packet),
+            Some(b'H') => Some("OK".to_string()),
+            Some(b'k') => return Ok(Reply::Close),
+            Some(b'D') => {
+                self.write_packet("OK")?;
+
+                return Ok(Reply::Close);
+            }
+            Some(b'q') if packet.starts_with("qRcmd,") => {
+                self.monitor(args, cpu, debugger)?
+            }
+            Some(b'q') | Some(b'Q') => Some(self.query(packet)),
+            _ => Some(String::new()),
+        };
+
+        Ok(Reply::Packet(reply.unwrap_or_else(|| "E01".to_string())))
+    }
+
+    fn query(&mut self, packet: &str) -> String {
+        const FEATURES: &str = "qXfer:features:read:target.xml:";
+
+        if packet.starts_with("qSupported") {
+            format!(
+                "PacketSize={:x};qXfer:features:read+;QStartNoAckMode+;\
+                 swbreak+;hwbreak+",
+                PACKET_SIZE
+            )
+        } else if packet == "QStartNoAckMode" {
+            self.no_ack = true;
+
+            "OK".to_string()
+        } else if let Some(annex) = packet.strip_prefix(FEATURES) {
+            xfer(TARGET_XML, annex).unwrap_or_else(|| "E01".to_string())
+        } else {
+            match packet {
+                "qAttached" => "1",
+                "qC" => "QC1",
+                "qfThreadInfo" => "m1",
+                "qsThreadInfo" => "l",
+                _ => "",
+            }
+            .to_string()
+        }
+    }
+
+    /// `monitor <command>` runs a debugger command; its output goes back as
+    /// console packets.
+    fn monitor(
+        &mut self,
+        args: &str,
+        cpu: &mut Cpu,
+        debugger: &mut Debugger,
+    ) -> io::Result<Option<String>> {
+        let command = match decode_hex(&args["Rcmd,".len()..]) {
+            Some(command) => String::from_utf8_lossy(&command).into_owned(),
+            None => return Ok(None),
+        };
+
+        let output = debugger.command(cpu, &command) + "\n";
+        let output: String =
+            output.bytes().map(|b| format!("{:02x}", b)).collect();
+
+        self.write_packet(&format!("O{}", output))?;
+
+        Ok(Some("OK".to_string()))
+    }
+
+    fn resume(
+        &mut self,
+        cpu: &mut Cpu,
+        debugger: &mut Debugger,
+    ) -> io::Result<StopReason> {
+        // gdb sends nothing but 0x03 while the target runs.
+        if self.buffer[self.pos..].contains(&0x03) {
+            self.pos = self.buffer.len();
+
+            return Ok(StopReason::Interrupted);
+        }
+
+        self.pos = self.buffer.len();
+        self.stream.set_nonblocking(true)?;
+
+        let stream = &self.stream;
+        let mut steps: u32 = 0;
+
+        let reason = debugger.resume(cpu, || {
+            steps = steps.wrapping_add(1);
+
+            if !steps.is_multiple_of(INTERRUPT_POLL) {
+                return false;
+            }
+
+            let mut byte = [0];
+
+            match (&*stream).read(&mut byte) {
+                Ok(0) => true,
+                Ok(_) => byte[0] == 0x03,
+                Err(_) => false,
+            }
+        });
+
+        self.stream.set_nonblocking(false)?;
+
+        Ok(reason)
+    }
+}
+
+fn stop_reply(reason: StopReason) -> String {
+    match reason {
+        StopReason::Watchpoint(watchpoint, address) => {
+            let kind = match watchpoint.kind {
+                WatchKind::Write => "watch",
+                WatchKind::Read => "rwatch",
+                WatchKind::Access => "awatch",
+            };
+
+            format!("T05{}:{:04x};", kind, address)
+        }
+        StopReason::Breakpoint(_) => "T05swbreak:;".to_string(),
+        StopReason::Interrupted => "S02".to_string(),
+        // SIGILL
+        StopReason::Unimplemented => "S04".to_string(),
+        StopReason::Step | StopReason::Halted => "S05".to_string(),
+    }
+}
+
+fn resume_at(cpu: &mut Cpu, args: &str) {
+    if let Ok(address) = u16::from_str_radix(args, 16) {
+        cpu.pc.set(address);
+    }
+}
+
+fn register(cpu: &Cpu, n: usize) -> u16 {
+    let r = &cpu.registers;
+
+    match n {
+        0 => r.a as u16,
+        1 => u8::from(r.f) as u16,
+        2 => r.b as u16,
+        3 => r.c as u16,
+        4 => r.d as u16,
+        5 => r.e as u16,
+        6 => r.h as u16,
+        7 => r.l as u16,
+        8 => cpu.sp,
+        _ => cpu.pc.get(),
+    }
+}
+
+fn set_register(cpu: &mut Cpu, n: usize, val: u16) {
+    let r = &mut cpu.registers;
+
+    match n {
+        0 => r.a = val as u8,
+        1 => r.f = FlagsRegister::from(val as u8),
+        2 => r.b = val as u8,
+        3 => r.c = val as u8,
+        4 => r.d = val as u8,
+        5 => r.e = val as u8,
+        6 => r.h = val as u8,
+        7 => r.l = val as u8,
+        8 => cpu.sp = val,
+        _ => cpu.pc.set(val),
+    }
+}
+
+fn register_width(n: usize) -> usize {
+    if n < 8 {
+        1
+    } else {
+        2
+    }
+}
+
+fn encode_register(n: usize, val: u16) -> String {
+    if register_width(n) == 1 {
+        format!("{:02x}", val as u8)
+    } else {
+        format!("{:02x}{:02x}", val as u8, val >> 8)
+    }
+}
+
+fn decode_hex(hex: &str) -> Option<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) {
+        return None;
+    }
+
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+fn read_registers(cpu: &Cpu) -> String {
+    (0..REGISTER_COUNT)
+        .map(|n| encode_register(n, register(cpu, n)))
+        .collect()
+}
+
+fn write_registers(cpu: &mut Cpu, args: &str) -> Option<String> {
+    let bytes = decode_hex(args)?;
+    let mut bytes = bytes.iter();
+
+    for n in 0..REGISTER_COUNT {
+        let lo = *bytes.next()? as u16;
+        let hi = match register_width(n) {
+            1 => 0,
+            _ => *bytes.next()? as u16,
+        };
+
+        set_register(cpu, n, (hi << 8) | lo);
+    }
+
+    Some("OK".to_string())
+}
+
+fn read_register(cpu: &Cpu, args: &str) -> Option<String> {
+    let n = usize::from_str_radix(args, 16).ok()?;
+
+    if n >= REGISTER_COUNT {
+        return None;
+    }
+
+    Some(encode_register(n, register(cpu, n)))
+}
+
+fn write_register(cpu: &mut Cpu, args: &str) -> Option<String> {
+    let mut parts = args.splitn(2, '=');
+
+    let n = usize::from_str_radix(parts.next()?, 16).ok()?;
+    let bytes = decode_hex(parts.next()?)?;
+
+    if n >= REGISTER_COUNT || bytes.len() != register_width(n) {
+        return None;
+    }
+
+    let val = bytes
+        .iter()
+        .rev()
+        .fold(0u16, |val, byte| (val << 8) | *byte as u16);
+
+    set_register(cpu, n, val);
+
+    Some("OK".to_string())
+}
+
+fn address_and_length(args: &str) -> Option<(u16, usize)> {
+    let mut parts = args.splitn(2, ',');
+
+    let address = u16::from_str_radix(parts.next()?, 16).ok()?;
+    let length = usize::from_str_radix(parts.next()?, 16).ok()?;
+
+    Some((address, length))
+}
+
+fn read_memory(cpu: &Cpu, args: &str) -> Option<String> {
+    let (address, length) = address_and_length(args)?;
+
+    // A short read tells gdb where accessible memory ends.
+    let data: String = (0..length.min(PACKET_SIZE / 2))
+        .map_while(|i| cpu.bus.peek_byte(address.wrapping_add(i as u16)))
+        .map(|byte| format!("{:02x}", byte))
+        .collect();
+
+    if data.is_empty() && length > 0 {
+        return Some("E14".to_string());
+    }
+
+    Some(data)
+}
+
+fn write_memory(cpu: &mut Cpu, args: &str) -> Option<String> {
+    let mut parts = args.splitn(2, ':');
+
+    let (address, length) = address_and_length(parts.next()?)?;
+    let bytes = decode_hex(parts.next()?)?;
+
+    if bytes.len() != length {
+        return None;
+    }
+
+    // Pokes rather than writes, so IO side effects and watchpoints don't fire
+    for (i, byte) in bytes.iter().enumerate() {
+        if !cpu.bus.poke_byte(address.wrapping_add(i as u16), *byte) {
+            return Some("E14".to_string());
+        }
+    }
+
+    Some("OK".to_string())
+}
+
+fn breakpoint(
+    cpu: &mut Cpu,
+    debugger: &mut Debugger,
+    packet: &str,
+) -> Option<String> {
+    let insert = packet.starts_with('Z');
+    let mut parts = packet[1..].split(',');
+
+    let kind = parts.next()?;
+    let address = u16::from_str_radix(parts.next()?, 16).ok()?;
+    let length = parts.next()?.split(';').next()?;
+    let length = u16::from_str_radix(length, 16).ok()?;
+
+    let kind = match kind {
+        "0" | "1" => {
+            if insert {
+                debugger.add_breakpoint(address);
+            } else {
+                debugger.remove_breakpoint(address);
+            }
+
+            return Some("OK".to_string());
+        }
+        "2" => WatchKind::Write,
+        "3" => WatchKind::Read,
+        "4" => WatchKind::Access,
+        _ => return Some(String::new()),
+    };
+
+    let watchpoint = Watchpoint {
+        kind,
+        address,
+        length,
+    };
+
+    if insert {
+        cpu.bus.add_watchpoint(watchpoint);
+    } else {
+        cpu.bus.remove_watchpoint(watchpoint);
+    }
+
+    Some("OK".to_string())
+}
+
+fn xfer(data: &str, args: &str) -> Option<String> {
+    let mut parts = args.splitn(2, ',');
+
+    let offset = usize::from_str_radix(parts.next()?, 16).ok()?;
+    let length = usize::from_str_radix(parts.next()?, 16).ok()?;
+
+    let rest = data.get(offset.min(data.len())..)?;
+    let chunk = rest.get(..length.min(rest.len()))?;
+
+    let more = if chunk.len() < rest.len() { 'm' } else { 'l' };
+
+    Some(format!("{}{}", more, chunk))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mmu::{BOOT_ROM_SIZE, ROM_BANK_0_SIZE};
+    use std::thread;
+
+    #[rustfmt::skip]
+    const PROGRAM: [u8; 11] = [
+        0x06, 0x12,       // LD B, 0x12
+        0x0E, 0x34,       // LD C, 0x34
+        0x21, 0x00, 0xC0, // LD HL, 0xC000
+        0x70,             // LD (HL), B
+        0x00,             // NOP
+        0x18, 0xFE,       // JR -2
+    ];
+
+    struct Client(TcpStream);
+
+    impl Client {
+        fn send(&mut self, data: &str) -> String {
+            let sum = data.bytes().fold(0u8, |sum, b| sum.wrapping_add(b));
+
+            write!(self.0, "${}#{:02x}", data, sum).unwrap();
+
+            self.ack();
+            self.read()
+        }
+
+        fn ack(&mut self) {
+            let mut byte = [0];
+
+            self.0.read_exact(&mut byte).unwrap();
+
+            assert_eq!(byte[0], b'+');
+        }
+
+        fn read(&mut self) -> String {
+            let mut byte = [0];
+            let mut data = Vec::new();
+
+            self.0.read_exact(&mut byte).unwrap();
+            assert_eq!(byte[0], b'$');
+
+            loop {
+                self.0.read_exact(&mut byte).unwrap();
+
+                if byte[0] == b'#' {
+                    break;
+                }
+
+                data.push(byte[0]);
+            }
+
+            let mut checksum = [0; 2];
+            self.0.read_exact(&mut checksum).unwrap();
+            self.0.write_all(b"+").unwrap();
+
+            String::from_utf8(data).unwrap()
+        }
+    }
+
+    fn client() -> (Client, thread::JoinHandle<()>) {
+        let stub = GdbStub::bind("127.0.0.1:0").unwrap();
+        let addr = stub.local_addr().unwrap();
+
+        let server = thread::spawn(move || {
+            let mut boot_rom = vec![0; BOOT_ROM_SIZE];
+            boot_rom[..PROGRAM.len()].copy_from_slice(&PROGRAM);
+
+            let mut cpu =
+                Cpu::new(boot_rom, vec![0; ROM_BANK_0_SIZE], None).unwrap();
+
+            stub.serve(&mut cpu, &mut Debugger::new()).unwrap();
+        });
+
+        (Client(TcpStream::connect(addr).unwrap()), server)
+    }
+
+    fn detach(mut client: Client, server: thread::JoinHandle<()>) {
+        assert_eq!(client.send("D"), "OK");
+
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn it_reads_and_writes_registers() {
+        let (mut client, server) = client();
+
+        assert_eq!(client.send("g"), "000000000000000000000000");
+
+        assert_eq!(client.send("G0100020304050607fffe3412"), "OK");
+        assert_eq!(client.send("g"), "0100020304050607fffe3412");
+        assert_eq!(client.send("p8"), "fffe");
+
+        assert_eq!(client.send("P9=0001"), "OK");
+        assert_eq!(client.send("p9"), "0001");
+
+        detach(client, server);
+    }
+
+    #[test]
+    fn it_steps_and_stops_at_breakpoints() {
+        let (mut client, server) = client();
+
+        assert_eq!(client.send("s"), "S05");
+        assert_eq!(client.send("p2"), "12");
+        assert_eq!(client.send("p9"), "0200");
+
+        assert_eq!(client.send("Z0,7,1"), "OK");
+        assert_eq!(client.send("c"), "T05swbreak:;");
+        assert_eq!(client.send("p9"), "0700");
+        assert_eq!(client.send("g"), "000012340000c00000000700");
+
+        assert_eq!(client.send("z0,7,1"), "OK");
+
+        detach(client, server);
+    }
+
+    #[test]
+    fn it_stops_at_watchpoints() {
+        let (mut client, server) = client();
+
+        assert_eq!(client.send("Z2,c000,1"), "OK");
+        assert_eq!(client.send("c"), "T05watch:c000;");
+        assert_eq!(client.send("mc000,1"), "12");
+
+        // Fetching the next opcode isn't a read
+        assert_eq!(client.send("Z3,8,1"), "OK");
+        assert_eq!(client.send("s"), "S05");
+        assert_eq!(client.send("p9"), "0900");
+
+        detach(client, server);
+    }
+
+    #[test]
+    fn it_reads_and_writes_memory() {
+        let (mut client, server) = client();
+
+        assert_eq!(client.send("m0,2"), "0612");
+        assert_eq!(client.send("Mc001,2:abcd"), "OK");
+        assert_eq!(client.send("mc000,3"), "00abcd");
+        assert_eq!(client.send("mff44,1"), "00");
+
+        // Unmapped, and IO registers only debuggers could write to
+        assert_eq!(client.send("mfea0,1"), "E14");
+        assert_eq!(client.send("mfe9f,2"), "00");
+        assert_eq!(client.send("Mff46,1:c0"), "E14");
+        assert_eq!(client.send("M0,1:00"), "E14");
+
+        detach(client, server);
+    }
+
+    #[test]
+    fn it_interrupts_a_running_target() {
+        let (mut client, server) = client();
+
+        write!(client.0, "$c#63").unwrap();
+        client.ack();
+
+        thread::sleep(std::time::Duration::from_millis(50));
+        client.0.write_all(&[0x03]).unwrap();
+
+        assert_eq!(client.read(), "S02");
+
+        detach(client, server);
+    }
+
+    #[test]
+    fn it_runs_monitor_commands() {
+        let (mut client, server) = client();
+
+        let command: String =
+            "break 7".bytes().map(|b| format!("{:02x}", b)).collect();
+
+        let output = client.send(&format!("qRcmd,{}", command));
+        let output = decode_hex(&output[1..]).unwrap();
+
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "Breakpoint at 0x0007\n"
+        );
+        assert_eq!(client.read(), "OK");
+
+        assert_eq!(client.send("c"), "T05swbreak:;");
+        assert_eq!(client.send("p9"), "0700");
+
+        detach(client, server);
+    }
+
+    #[test]
+    fn it_serves_the_target_description() {
+        let (mut client, server) = client();
+
+        assert!(client.send("qSupported").contains("qXfer:features:read+"));
+
+        let xml = client.send("qXfer:features:read:target.xml:0,fff");
+        assert!(xml.starts_with("l<?xml"));
+        assert!(xml.contains("name=\"pc\""));
+
+        let chunk = client.send("qXfer:features:read:target.xml:0,10");
+        assert_eq!(chunk, format!("m{}", &TARGET_XML[..0x10]));
+
+        detach(client, server);
+    }
+}
@@ -0,0 +1,722 @@
+mod gdb;
+
+use crate::{
+    bus::Bus,
+    cheats::Cheat,
+    disasm,
+    io_registers::{self, Snapshot},
+    mmu::{WatchKind, Watchpoint},
+    oam,
+    search::{Comparison, Search, Width},
+    symbols::Location,
+    Cpu,
+};
+use std::{
+    collections::BTreeSet,
+    io::{self, BufRead, Write},
+};
+
+pub use gdb::GdbStub;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum StopReason {
+    Step,
+    Breakpoint(u16),
+    Watchpoint(Watchpoint, u16),
+    Halted,
+    Interrupted,
+    /// At an instruction the CPU can't execute yet.
+    Unimplemented,
+}
+
+/// A breakpoint without a bank fires whichever bank is mapped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+struct Breakpoint {
+    address: u16,
+    bank: Option<u16>,
+}
+
+pub struct Debugger {
+    breakpoints: BTreeSet<Breakpoint>,
+    search: Option<Search>,
+    // The IO registers as the current frame began, to show what changed
+    frame: u64,
+    io_registers: Option<Snapshot>,
+}
+
+impl Default for Debugger {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Debugger {
+    pub fn new() -> Self {
+        Self {
+            breakpoints: BTreeSet::new(),
+            search: None,
+            frame: 0,
+            io_registers: None,
+        }
+    }
+
+    pub fn add_breakpoint(&mut self, address: u16) {
+        self.breakpoints.insert(Breakpoint {
+            address,
+            bank: None,
+        });
+    }
+
+    pub fn remove_breakpoint(&mut self, address: u16) -> bool {
+        self.breakpoints.remove(&Breakpoint {
+            address,
+            bank: None,
+        })
+    }
+
+    /// Sets a breakpoint on a label, `bank:addr` or address. Returns `false`
+    /// when the target doesn't resolve.
+    pub fn break_on(&mut self, cpu: &Cpu, target: &str) -> bool {
+        match parse_target(cpu, target) {
+            Some((address, bank)) => {
+                self.breakpoints.insert(Breakpoint { address, bank });
+
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn is_breakpoint(&self, cpu: &Cpu, pc: u16) -> bool {
+        let from = Breakpoint {
+            address: pc,
+            bank: None,
+        };
+        let to = Breakpoint {
+            address: pc,
+            bank: Some(u16::MAX),
+        };
+
+        self.breakpoints.range(from..=to).any(|b| match b.bank {
+            Some(bank) => bank == cpu.bus.bank_at(pc),
+            None => true,
+        })
+    }
+
+    pub fn step(&mut self, cpu: &mut Cpu) -> StopReason {
+        if cpu.is_halted() {
+            return StopReason::Halted;
+        }
+
+        if cpu.step().is_err() {
+            return StopReason::Unimplemented;
+        }
+
+        if cpu.bus.gpu.frames() != self.frame {
+            self.frame = cpu.bus.gpu.frames();
+            self.io_registers = Some(Snapshot::new(&cpu.bus));
+        }
+
+        match cpu.bus.take_watch_hit() {
+            Some((watchpoint, address)) => {
+                StopReason::Watchpoint(watchpoint, address)
+            }
+            None => StopReason::Step,
+        }
+    }
+
+    /// Runs until a breakpoint, watchpoint or halt. Always executes at least
+    /// one instruction, so resuming from a breakpoint makes progress.
+    /// `interrupted` is polled after every instruction.
+    pub fn resume<F>(&mut self, cpu: &mut Cpu, mut interrupted: F) -> StopReason
+    where
+        F: FnMut() -> bool,
+    {
+        loop {
+            let reason = self.step(cpu);
+
+            if reason != StopReason::Step {
+                return reason;
+            }
+
+            let pc = cpu.pc.get();
+
+            if self.is_breakpoint(cpu, pc) {
+                return StopReason::Breakpoint(pc);
+            }
+
+            if interrupted() {
+                return StopReason::Interrupted;
+            }
+        }
+    }
+
+    /// Runs a single debugger command and returns its output. Shared by the
+    /// REPL and gdb's `monitor`.
+    pub fn command(&mut self, cpu: &mut Cpu, line: &str) -> String {
+        let words: Vec<&str> = line.split_whitespace().collect();
+
+        let arg = |n: usize| words.get(n).copied();
+        let count = |n: usize, default: usize| {
+            arg(n).and_then(|c| c.parse().ok()).unwrap_or(default)
+        };
+
+        match words.first().copied() {
+            Some("break") | Some("b") => match arg(1) {
+                Some(target) => self.break_at(cpu, target, true),
+                None => self.list_breakpoints(cpu),
+            },
+            Some("delete") | Some("d") => match arg(1) {
+                Some(target) => self.break_at(cpu, target, false),
+                None => {
+                    self.breakpoints.clear();
+
+                    "Deleted all breakpoints".to_string()
+                }
+            },
+            Some("watch") | Some("rwatch") | Some("awatch") => {
+                self.watch(cpu, words[0], arg(1))
+            }
+            Some("step") | Some("s") => {
+                let mut reason = StopReason::Step;
+
+                for _ in 0..count(1, 1) {
+                    reason = self.step(cpu);
+
+                    if reason != StopReason::Step {
+                        break;
+                    }
+                }
+
+                self.stopped(cpu, reason)
+            }
+            Some("continue") | Some("c") => {
+                let reason = self.resume(cpu, || false);
+
+                self.stopped(cpu, reason)
+            }
+            Some("regs") | Some("r") => registers(cpu),
+            Some("disas") => {
+                let address = match arg(1) {
+                    Some(target) => match parse_target(cpu, target) {
+                        Some((address, _)) => address,
+                        None => return unknown(target),
+                    },
+                    None => cpu.pc.get(),
+                };
+
+                disassembly(cpu, address, count(2, 10))
+            }
+            Some("sym") => match arg(1).and_then(|t| parse_target(cpu, t)) {
+                Some((address, bank)) => {
+                    let bank = bank.unwrap_or_else(|| cpu.bus.bank_at(address));
+
+                    match cpu.symbols.describe(Location { bank, address }) {
+                        Some(name) => name,
+                        None => format!("No symbol at 0x{:04X}", address),
+                    }
+                }
+                None => unknown(arg(1).unwrap_or("")),
+            },
+            Some("x") => match arg(1).and_then(|t| parse_target(cpu, t)) {
+                Some((address, _)) => dump(cpu, address, count(2, 16)),
+                None => unknown(arg(1).unwrap_or("")),
+            },
+            Some("cheat") => cheat(cpu, &words[1..]),
+            Some("search") => self.search(cpu, &words[1..]),
+            Some("io") => io_registers::dump(
+                &Snapshot::new(&cpu.bus),
+                self.io_registers.as_ref(),
+                false,
+            ),
+            Some("oam") => match arg(1).map(str::parse) {
+                None => oam::dump(&cpu.bus.gpu, None),
+                Some(Ok(line)) => oam::dump(&cpu.bus.gpu, Some(line)),
+                Some(Err(_)) => format!("Invalid line: {}", words[1]),
+            },
+            Some("help") | Some("h") => HELP.to_string(),
+            Some(other) => format!("Unknown command: {}", other),
+            None => String::new(),
+        }
+    }
+
+    /// Reads commands from stdin until `quit` or EOF.
+    pub fn repl(&mut self, cpu: &mut Cpu) -> io::Result<()> {
+        let stdin = io::stdin();
+        let mut stdout = io::stdout();
+
+        loop {
+            write!(stdout, "(rboy) ")?;
+            stdout.flush()?;
+
+            let mut line = String::new();
+
+            if stdin.lock().read_line(&mut line)? == 0 {
+                return Ok(());
+            }
+
+            match line.trim() {
+                "quit" | "q" => return Ok(()),
+                line => writeln!(stdout, "{}", self.command(cpu, line))?,
+            }
+        }
+    }
+
+    fn break_at(&mut self, cpu: &Cpu, target: &str, insert: bool) -> String {
+        let (address, bank) = match parse_target(cpu, target) {
+            Some(target) => target,
+            None => return unknown(target),
+        };
+
+        let breakpoint = Breakpoint { address, bank };
+        let name = describe(cpu, address, bank);
+
+        if insert {
+            self.breakpoints.insert(breakpoint);
+
+            format!("Breakpoint at {}", name)
+        } else if self.breakpoints.remove(&breakpoint) {
+            format!("Deleted breakpoint at {}", name)
+        } else {
+            format!("No breakpoint at {}", name)
+        }
+    }
+
+    fn list_breakpoints(&self, cpu: &Cpu) -> String {
+        if self.breakpoints.is_empty() {
+            return "No breakpoints".to_string();
+        }
+
+        self.breakpoints
+            .iter()
+            .map(|b| describe(cpu, b.address, b.bank))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    fn watch(
+        &mut self,
+        cpu: &mut Cpu,
+        kind: &str,
+        target: Option<&str>,
+    ) -> String {
+        let address = match target.and_then(|t| parse_target(cpu, t)) {
+            Some((address, _)) => address,
+            None => return unknown(target.unwrap_or("")),
+        };
+
+        let kind = match kind {
+            "rwatch" => WatchKind::Read,
+            "awatch" => WatchKind::Access,
+            _ => WatchKind::Write,
+        };
+
+        cpu.bus.add_watchpoint(Watchpoint {
+            kind,
+            address,
+            length: 1,
+        });
+
+        format!(
+            "Watchpoint ({:?}) at {}",
+            kind,
+            describe(cpu, address, None)
+        )
+    }
+
+    fn search(&mut self, cpu: &Cpu, args: &[&str]) -> String {
+        let op = args.first().copied().unwrap_or("list");
+
+        if op == "start" {
+            let width = match args.get(1).copied() {
+                None | Some("8") => Width::Byte,
+                Some("16") => Width::Word,
+                Some(other) => return format!("Invalid width: {}", other),
+            };
+
+            let search = Search::new(&cpu.bus, width);
+            let count = search.len();
+
+            self.search = Some(search);
+
+            return format!("{} candidates", count);
+        }
+
+        let search = match &mut self.search {
+            Some(search) => search,
+            None => {
+                return "No search; start one with search start".to_string()
+            }
+        };
+
+        if op == "list" {
+            let count = args.get(1).and_then(|n| n.parse().ok()).unwrap_or(20);
+
+            let digits = match search.width() {
+                Width::Byte => 2,
+                Width::Word => 4,
+            };
+
+            let mut lines: Vec<String> = search
+                .candidates()
+                .iter()
+                .take(count)
+                .map(|(address, value)| {
+                    format!(
+                        "{}: {:02$X}",
+                        describe(cpu, *address, None),
+                        value,
+                        digits
+                    )
+                })
+                .collect();
+
+            if search.len() > count {
+                lines.push(format!("({} more)", search.len() - count));
+            }
+
+            return lines.join("\n");
+        }
+
+        match Comparison::parse(op, args.get(1).copied()) {
+            Some(comparison) => {
+                format!("{} candidates", search.filter(&cpu.bus, comparison))
+            }
+            None => format!("Invalid search: {}", args.join(" ")),
+        }
+    }
+
+    fn stopped(&self, cpu: &Cpu, reason: StopReason) -> String {
+        let reason = match reason {
+            StopReason::Breakpoint(_) => "Breakpoint".to_string(),
+            StopReason::Watchpoint(watchpoint, address) => format!(
+                "Watchpoint ({:?}) at {}",
+                watchpoint.kind,
+                describe(cpu, address, None)
+            ),
+            StopReason::Halted => "Halted".to_string(),
+            StopReason::Interrupted => "Interrupted".to_string(),
+            StopReason::Unimplemented => {
+                "Unimplemented instruction".to_string()
+            }
+            StopReason::Step => return disassembly(cpu, cpu.pc.get(), 1),
+        };
+
+        format!("{}\n{}", reason, disassembly(cpu, cpu.pc.get(), 1))
+    }
+}
+
+const HELP: &str = "\
+break|b [label|addr]     set a breakpoint, or list them
+delete|d [label|addr]    delete a breakpoint, or all of them
+watch|rwatch|awatch addr set a write/read/access watchpoint
+step|s [n]               execute n instructions
+continue|c               run until a breakpoint or watchpoint
+regs|r                   show registers
+disas [label|addr] [n]   disassemble n instructions
+sym addr                 show the label for an address
+x addr [n]               dump n bytes of memory
+cheat [add code [name]]  list cheats, or add a Game Genie/GameShark code
+cheat on|off|delete n    enable, disable or delete cheat n
+search start [8|16]      start a RAM search over 8 or 16-bit values
+search eq|ne|gt|lt [n]   keep values equal, changed, greater or less than
+                         the last step, or equal to n
+search [list] [n]        show the first n candidates
+io                       show the IO registers, * marking changes this
+                         frame
+oam [line]               show the sprites, and those selected for a line
+quit|q                   leave the debugger";
+
+fn unknown(target: &str) -> String {
+    format!("No symbol or address: {}", target)
+}
+
+/// Accepts a label, `bank:addr`, or an address with optional `0x` or `$`.
+/// Only labels and `bank:addr` pin the breakpoint to a bank.
+fn parse_target(cpu: &Cpu, target: &str) -> Option<(u16, Option<u16>)> {
+    if let Some(location) = cpu.symbols.lookup(target) {
+        return Some((location.address, Some(location.bank)));
+    }
+
+    let hex = |s: &str| {
+        let s = s.trim_start_matches("0x").trim_start_matches('$');
+
+        u16::from_str_radix(s, 16).ok()
+    };
+
+    match target.find(':') {
+        Some(i) => Some((hex(&target[i + 1..])?, Some(hex(&target[..i])?))),
+        None => Some((hex(target)?, None)),
+    }
+}
+
+fn cheat(cpu: &mut Cpu, args: &[&str]) -> String {
+    let cheats = &mut cpu.bus.cheats;
+    let index = args.get(1).and_then(|n| n.parse().ok());
+
+    match (args.first().copied(), index) {
+        (None, _) if cheats.is_empty() => "No cheats".to_string(),
+        (None, _) => cheats
+            .iter()
+            .enumerate()
+            .map(|(i, cheat)| {
+                let state = if cheat.enabled { "on" } else { "off" };
+
+                format!("{}: [{}] {} {}", i, state, cheat.name, cheat.code)
+            })
+            .collect::<Vec<_>>()
+            .join("\n"),
+        (Some("add"), _) if args.len() > 1 => {
+            let name = if args.len() > 2 {
+                args[2..].join(" ")
+            } else {
+                args[1].to_string()
+            };
+
+            match Cheat::new(&name, args[1]) {
+                Ok(cheat) => {
+                    cheats.add(cheat);
+
+                    format!("Cheat {}: {}", cheats.iter().count() - 1, name)
+                }
+                Err(e) => e,
+            }
+        }
+        (Some(state @ "on"), Some(i)) | (Some(state @ "off"), Some(i)) => {
+            if cheats.set_enabled(i, state == "on") {
+                format!("Cheat {} {}", i, state)
+            } else {
+                format!("No cheat {}", i)
+            }
+        }
+        (Some("delete"), Some(i)) => match cheats.remove(i) {
+            Some(cheat) => format!("Deleted cheat {}: {}", i, cheat.name),
+            None => format!("No cheat {}", i),
+        },
+        _ => "Usage: cheat [add code [name] | on|off|delete n]".to_string(),
+    }
+}
+
+fn describe(cpu: &Cpu, address: u16, bank: Option<u16>) -> String {
+    let location = Location {
+        bank: bank.unwrap_or_else(|| cpu.bus.bank_at(address)),
+        address,
+    };
+
+    match (cpu.symbols.describe(location), bank) {
+        (Some(name), _) => format!("0x{:04X} <{}>", address, name),
+        (None, Some(bank)) => format!("{:02X}:{:04X}", bank, address),
+        (None, None) => format!("0x{:04X}", address),
+    }
+}
+
+fn registers(cpu: &Cpu) -> String {
+    let r = &cpu.registers;
+
+    format!(
+        "A: {:02X} F: {:02X} B: {:02X} C: {:02X} D: {:02X} E: {:02X} \
+         H: {:02X} L: {:02X} SP: {:04X} PC: {}",
+        r.a,
+        u8::from(r.f),
+        r.b,
+        r.c,
+        r.d,
+        r.e,
+        r.h,
+        r.l,
+        cpu.sp,
+        describe(cpu, cpu.pc.get(), None)
+    )
+}
+
+fn disassembly(cpu: &Cpu, mut address: u16, count: usize) -> String {
+    let mut lines = Vec::new();
+
+    for _ in 0..count {
+        let line = disasm::disassemble(&cpu.bus, &cpu.symbols, address);
+
+        address = address.wrapping_add(line.length());
+        lines.push(line.to_string());
+    }
+
+    lines.join("\n")
+}
+
+fn dump(cpu: &Cpu, address: u16, count: usize) -> String {
+    let mut lines = Vec::new();
+
+    for row in (0..count).step_by(16) {
+        let start = address.wrapping_add(row as u16);
+
+        let bytes: Vec<String> = (0..(count - row).min(16))
+            .map(|i| match cpu.bus.peek_byte(start.wrapping_add(i as u16)) {
+                Some(byte) => format!("{:02X}", byte),
+                None => "??".to_string(),
+            })
+            .collect();
+
+        lines.push(format!("0x{:04X}: {}", start, bytes.join(" ")));
+    }
+
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        mmu::{BOOT_ROM_SIZE, ROM_BANK_0_SIZE},
+        symbols::Symbols,
+    };
+
+    #[rustfmt::skip]
+    const PROGRAM: [u8; 6] = [
+        0x00,       // NOP
+        0x06, 0x12, // LD B, 0x12
+        0x00,       // Loop: NOP
+        0x18, 0xFD, // JR Loop
+    ];
+
+    fn cpu() -> Cpu {
+        let mut boot_rom = vec![0; BOOT_ROM_SIZE];
+        boot_rom[..PROGRAM.len()].copy_from_slice(&PROGRAM);
+
+        let mut cpu =
+            Cpu::new(boot_rom, vec![0; ROM_BANK_0_SIZE], None).unwrap();
+        cpu.symbols =
+            Symbols::parse("00:0000 Start\n00:0003 Loop\n01:0003 Far");
+
+        cpu
+    }
+
+    #[test]
+    fn it_breaks_at_labels() {
+        let mut cpu = cpu();
+        let mut debugger = Debugger::new();
+
+        assert_eq!(
+            debugger.command(&mut cpu, "break Loop"),
+            "Breakpoint at 0x0003 <Loop>"
+        );
+
+        let out = debugger.command(&mut cpu, "continue");
+
+        assert!(out.starts_with("Breakpoint\n"));
+        assert_eq!(cpu.pc.get(), 3);
+        assert_eq!(cpu.registers.b, 0x12);
+    }
+
+    #[test]
+    fn it_ignores_label_breakpoints_in_other_banks() {
+        let mut cpu = cpu();
+        let mut debugger = Debugger::new();
+
+        debugger.command(&mut cpu, "break Far");
+
+        assert!(!debugger.is_breakpoint(&cpu, 3));
+
+        debugger.command(&mut cpu, "break 3");
+
+        assert!(debugger.is_breakpoint(&cpu, 3));
+        assert_eq!(
+            debugger.command(&mut cpu, "delete Far"),
+            "Deleted breakpoint at 0x0003 <Far>"
+        );
+    }
+
+    #[test]
+    fn it_rejects_unknown_targets() {
+        let mut cpu = cpu();
+        let mut debugger = Debugger::new();
+
+        assert_eq!(
+            debugger.command(&mut cpu, "break Nowhere"),
+            "No symbol or address: Nowhere"
+        );
+    }
+
+    #[test]
+    fn it_manages_cheats() {
+        let mut cpu = cpu();
+        let mut debugger = Debugger::new();
+
+        assert_eq!(debugger.command(&mut cpu, "cheat"), "No cheats");
+        assert_eq!(
+            debugger.command(&mut cpu, "cheat add 01FFF0C0 Full health"),
+            "Cheat 0: Full health"
+        );
+        assert!(debugger
+            .command(&mut cpu, "cheat add 99FFF0C0")
+            .starts_with("unsupported"));
+
+        assert_eq!(cpu.bus.cheats.ram_writes(), vec![(None, 0xC0F0, 0xFF)]);
+
+        debugger.command(&mut cpu, "cheat off 0");
+        assert_eq!(
+            debugger.command(&mut cpu, "cheat"),
+            "0: [off] Full health 01FFF0C0"
+        );
+    }
+
+    #[test]
+    fn it_searches_ram() {
+        let mut cpu = cpu();
+        let mut debugger = Debugger::new();
+
+        assert!(debugger
+            .command(&mut cpu, "search gt")
+            .starts_with("No search"));
+
+        cpu.bus.write_byte(0xC0F0, 5);
+
+        assert_eq!(
+            debugger.command(&mut cpu, "search start"),
+            "16511 candidates"
+        );
+        assert_eq!(debugger.command(&mut cpu, "search eq 5"), "1 candidates");
+
+        cpu.bus.write_byte(0xC0F0, 4);
+
+        assert_eq!(debugger.command(&mut cpu, "search lt"), "1 candidates");
+        assert_eq!(debugger.command(&mut cpu, "search"), "0xC0F0: 04");
+    }
+
+    #[test]
+    fn it_shows_io_register_changes() {
+        let mut cpu = cpu();
+        let mut debugger = Debugger::new();
+
+        cpu.bus.write_byte(0xFF42, 3);
+
+        let io = debugger.command(&mut cpu, "io");
+        assert!(io.lines().any(|line| line == " FF42 SCY   03"));
+
+        // A new frame snapshots the registers
+        debugger.frame = u64::MAX;
+        debugger.step(&mut cpu);
+        cpu.bus.write_byte(0xFF42, 4);
+
+        let io = debugger.command(&mut cpu, "io");
+        assert!(io.lines().any(|line| line == "*FF42 SCY   04"));
+    }
+
+    #[test]
+    fn it_dumps_oam() {
+        let mut cpu = cpu();
+        let mut debugger = Debugger::new();
+
+        cpu.bus.gpu.oam[4..8].copy_from_slice(&[20, 12, 0x2A, 0x60]);
+
+        let dump = debugger.command(&mut cpu, "oam 6");
+        let lines: Vec<&str> = dump.lines().collect();
+
+        assert!(lines[0].ends_with("line 6"));
+        assert_eq!(
+            lines[2],
+            " 1 14 0C    4,4     2A   60   OBJ  XY   OBP0       selected 0"
+        );
+        assert!(debugger
+            .command(&mut cpu, "oam x")
+            .starts_with("Invalid line"));
+    }
+}
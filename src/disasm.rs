@@ -0,0 +1,149 @@
+use crate::{instr::Instr, mmu::Mmu, symbols::Symbols};
+use std::fmt;
+
+pub struct Line {
+    pub address: u16,
+    pub location: String,
+    pub bytes: Vec<u8>,
+    pub text: String,
+}
+
+impl Line {
+    pub fn length(&self) -> u16 {
+        self.bytes.len().max(1) as u16
+    }
+}
+
+impl fmt::Display for Line {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let bytes: Vec<String> =
+            self.bytes.iter().map(|b| format!("{:02X}", b)).collect();
+
+        write!(
+            f,
+            "{:<20} {:<8} {}",
+            self.location,
+            bytes.join(" "),
+            self.text
+        )
+    }
+}
+
+pub fn disassemble(bus: &Mmu, symbols: &Symbols, address: u16) -> Line {
+    let peek = |offset: u16| bus.peek_byte(address.wrapping_add(offset));
+    let location = symbols.format_address(bus, address);
+
+    let opcode = match peek(0) {
+        Some(opcode) => opcode,
+        None => {
+            return Line {
+                address,
+                location,
+                bytes: Vec::new(),
+                text: "??".to_string(),
+            }
+        }
+    };
+
+    let instr = match (opcode, peek(1)) {
+        (0xCB, Some(op)) => Instr::from(0xCB00 | op as u16),
+        _ => Instr::from(opcode),
+    };
+
+    let length = instr.length as u16;
+
+    let bytes: Vec<u8> = (0..length).map_while(peek).collect();
+
+    let text = if bytes.len() < length as usize {
+        instr.tag.to_string()
+    } else {
+        operands(bus, symbols, address, instr.tag, &bytes)
+    };
+
+    Line {
+        address,
+        location,
+        bytes,
+        text,
+    }
+}
+
+fn operands(
+    bus: &Mmu,
+    symbols: &Symbols,
+    address: u16,
+    tag: &str,
+    bytes: &[u8],
+) -> String {
+    let byte = || bytes[1];
+    let word = || (bytes[2] as u16) << 8 | bytes[1] as u16;
+
+    if tag.contains("FF00+u8") {
+        let target = 0xFF00 | byte() as u16;
+
+        tag.replace("FF00+u8", &symbols.format_address(bus, target))
+    } else if tag.contains("u16") {
+        tag.replace("u16", &symbols.format_address(bus, word()))
+    } else if tag.contains("u8") {
+        tag.replace("u8", &format!("0x{:02X}", byte()))
+    } else if tag.starts_with("JR") {
+        let target = address
+            .wrapping_add(2)
+            .wrapping_add(byte() as i8 as i16 as u16);
+
+        tag.replace("i8", &symbols.format_address(bus, target))
+    } else if tag.contains("i8") {
+        tag.replace("i8", &format!("{}", byte() as i8))
+    } else {
+        tag.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mmu::{BOOT_ROM_SIZE, ROM_BANK_0_SIZE};
+
+    #[rustfmt::skip]
+    const PROGRAM: [u8; 9] = [
+        0xC3, 0x50, 0x00, // JP 0x0050
+        0x18, 0xFB,       // JR -5
+        0xE0, 0x80,       // LD (FF00+0x80), A
+        0xCB, 0x11,       // RL C
+    ];
+
+    fn bus() -> Mmu {
+        let mut boot_rom = vec![0; BOOT_ROM_SIZE];
+        boot_rom[..PROGRAM.len()].copy_from_slice(&PROGRAM);
+
+        Mmu::new(boot_rom, vec![0; ROM_BANK_0_SIZE]).unwrap()
+    }
+
+    #[test]
+    fn it_disassembles_without_symbols() {
+        let bus = bus();
+        let symbols = Symbols::new();
+
+        let line = disassemble(&bus, &symbols, 0);
+        assert_eq!(line.text, "JP 0x0050");
+        assert_eq!(line.length(), 3);
+
+        assert_eq!(disassemble(&bus, &symbols, 3).text, "JR 0x0000");
+        assert_eq!(disassemble(&bus, &symbols, 7).text, "RL C");
+    }
+
+    #[test]
+    fn it_shows_addresses_as_labels() {
+        let bus = bus();
+        let symbols =
+            Symbols::parse("00:0000 Start\n00:0040 Loop\n00:FF80 hTmp");
+
+        let line = disassemble(&bus, &symbols, 0);
+        assert_eq!(line.location, "Start");
+        assert_eq!(line.text, "JP Loop+0x10");
+
+        assert_eq!(disassemble(&bus, &symbols, 3).location, "Start+0x3");
+        assert_eq!(disassemble(&bus, &symbols, 3).text, "JR Start");
+        assert_eq!(disassemble(&bus, &symbols, 5).text, "LD (hTmp), A");
+    }
+}
@@ -0,0 +1,278 @@
+//! Upscaling filters for the frontend and frame blending, which stands in for
+//! the slow DMG LCD that games flickering sprites at 30 Hz rely on.
+
+use crate::screenshot::scale;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Filter {
+    /// Plain integer scaling.
+    Nearest,
+    /// EPX/AdvMAME2x, then integer scaling.
+    Scale2x,
+    /// AdvMAME3x, then integer scaling.
+    Scale3x,
+    /// Integer scaling with darkened gaps between pixels.
+    LcdGrid,
+}
+
+pub const FILTERS: [Filter; 4] = [
+    Filter::Nearest,
+    Filter::Scale2x,
+    Filter::Scale3x,
+    Filter::LcdGrid,
+];
+
+impl Filter {
+    pub fn name(self) -> &'static str {
+        match self {
+            Filter::Nearest => "nearest",
+            Filter::Scale2x => "scale2x",
+            Filter::Scale3x => "scale3x",
+            Filter::LcdGrid => "lcd",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        FILTERS.iter().copied().find(|f| f.name() == name)
+    }
+
+    /// The factor `apply` scales by when asked for `factor`: the largest
+    /// multiple of what the filter needs that isn't above it.
+    pub fn factor(self, factor: usize) -> usize {
+        let step = match self {
+            Filter::Nearest => 1,
+            Filter::Scale2x | Filter::LcdGrid => 2,
+            Filter::Scale3x => 3,
+        };
+
+        (factor / step).max(1) * step
+    }
+
+    /// Scales a frame `width` pixels wide up by `self.factor(factor)`.
+    pub fn apply(
+        self,
+        pixels: &[u32],
+        width: usize,
+        factor: usize,
+    ) -> Vec<u32> {
+        let factor = self.factor(factor);
+        let height = pixels.len() / width;
+
+        match self {
+            Filter::Nearest => scale(pixels, width, factor),
+            Filter::Scale2x => {
+                scale(&scale2x(pixels, width, height), width * 2, factor / 2)
+            }
+            Filter::Scale3x => {
+                scale(&scale3x(pixels, width, height), width * 3, factor / 3)
+            }
+            Filter::LcdGrid => lcd_grid(pixels, width, factor),
+        }
+    }
+}
+
+/// Neighbours of `(x, y)` as a 3x3 block, repeating the edges.
+fn neighbours(
+    pixels: &[u32],
+    width: usize,
+    height: usize,
+    x: usize,
+    y: usize,
+) -> [u32; 9] {
+    let mut block = [0; 9];
+
+    for (i, pixel) in block.iter_mut().enumerate() {
+        let nx = (x + i % 3).saturating_sub(1).min(width - 1);
+        let ny = (y + i / 3).saturating_sub(1).min(height - 1);
+
+        *pixel = pixels[ny * width + nx];
+    }
+
+    block
+}
+
+pub fn scale2x(pixels: &[u32], width: usize, height: usize) -> Vec<u32> {
+    let mut out = vec![0; pixels.len() * 4];
+
+    for y in 0..height {
+        for x in 0..width {
+            let [_, a, _, c, p, b, _, d, _] =
+                neighbours(pixels, width, height, x, y);
+
+            let top = (y * 2) * width * 2 + x * 2;
+            let bottom = top + width * 2;
+
+            out[top] = if c == a && c != d && a != b { a } else { p };
+            out[top + 1] = if a == b && a != c && b != d { b } else { p };
+            out[bottom] = if d == c && d != b && c != a { c } else { p };
+            out[bottom + 1] = if b == d && b != a && d != c { d } else { p };
+        }
+    }
+
+    out
+}
+
+pub fn scale3x(pixels: &[u32], width: usize, height: usize) -> Vec<u32> {
+    let mut out = vec![0; pixels.len() * 9];
+
+    for y in 0..height {
+        for x in 0..width {
+            let [a, b, c, d, e, f, g, h, i] =
+                neighbours(pixels, width, height, x, y);
+
+            let mut block = [e; 9];
+
+            if b != h && d != f {
+                if d == b {
+                    block[0] = d;
+                }
+
+                if (d == b && e != c) || (b == f && e != a) {
+                    block[1] = b;
+                }
+
+                if b == f {
+                    block[2] = f;
+                }
+
+                if (d == b && e != g) || (d == h && e != a) {
+                    block[3] = d;
+                }
+
+                if (b == f && e != i) || (h == f && e != c) {
+                    block[5] = f;
+                }
+
+                if d == h {
+                    block[6] = d;
+                }
+
+                if (h == f && e != g) || (d == h && e != i) {
+                    block[7] = h;
+                }
+
+                if h == f {
+                    block[8] = f;
+                }
+            }
+
+            for (n, pixel) in block.iter().enumerate() {
+                out[(y * 3 + n / 3) * width * 3 + x * 3 + n % 3] = *pixel;
+            }
+        }
+    }
+
+    out
+}
+
+/// Darkens the last row and column of every scaled pixel by a quarter.
+fn lcd_grid(pixels: &[u32], width: usize, factor: usize) -> Vec<u32> {
+    let mut out = scale(pixels, width, factor);
+    let width = width * factor;
+
+    for (i, pixel) in out.iter_mut().enumerate() {
+        let (x, y) = (i % width, i / width);
+
+        if x % factor == factor - 1 || y % factor == factor - 1 {
+            *pixel = darken(*pixel);
+        }
+    }
+
+    out
+}
+
+fn darken(pixel: u32) -> u32 {
+    let channel = |shift: u32| ((pixel >> shift & 0xFF) * 3 / 4) << shift;
+
+    channel(16) | channel(8) | channel(0)
+}
+
+/// Averages each frame with the one before it.
+pub struct Blender {
+    previous: Vec<u32>,
+}
+
+impl Default for Blender {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Blender {
+    pub fn new() -> Self {
+        Self {
+            previous: Vec::new(),
+        }
+    }
+
+    pub fn blend(&mut self, pixels: &[u32]) -> Vec<u32> {
+        let blended = if self.previous.len() == pixels.len() {
+            pixels
+                .iter()
+                .zip(self.previous.iter())
+                .map(|(a, b)| average(*a, *b))
+                .collect()
+        } else {
+            pixels.to_vec()
+        };
+
+        self.previous = pixels.to_vec();
+
+        blended
+    }
+}
+
+fn average(a: u32, b: u32) -> u32 {
+    // Per channel, without carries between them
+    (a & b) + (((a ^ b) & 0xFEFEFE) >> 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gpu::{SCREEN_HEIGHT, SCREEN_WIDTH};
+
+    #[test]
+    fn it_smooths_diagonals_with_scale2x() {
+        // A diagonal step: X marks the 1s
+        //   . X
+        //   X X
+        let out = scale2x(&[0, 1, 1, 1], 2, 2);
+
+        assert_eq!(
+            out,
+            vec![
+                0, 0, 1, 1, //
+                0, 1, 1, 1, //
+                1, 1, 1, 1, //
+                1, 1, 1, 1,
+            ]
+        );
+    }
+
+    #[test]
+    fn it_keeps_flat_areas_with_scale3x() {
+        assert_eq!(scale3x(&[5; 4], 2, 2), vec![5; 36]);
+    }
+
+    #[test]
+    fn it_rounds_factors_to_what_the_filter_needs() {
+        assert_eq!(Filter::Nearest.factor(5), 5);
+        assert_eq!(Filter::Scale2x.factor(5), 4);
+        assert_eq!(Filter::Scale3x.factor(2), 3);
+
+        let pixels = vec![0xFFFFFF; SCREEN_WIDTH * SCREEN_HEIGHT];
+        let out = Filter::LcdGrid.apply(&pixels, SCREEN_WIDTH, 2);
+
+        assert_eq!(out.len(), pixels.len() * 4);
+        assert_eq!(&out[..2], &[0xFFFFFF, 0xBFBFBF]);
+    }
+
+    #[test]
+    fn it_blends_consecutive_frames() {
+        let mut blender = Blender::new();
+
+        assert_eq!(blender.blend(&[0xFFFFFF, 0]), vec![0xFFFFFF, 0]);
+        assert_eq!(blender.blend(&[0, 0x020406]), vec![0x7F7F7F, 0x010203]);
+    }
+}
@@ -0,0 +1,194 @@
+//! Golden-frame checks: render to a given frame and compare against a
+//! reference PNG or hash.
+
+use crate::{
+    checksum::crc32,
+    gpu::{SCREEN_HEIGHT, SCREEN_WIDTH},
+    headless::Headless,
+    png, Cpu,
+};
+use std::{fs, path::Path};
+
+// Mismatching pixels in diff images.
+const DIFF_COLOR: u32 = 0xFF0000;
+
+pub enum Reference {
+    Image(png::Image),
+    Hash(u32),
+}
+
+impl Reference {
+    /// `crc32:XXXXXXXX`, or the path of a PNG relative to `dir`.
+    pub fn parse(spec: &str, dir: &Path) -> Result<Self, String> {
+        if let Some(hash) = spec.strip_prefix("crc32:") {
+            return u32::from_str_radix(hash, 16)
+                .map(Reference::Hash)
+                .map_err(|_| format!("invalid hash {}", spec));
+        }
+
+        let path = dir.join(spec);
+        let data = fs::read(&path)
+            .map_err(|e| format!("{}: {}", path.display(), e))?;
+
+        png::decode(&data)
+            .map(Reference::Image)
+            .map_err(|e| format!("{}: {}", path.display(), e))
+    }
+}
+
+#[derive(Debug)]
+pub struct Mismatch {
+    pub hash: u32,
+    /// The reference image's width and height when they aren't the
+    /// screen's, in which case no pixels are compared.
+    pub size: Option<(usize, usize)>,
+    /// Number of differing pixels; only known against an image.
+    pub differing: Option<usize>,
+    /// The frame with matching pixels faded and differing ones in red.
+    pub diff: Option<Vec<u32>>,
+}
+
+/// CRC-32 of the frame's RGB bytes, for references too small to bother
+/// storing an image for.
+pub fn frame_hash(frame: &[u32]) -> u32 {
+    let bytes: Vec<u8> = frame
+        .iter()
+        .flat_map(|p| p.to_be_bytes()[1..].to_vec())
+        .collect();
+
+    crc32(&bytes)
+}
+
+/// Runs `frames` frames worth of cycles and returns the frame on screen.
+pub fn render(cpu: &mut Cpu, frames: u64) -> Result<Vec<u32>, String> {
+    Headless::new(frames).run(cpu)?;
+
+    Ok(cpu.bus.gpu.frame_rgb())
+}
+
+pub fn compare(frame: &[u32], reference: &Reference) -> Result<(), Mismatch> {
+    let hash = frame_hash(frame);
+
+    match reference {
+        Reference::Hash(expected) if hash == *expected => Ok(()),
+        Reference::Hash(_) => Err(Mismatch {
+            hash,
+            size: None,
+            differing: None,
+            diff: None,
+        }),
+        Reference::Image(expected)
+            if expected.width != SCREEN_WIDTH
+                || expected.height != SCREEN_HEIGHT =>
+        {
+            Err(Mismatch {
+                hash,
+                size: Some((expected.width, expected.height)),
+                differing: None,
+                diff: None,
+            })
+        }
+        Reference::Image(expected) => {
+            let diff: Vec<u32> = frame
+                .iter()
+                .enumerate()
+                .map(|(i, pixel)| match expected.pixels.get(i) {
+                    Some(e) if e == pixel => (pixel >> 2) & 0x3F3F3F | 0xC0C0C0,
+                    _ => DIFF_COLOR,
+                })
+                .collect();
+
+            let differing = diff.iter().filter(|p| **p == DIFF_COLOR).count();
+
+            if differing == 0 {
+                Ok(())
+            } else {
+                Err(Mismatch {
+                    hash,
+                    size: None,
+                    differing: Some(differing),
+                    diff: Some(diff),
+                })
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mmu::ROM_BANK_0_SIZE;
+
+    // Fills tile 1 with colour 3 and puts it at the top left of the map and
+    // one tile down and to the right.
+    #[rustfmt::skip]
+    const SCENE: [u8; 23] = [
+        0x21, 0x10, 0x80, // LD HL, 0x8010
+        0x3E, 0xFF,       // LD A, 0xFF
+        0x06, 0x10,       // LD B, 16
+        0x22,             // LD (HL+), A
+        0x05,             // DEC B
+        0x20, 0xFC,       // JR NZ, -4
+        0x3E, 0x01,       // LD A, 1
+        0x21, 0x00, 0x98, // LD HL, 0x9800
+        0x77,             // LD (HL), A
+        0x21, 0x21, 0x98, // LD HL, 0x9821
+        0x77,             // LD (HL), A
+        0x18, 0xFE,       // JR -2
+    ];
+
+    fn scene() -> Cpu {
+        let mut rom = vec![0; ROM_BANK_0_SIZE * 2];
+        rom[0x100..0x100 + SCENE.len()].copy_from_slice(&SCENE);
+
+        Cpu::post_boot(rom)
+    }
+
+    fn image(pixels: Vec<u32>) -> png::Image {
+        png::Image {
+            width: SCREEN_WIDTH,
+            height: SCREEN_HEIGHT,
+            pixels,
+        }
+    }
+
+    #[test]
+    fn it_matches_the_scene_hash() {
+        let frame = render(&mut scene(), 2).unwrap();
+
+        assert!(compare(&frame, &Reference::Hash(0x0FA6A967)).is_ok());
+        assert!(compare(&frame, &Reference::Hash(0x12345678)).is_err());
+    }
+
+    #[test]
+    fn it_marks_differing_pixels() {
+        let frame = render(&mut scene(), 2).unwrap();
+
+        let mut expected = image(frame.clone());
+        expected.pixels[0] = 0xFFFFFF;
+        expected.pixels[1] = 0xFFFFFF;
+
+        let mismatch =
+            compare(&frame, &Reference::Image(expected)).unwrap_err();
+
+        assert_eq!(mismatch.differing, Some(2));
+
+        let diff = mismatch.diff.unwrap();
+        assert_eq!(&diff[..3], &[DIFF_COLOR, DIFF_COLOR, 0xC0C0C0]);
+    }
+
+    #[test]
+    fn it_rejects_references_of_another_size() {
+        let frame = render(&mut scene(), 2).unwrap();
+
+        let mut expected = image(frame.clone());
+        expected.width = SCREEN_HEIGHT;
+        expected.height = SCREEN_WIDTH;
+
+        let mismatch =
+            compare(&frame, &Reference::Image(expected)).unwrap_err();
+
+        assert_eq!(mismatch.size, Some((SCREEN_HEIGHT, SCREEN_WIDTH)));
+        assert!(mismatch.diff.is_none());
+    }
+}
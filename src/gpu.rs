@@ -1,7 +1,54 @@
-use crate::mmu::{OAM_SIZE, V_RAM_SIZE};
+use crate::{
+    mmu::{OAM_SIZE, V_RAM_SIZE},
+    palette::{Palettes, LAYER_BG, LAYER_OBP0, LAYER_OBP1},
+    state::{Reader, Writer},
+};
 
-const SCREEN_SIZE: usize = 168 * 144 * 4;
+pub const SCREEN_WIDTH: usize = 160;
+pub const SCREEN_HEIGHT: usize = 144;
+const SCREEN_SIZE: usize = SCREEN_WIDTH * SCREEN_HEIGHT;
 
+// Sprites per scanline the hardware can fetch.
+pub const SPRITES_PER_LINE: usize = 10;
+
+/// Tiles in a VRAM bank, from 0x8000 to 0x97FF.
+pub const TILES: usize = 384;
+
+/// VRAM offsets of the two 32x32 tile maps, at 0x9800 and 0x9C00.
+pub const BG_MAPS: [usize; 2] = [0x1800, 0x1C00];
+
+const LCDC_LCD_ON: u8 = 1 << 7;
+const LCDC_WINDOW_MAP: u8 = 1 << 6;
+const LCDC_WINDOW_ON: u8 = 1 << 5;
+const LCDC_TILE_DATA: u8 = 1 << 4;
+const LCDC_BG_MAP: u8 = 1 << 3;
+const LCDC_OBJ_SIZE: u8 = 1 << 2;
+const LCDC_OBJ_ON: u8 = 1 << 1;
+const LCDC_BG_ON: u8 = 1;
+
+// OAM attributes
+pub const OBJ_BEHIND_BG: u8 = 1 << 7;
+pub const OBJ_Y_FLIP: u8 = 1 << 6;
+pub const OBJ_X_FLIP: u8 = 1 << 5;
+pub const OBJ_PALETTE: u8 = 1 << 4;
+pub const OBJ_BANK: u8 = 1 << 3;
+pub const OBJ_CGB_PALETTE: u8 = 0b111;
+
+// BG map attributes in VRAM bank 1 (CGB)
+const BG_PRIORITY: u8 = 1 << 7;
+const BG_Y_FLIP: u8 = 1 << 6;
+const BG_X_FLIP: u8 = 1 << 5;
+const BG_BANK: u8 = 1 << 3;
+const BG_PALETTE: u8 = 0b111;
+
+// BCPS/OCPS
+const CPS_INCREMENT: u8 = 1 << 7;
+const CRAM_SIZE: usize = 64;
+
+const STAT_COINCIDENCE: u8 = 1 << 2;
+const STAT_WRITABLE: u8 = 0b0111_1000;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
 enum Mode {
     ScanlineOam,
     ScanlineVram,
@@ -9,81 +56,726 @@ enum Mode {
     Vblank,
 }
 
+impl Mode {
+    fn bits(self) -> u8 {
+        match self {
+            Mode::Hblank => 0,
+            Mode::Vblank => 1,
+            Mode::ScanlineOam => 2,
+            Mode::ScanlineVram => 3,
+        }
+    }
+
+    fn from_bits(bits: u8) -> Self {
+        match bits & 0b11 {
+            0 => Mode::Hblank,
+            1 => Mode::Vblank,
+            2 => Mode::ScanlineOam,
+            _ => Mode::ScanlineVram,
+        }
+    }
+}
+
 // TODO: Who should own the CPU, what is the hiearchy of components?
 // RN: CPU -> Bus -> GPU
 // Q? Bus -> CPU
 //        -> GPU
+#[derive(Clone)]
 pub struct Gpu {
-    pub v_ram: [u8; V_RAM_SIZE],
-    oam: [i8; OAM_SIZE],
-    // TODO: Has to be accessible by external framebuffer
-    // Push to fb every vblank
+    /// Both banks; bank 1 only exists on the CGB.
+    pub v_ram: [u8; V_RAM_SIZE * 2],
+    pub oam: [u8; OAM_SIZE],
+    // Shade (0-3) per pixel, after BGP or OBPx, and the layer it's from.
     screen: [u8; SCREEN_SIZE],
+    layers: [u8; SCREEN_SIZE],
+    // RGB555 per pixel in CGB mode.
+    colors: [u16; SCREEN_SIZE],
+    palettes: Palettes,
+    cgb: bool,
+    vbk: u8,
+    bcps: u8,
+    ocps: u8,
+    bg_cram: [u8; CRAM_SIZE],
+    obj_cram: [u8; CRAM_SIZE],
     modeclock: u32,
     mode: Mode,
     line: u8,
+    frames: u64,
+    lcdc: u8,
+    stat: u8,
+    scy: u8,
+    scx: u8,
+    lyc: u8,
+    bgp: u8,
+    obp0: u8,
+    obp1: u8,
+    wy: u8,
+    wx: u8,
+}
+
+impl Default for Gpu {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Gpu {
     pub fn new() -> Self {
         Self {
-            v_ram: [0; V_RAM_SIZE],
+            v_ram: [0; V_RAM_SIZE * 2],
             oam: [0; OAM_SIZE],
             screen: [0; SCREEN_SIZE],
+            layers: [LAYER_BG; SCREEN_SIZE],
+            colors: [0; SCREEN_SIZE],
+            palettes: Palettes::default(),
+            cgb: false,
+            vbk: 0,
+            bcps: 0,
+            ocps: 0,
+            bg_cram: [0; CRAM_SIZE],
+            obj_cram: [0; CRAM_SIZE],
             modeclock: 0,
             mode: Mode::Hblank,
             line: 0,
+            frames: 0,
+            lcdc: 0,
+            stat: 0,
+            scy: 0,
+            scx: 0,
+            lyc: 0,
+            bgp: 0,
+            obp0: 0,
+            obp1: 0,
+            wy: 0,
+            wx: 0,
+        }
+    }
+
+    pub fn is_cgb(&self) -> bool {
+        self.cgb
+    }
+
+    /// Switches to CGB rendering, with colour palettes and VRAM bank 1.
+    pub fn set_cgb(&mut self, cgb: bool) {
+        self.cgb = cgb;
+    }
+
+    /// VRAM at `offset` from 0x8000, in the bank selected through VBK.
+    pub fn read_vram(&self, offset: usize) -> u8 {
+        self.v_ram[self.vbk as usize * V_RAM_SIZE + offset]
+    }
+
+    pub fn write_vram(&mut self, offset: usize, byte: u8) {
+        self.v_ram[self.vbk as usize * V_RAM_SIZE + offset] = byte;
+    }
+
+    /// Number of frames completed (V-Blank entries) since power on.
+    pub fn frames(&self) -> u64 {
+        self.frames
+    }
+
+    pub fn screen(&self) -> &[u8] {
+        &self.screen
+    }
+
+    /// Palette layer per pixel, see `palette::LAYER_BG`.
+    pub fn layers(&self) -> &[u8] {
+        &self.layers
+    }
+
+    pub fn palettes(&self) -> &Palettes {
+        &self.palettes
+    }
+
+    /// Colours for `frame_rgb`; not part of save states.
+    pub fn set_palettes(&mut self, palettes: Palettes) {
+        self.palettes = palettes;
+    }
+
+    /// The current frame as `0xRRGGBB` pixels, row by row.
+    pub fn frame_rgb(&self) -> Vec<u32> {
+        if self.cgb {
+            return self.colors.iter().map(|c| rgb555_to_rgb(*c)).collect();
+        }
+
+        self.screen
+            .iter()
+            .zip(self.layers.iter())
+            .map(|(shade, layer)| self.palettes.get(*layer)[*shade as usize])
+            .collect()
+    }
+
+    pub fn sprite_height(&self) -> usize {
+        if self.lcdc & LCDC_OBJ_SIZE != 0 {
+            16
+        } else {
+            8
+        }
+    }
+
+    /// OAM indices of the sprites drawn on `line`: the first ten in OAM
+    /// order that overlap it, sorted by drawing priority (on the DMG lower X
+    /// first, then lower index).
+    pub fn sprites_on_line(&self, line: u8) -> Vec<usize> {
+        let mut sprites = self.sprites_overlapping(line);
+        sprites.truncate(SPRITES_PER_LINE);
+
+        // The CGB goes by OAM order alone
+        if !self.cgb {
+            sprites.sort_by_key(|i| self.oam[i * 4 + 1]);
         }
+
+        sprites
+    }
+
+    /// OAM indices of every sprite overlapping `line`, in OAM order; those
+    /// past the first ten aren't drawn.
+    pub fn sprites_overlapping(&self, line: u8) -> Vec<usize> {
+        let height = self.sprite_height() as i32;
+        let line = line as i32;
+
+        (0..OAM_SIZE / 4)
+            .filter(|i| {
+                let y = self.oam[i * 4] as i32 - 16;
+
+                line >= y && line < y + height
+            })
+            .collect()
+    }
+
+    pub fn read_reg(&self, address: u16) -> u8 {
+        match address {
+            0xFF40 => self.lcdc,
+            0xFF41 => {
+                let coincidence = if self.line == self.lyc {
+                    STAT_COINCIDENCE
+                } else {
+                    0
+                };
+
+                0x80 | (self.stat & STAT_WRITABLE)
+                    | coincidence
+                    | self.mode.bits()
+            }
+            0xFF42 => self.scy,
+            0xFF43 => self.scx,
+            0xFF44 => self.line,
+            0xFF45 => self.lyc,
+            0xFF47 => self.bgp,
+            0xFF48 => self.obp0,
+            0xFF49 => self.obp1,
+            0xFF4A => self.wy,
+            0xFF4B => self.wx,
+            0xFF4F if self.cgb => 0xFE | self.vbk,
+            0xFF68 if self.cgb => 0x40 | self.bcps,
+            0xFF69 if self.cgb => self.bg_cram[(self.bcps & 0x3F) as usize],
+            0xFF6A if self.cgb => 0x40 | self.ocps,
+            0xFF6B if self.cgb => self.obj_cram[(self.ocps & 0x3F) as usize],
+            _ => 0xFF,
+        }
+    }
+
+    pub fn write_reg(&mut self, address: u16, byte: u8) {
+        match address {
+            0xFF40 => {
+                if byte & LCDC_LCD_ON == 0 {
+                    self.line = 0;
+                    self.modeclock = 0;
+                    self.mode = Mode::Hblank;
+                } else if self.lcdc & LCDC_LCD_ON == 0 {
+                    self.mode = Mode::ScanlineOam;
+                }
+
+                self.lcdc = byte;
+            }
+            0xFF41 => self.stat = byte & STAT_WRITABLE,
+            0xFF42 => self.scy = byte,
+            0xFF43 => self.scx = byte,
+            0xFF45 => self.lyc = byte,
+            0xFF47 => self.bgp = byte,
+            0xFF48 => self.obp0 = byte,
+            0xFF49 => self.obp1 = byte,
+            0xFF4A => self.wy = byte,
+            0xFF4B => self.wx = byte,
+            0xFF4F if self.cgb => self.vbk = byte & 1,
+            0xFF68 if self.cgb => self.bcps = byte & 0xBF,
+            0xFF69 if self.cgb => {
+                write_cram(&mut self.bg_cram, &mut self.bcps, byte)
+            }
+            0xFF6A if self.cgb => self.ocps = byte & 0xBF,
+            0xFF6B if self.cgb => {
+                write_cram(&mut self.obj_cram, &mut self.ocps, byte)
+            }
+            _ => {}
+        }
+    }
+
+    pub fn save(&self, w: &mut Writer) {
+        w.bytes(&self.v_ram);
+        w.bytes(&self.oam);
+        w.bytes(&self.screen);
+        w.bytes(&self.layers);
+
+        for color in self.colors.iter() {
+            w.u16(*color);
+        }
+
+        w.bytes(&self.bg_cram);
+        w.bytes(&self.obj_cram);
+        w.u32(self.modeclock);
+        w.u8(self.mode.bits());
+        w.u8(self.line);
+        w.u64(self.frames);
+
+        for reg in &[
+            self.lcdc, self.stat, self.scy, self.scx, self.lyc, self.bgp,
+            self.obp0, self.obp1, self.wy, self.wx, self.vbk, self.bcps,
+            self.ocps,
+        ] {
+            w.u8(*reg);
+        }
+    }
+
+    pub fn load(&mut self, r: &mut Reader) -> Result<(), String> {
+        r.fill(&mut self.v_ram)?;
+        r.fill(&mut self.oam)?;
+        r.fill(&mut self.screen)?;
+        r.fill(&mut self.layers)?;
+
+        for color in self.colors.iter_mut() {
+            *color = r.u16()?;
+        }
+
+        r.fill(&mut self.bg_cram)?;
+        r.fill(&mut self.obj_cram)?;
+        self.modeclock = r.u32()?;
+        self.mode = Mode::from_bits(r.u8()?);
+        self.line = r.u8()?;
+        self.frames = r.u64()?;
+
+        for reg in &mut [
+            &mut self.lcdc,
+            &mut self.stat,
+            &mut self.scy,
+            &mut self.scx,
+            &mut self.lyc,
+            &mut self.bgp,
+            &mut self.obp0,
+            &mut self.obp1,
+            &mut self.wy,
+            &mut self.wx,
+            &mut self.vbk,
+            &mut self.bcps,
+            &mut self.ocps,
+        ] {
+            **reg = r.u8()?;
+        }
+
+        Ok(())
     }
 
     pub fn step(&mut self, ticks: u8) {
+        if self.lcdc & LCDC_LCD_ON == 0 {
+            return;
+        }
+
         self.modeclock += ticks as u32;
 
         match self.mode {
             Mode::ScanlineOam => {
                 if self.modeclock >= 80 {
                     self.mode = Mode::ScanlineVram;
-                    self.modeclock = 0;
+                    self.modeclock -= 80;
                 }
-            },
+            }
 
             Mode::ScanlineVram => {
                 if self.modeclock >= 172 {
                     self.mode = Mode::Hblank;
-                    self.modeclock = 0;
+                    self.modeclock -= 172;
 
-                    // TODO: Write scanline
+                    self.render_scanline();
                 }
-            },
+            }
 
             Mode::Hblank => {
                 if self.modeclock >= 204 {
-                    self.modeclock = 0;
-                    self.line += self.line;
+                    self.modeclock -= 204;
+                    self.line += 1;
 
-                    if self.line == 143 {
+                    if self.line == SCREEN_HEIGHT as u8 {
                         self.mode = Mode::Vblank;
-
-                        // TODO: screen to framebuffer
+                        self.frames += 1;
                     } else {
                         self.mode = Mode::ScanlineOam;
                     }
                 }
-            },
+            }
 
             Mode::Vblank => {
                 if self.modeclock >= 456 {
-                    self.modeclock = 0;
-                    self.line += self.line;
+                    self.modeclock -= 456;
+                    self.line += 1;
 
                     if self.line > 153 {
                         self.mode = Mode::ScanlineOam;
                         self.line = 0;
                     }
                 }
-            },
+            }
+        }
+    }
 
-            _ => panic!("unimplemented GPU mode"),
+    fn render_scanline(&mut self) {
+        let line = self.line as usize;
+
+        let window = self.lcdc & LCDC_WINDOW_ON != 0 && self.line >= self.wy;
+
+        // Colour numbers before BGP and the CGB attributes, for sprites
+        let mut bg = [(0, 0); SCREEN_WIDTH];
+
+        for (x, pixel) in bg.iter_mut().enumerate() {
+            // On the CGB, LCDC bit 0 only takes away the BG's priority
+            *pixel = if self.lcdc & LCDC_BG_ON == 0 && !self.cgb {
+                (0, 0)
+            } else if window && x + 7 >= self.wx as usize {
+                let map = BG_MAPS[(self.lcdc & LCDC_WINDOW_MAP != 0) as usize];
+
+                self.tile_pixel(
+                    map,
+                    x + 7 - self.wx as usize,
+                    line - self.wy as usize,
+                )
+            } else {
+                let map = BG_MAPS[(self.lcdc & LCDC_BG_MAP != 0) as usize];
+
+                self.tile_pixel(
+                    map,
+                    (x + self.scx as usize) & 0xFF,
+                    (line + self.scy as usize) & 0xFF,
+                )
+            };
+
+            let (color, attributes) = *pixel;
+            let i = line * SCREEN_WIDTH + x;
+
+            self.screen[i] = (self.bgp >> (color * 2)) & 0b11;
+            self.layers[i] = LAYER_BG;
+            self.colors[i] =
+                cram_color(&self.bg_cram, attributes & BG_PALETTE, color);
+        }
+
+        if self.lcdc & LCDC_OBJ_ON != 0 {
+            self.render_sprites(&bg);
         }
     }
+
+    fn render_sprites(&mut self, bg: &[(u8, u8); SCREEN_WIDTH]) {
+        let line = self.line as usize;
+        let height = self.sprite_height();
+
+        // The first opaque sprite pixel wins, even if it's behind the BG
+        let mut drawn = [false; SCREEN_WIDTH];
+
+        for i in self.sprites_on_line(self.line) {
+            let sprite = &self.oam[i * 4..i * 4 + 4];
+            let flags = sprite[3];
+
+            let mut row = line + 16 - sprite[0] as usize;
+
+            if flags & OBJ_Y_FLIP != 0 {
+                row = height - 1 - row;
+            }
+
+            let tile = if height == 16 {
+                sprite[2] & 0xFE
+            } else {
+                sprite[2]
+            } as usize;
+
+            let bank = if self.cgb && flags & OBJ_BANK != 0 {
+                V_RAM_SIZE
+            } else {
+                0
+            };
+
+            let address = bank + tile * 16 + row * 2;
+            let (lo, hi) = (self.v_ram[address], self.v_ram[address + 1]);
+
+            let (palette, layer) = if flags & OBJ_PALETTE != 0 {
+                (self.obp1, LAYER_OBP1)
+            } else {
+                (self.obp0, LAYER_OBP0)
+            };
+
+            for px in 0..8 {
+                let x = sprite[1] as usize + px;
+
+                if !(8..SCREEN_WIDTH + 8).contains(&x) || drawn[x - 8] {
+                    continue;
+                }
+
+                let x = x - 8;
+                let bit = if flags & OBJ_X_FLIP != 0 { px } else { 7 - px };
+                let color = (((hi >> bit) & 1) << 1) | ((lo >> bit) & 1);
+
+                if color == 0 {
+                    continue;
+                }
+
+                drawn[x] = true;
+
+                let (bg_color, attributes) = bg[x];
+
+                let behind = if self.cgb {
+                    self.lcdc & LCDC_BG_ON != 0
+                        && (flags & OBJ_BEHIND_BG != 0
+                            || attributes & BG_PRIORITY != 0)
+                } else {
+                    flags & OBJ_BEHIND_BG != 0
+                };
+
+                if behind && bg_color != 0 {
+                    continue;
+                }
+
+                let i = line * SCREEN_WIDTH + x;
+
+                self.screen[i] = (palette >> (color * 2)) & 0b11;
+                self.layers[i] = layer;
+                self.colors[i] =
+                    cram_color(&self.obj_cram, flags & OBJ_CGB_PALETTE, color);
+            }
+        }
+    }
+
+    /// Colour number (0-3) at `x`, `y` of the 256x256 map at `map` (a VRAM
+    /// offset), and the tile's attributes in CGB mode.
+    pub fn tile_pixel(&self, map: usize, x: usize, y: usize) -> (u8, u8) {
+        let index = self.v_ram[map + (y / 8) * 32 + x / 8];
+
+        let attributes = if self.cgb {
+            self.v_ram[V_RAM_SIZE + map + (y / 8) * 32 + x / 8]
+        } else {
+            0
+        };
+
+        // 0x8000 unsigned, or 0x9000 signed
+        let tile = if self.lcdc & LCDC_TILE_DATA != 0 {
+            index as usize
+        } else {
+            (256 + index as i8 as isize) as usize
+        };
+
+        let bank = (attributes & BG_BANK != 0) as usize;
+        let (mut x, mut y) = (x % 8, y % 8);
+
+        if attributes & BG_X_FLIP != 0 {
+            x = 7 - x;
+        }
+
+        if attributes & BG_Y_FLIP != 0 {
+            y = 7 - y;
+        }
+
+        (self.tile_color(bank, tile, x, y), attributes)
+    }
+
+    /// Colour number (0-3) at `x`, `y` of tile `tile` (up to `TILES`) in
+    /// VRAM bank `bank`.
+    pub fn tile_color(
+        &self,
+        bank: usize,
+        tile: usize,
+        x: usize,
+        y: usize,
+    ) -> u8 {
+        let row = bank * V_RAM_SIZE + tile * 16 + y * 2;
+        let bit = 7 - x;
+
+        let lo = (self.v_ram[row] >> bit) & 1;
+        let hi = (self.v_ram[row + 1] >> bit) & 1;
+
+        (hi << 1) | lo
+    }
+
+    /// `0xRRGGBB` of BG colour number `color`: through BGP and the BG
+    /// palette, or in CGB mode the colour palette in `attributes`.
+    pub fn bg_rgb(&self, color: u8, attributes: u8) -> u32 {
+        if self.cgb {
+            let palette = attributes & BG_PALETTE;
+
+            return rgb555_to_rgb(cram_color(&self.bg_cram, palette, color));
+        }
+
+        self.palettes.bg[((self.bgp >> (color * 2)) & 0b11) as usize]
+    }
+
+    /// `0xRRGGBB` of sprite colour number `color` with OAM attributes
+    /// `flags`: through OBP0/OBP1, or the CGB colour palette.
+    pub fn obj_rgb(&self, color: u8, flags: u8) -> u32 {
+        if self.cgb {
+            let palette = flags & OBJ_CGB_PALETTE;
+
+            return rgb555_to_rgb(cram_color(&self.obj_cram, palette, color));
+        }
+
+        let (palette, layer) = if flags & OBJ_PALETTE != 0 {
+            (self.obp1, LAYER_OBP1)
+        } else {
+            (self.obp0, LAYER_OBP0)
+        };
+
+        self.palettes.get(layer)[((palette >> (color * 2)) & 0b11) as usize]
+    }
+
+    /// SCX and SCY.
+    pub fn scroll(&self) -> (u8, u8) {
+        (self.scx, self.scy)
+    }
+}
+
+/// Writes through BCPD/OCPD, advancing the index if auto-increment is on.
+fn write_cram(cram: &mut [u8; CRAM_SIZE], cps: &mut u8, byte: u8) {
+    cram[(*cps & 0x3F) as usize] = byte;
+
+    if *cps & CPS_INCREMENT != 0 {
+        *cps = CPS_INCREMENT | ((*cps + 1) & 0x3F);
+    }
+}
+
+/// RGB555 colour `color` of palette `palette`.
+fn cram_color(cram: &[u8; CRAM_SIZE], palette: u8, color: u8) -> u16 {
+    let i = palette as usize * 8 + color as usize * 2;
+
+    u16::from_le_bytes([cram[i], cram[i + 1]])
+}
+
+pub fn rgb555_to_rgb(color: u16) -> u32 {
+    let channel = |shift: u16| {
+        let c = (color >> shift & 0x1F) as u32;
+
+        (c << 3) | (c >> 2)
+    };
+
+    (channel(0) << 16) | (channel(5) << 8) | channel(10)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run_line(gpu: &mut Gpu) {
+        for _ in 0..(456 / 4) {
+            gpu.step(4);
+        }
+    }
+
+    #[test]
+    fn it_counts_lines_and_frames() {
+        let mut gpu = Gpu::new();
+        gpu.write_reg(0xFF40, LCDC_LCD_ON);
+
+        run_line(&mut gpu);
+        assert_eq!(gpu.read_reg(0xFF44), 1);
+
+        for _ in 1..154 {
+            run_line(&mut gpu);
+        }
+
+        assert_eq!(gpu.read_reg(0xFF44), 0);
+        assert_eq!(gpu.frames(), 1);
+    }
+
+    #[test]
+    fn it_renders_the_background_through_bgp() {
+        let mut gpu = Gpu::new();
+
+        // Tile 1, row 0: colours 3, 2, 1, 0, 0, 0, 0, 0
+        gpu.v_ram[16] = 0b1010_0000;
+        gpu.v_ram[17] = 0b1100_0000;
+        gpu.v_ram[0x1800] = 1;
+
+        gpu.write_reg(0xFF47, 0b11_10_01_00);
+        gpu.write_reg(0xFF40, LCDC_LCD_ON | LCDC_TILE_DATA | LCDC_BG_ON);
+
+        run_line(&mut gpu);
+
+        assert_eq!(&gpu.screen()[..5], &[3, 2, 1, 0, 0]);
+    }
+
+    #[test]
+    fn it_draws_ten_sprites_per_line_in_priority_order() {
+        let mut gpu = Gpu::new();
+
+        // Tile 1: a solid block in colour 3
+        for byte in &mut gpu.v_ram[16..32] {
+            *byte = 0xFF;
+        }
+
+        // Eleven sprites in a row, then one overlapping the first
+        for i in 0..12 {
+            let x = if i == 11 { 12 } else { 8 + i as u8 * 8 };
+
+            gpu.oam[i * 4..i * 4 + 4].copy_from_slice(&[16, x, 1, 0]);
+        }
+
+        gpu.oam[11 * 4 + 3] = OBJ_PALETTE;
+
+        gpu.write_reg(0xFF48, 0b11_00_00_00);
+        gpu.write_reg(0xFF49, 0b10_00_00_00);
+        gpu.write_reg(0xFF40, LCDC_LCD_ON | LCDC_OBJ_ON);
+
+        assert_eq!(gpu.sprites_on_line(0), (0..10).collect::<Vec<_>>());
+
+        run_line(&mut gpu);
+
+        assert_eq!(gpu.screen()[0], 3);
+        assert_eq!(gpu.layers()[0], LAYER_OBP0);
+        // The eleventh sprite isn't drawn, nor is the twelfth ever fetched
+        assert_eq!(gpu.screen()[80], 0);
+        assert_eq!(gpu.layers()[80], LAYER_BG);
+        assert_eq!(gpu.layers()[4], LAYER_OBP0);
+    }
+
+    #[test]
+    fn it_colours_each_layer_with_its_palette() {
+        let mut gpu = Gpu::new();
+        gpu.screen[0] = 1;
+        gpu.screen[1] = 1;
+        gpu.layers[1] = LAYER_OBP1;
+
+        gpu.set_palettes(Palettes {
+            bg: [0, 0x111111, 0, 0],
+            obp0: [0; 4],
+            obp1: [0, 0x222222, 0, 0],
+        });
+
+        assert_eq!(&gpu.frame_rgb()[..2], &[0x111111, 0x222222]);
+    }
+
+    #[test]
+    fn it_renders_cgb_attributes_and_colour_palettes() {
+        let mut gpu = Gpu::new();
+        gpu.set_cgb(true);
+
+        // Tile 0 in bank 1, row 0: colour 1 at the left edge only
+        gpu.v_ram[V_RAM_SIZE] = 0b1000_0000;
+        // Map entry 0: bank 1, BG palette 2, flipped horizontally
+        gpu.v_ram[V_RAM_SIZE + 0x1800] = BG_BANK | BG_X_FLIP | 2;
+
+        // BG palette 2, colour 1: pure red, written with auto-increment
+        gpu.write_reg(0xFF68, CPS_INCREMENT | (2 * 8 + 2));
+        gpu.write_reg(0xFF69, 0x1F);
+        gpu.write_reg(0xFF69, 0x00);
+        assert_eq!(gpu.read_reg(0xFF68), 0xC0 | (2 * 8 + 4));
+
+        gpu.write_reg(0xFF40, LCDC_LCD_ON | LCDC_TILE_DATA | LCDC_BG_ON);
+        run_line(&mut gpu);
+
+        let frame = gpu.frame_rgb();
+        assert_eq!(frame[7], 0xFF0000);
+        assert_eq!(frame[0], 0x000000);
+        assert_eq!(rgb555_to_rgb(0x7FFF), 0xFFFFFF);
+    }
 }
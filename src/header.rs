@@ -0,0 +1,528 @@
+//! The cartridge header at 0x0100-0x014F.
+
+use crate::archive;
+use std::fmt;
+
+const LOGO_START: usize = 0x104;
+const TITLE_START: usize = 0x134;
+const TITLE_END: usize = 0x143;
+const MANUFACTURER_START: usize = 0x13F;
+const CGB_FLAG: usize = 0x143;
+const NEW_LICENSEE: usize = 0x144;
+const SGB_FLAG: usize = 0x146;
+const CARTRIDGE_TYPE: usize = 0x147;
+const ROM_SIZE: usize = 0x148;
+const RAM_SIZE: usize = 0x149;
+const OLD_LICENSEE: usize = 0x14B;
+const VERSION: usize = 0x14C;
+const HEADER_CHECKSUM: usize = 0x14D;
+const GLOBAL_CHECKSUM: usize = 0x14E;
+const HEADER_END: usize = 0x14F;
+
+// Old licensee code meaning the new one is used instead
+const USE_NEW_LICENSEE: u8 = 0x33;
+
+/// The logo the boot ROM compares the cartridge's with.
+#[rustfmt::skip]
+pub const LOGO: [u8; 48] = [
+    0xCE, 0xED, 0x66, 0x66, 0xCC, 0x0D, 0x00, 0x0B, 0x03, 0x73, 0x00, 0x83,
+    0x00, 0x0C, 0x00, 0x0D, 0x00, 0x08, 0x11, 0x1F, 0x88, 0x89, 0x00, 0x0E,
+    0xDC, 0xCC, 0x6E, 0xE6, 0xDD, 0xDD, 0xD9, 0x99, 0xBB, 0xBB, 0x67, 0x63,
+    0x6E, 0x0E, 0xEC, 0xCC, 0xDD, 0xDC, 0x99, 0x9F, 0xBB, 0xB9, 0x33, 0x3E,
+];
+
+#[rustfmt::skip]
+const CARTRIDGE_TYPES: [(u8, &str); 28] = [
+    (0x00, "ROM ONLY"), (0x01, "MBC1"), (0x02, "MBC1+RAM"),
+    (0x03, "MBC1+RAM+BATTERY"), (0x05, "MBC2"), (0x06, "MBC2+BATTERY"),
+    (0x08, "ROM+RAM"), (0x09, "ROM+RAM+BATTERY"), (0x0B, "MMM01"),
+    (0x0C, "MMM01+RAM"), (0x0D, "MMM01+RAM+BATTERY"),
+    (0x0F, "MBC3+TIMER+BATTERY"), (0x10, "MBC3+TIMER+RAM+BATTERY"),
+    (0x11, "MBC3"), (0x12, "MBC3+RAM"), (0x13, "MBC3+RAM+BATTERY"),
+    (0x19, "MBC5"), (0x1A, "MBC5+RAM"), (0x1B, "MBC5+RAM+BATTERY"),
+    (0x1C, "MBC5+RUMBLE"), (0x1D, "MBC5+RUMBLE+RAM"),
+    (0x1E, "MBC5+RUMBLE+RAM+BATTERY"), (0x20, "MBC6"),
+    (0x22, "MBC7+SENSOR+RUMBLE+RAM+BATTERY"), (0xFC, "POCKET CAMERA"),
+    (0xFD, "BANDAI TAMA5"), (0xFE, "HuC3"), (0xFF, "HuC1+RAM+BATTERY"),
+];
+
+// Cartridge types the MMU can run: no MBC, so 32 KiB of ROM at most
+const SUPPORTED_TYPES: [u8; 3] = [0x00, 0x08, 0x09];
+const SUPPORTED_ROM_SIZE: usize = 0x8000;
+
+#[rustfmt::skip]
+const NEW_LICENSEES: [(&str, &str); 64] = [
+    ("00", "None"), ("01", "Nintendo R&D1"), ("08", "Capcom"),
+    ("13", "Electronic Arts"), ("18", "Hudson Soft"), ("19", "b-ai"),
+    ("20", "KSS"), ("22", "POW"), ("24", "PCM Complete"), ("25", "San-X"),
+    ("28", "Kemco Japan"), ("29", "SETA"), ("30", "Viacom"),
+    ("31", "Nintendo"), ("32", "Bandai"), ("33", "Ocean/Acclaim"),
+    ("34", "Konami"), ("35", "HectorSoft"), ("37", "Taito"),
+    ("38", "Hudson Soft"), ("39", "Banpresto"), ("41", "Ubi Soft"),
+    ("42", "Atlus"), ("44", "Malibu"), ("46", "Angel"),
+    ("47", "Bullet-Proof Software"), ("49", "Irem"), ("50", "Absolute"),
+    ("51", "Acclaim"), ("52", "Activision"), ("53", "American Sammy"),
+    ("54", "Konami"), ("55", "Hi Tech Entertainment"), ("56", "LJN"),
+    ("57", "Matchbox"), ("58", "Mattel"), ("59", "Milton Bradley"),
+    ("60", "Titus"), ("61", "Virgin"), ("64", "LucasArts"),
+    ("67", "Ocean"), ("69", "Electronic Arts"), ("70", "Infogrames"),
+    ("71", "Interplay"), ("72", "Broderbund"), ("73", "Sculptured Software"),
+    ("75", "The Sales Curve"), ("78", "THQ"), ("79", "Accolade"),
+    ("80", "Misawa"), ("83", "LOZC"), ("86", "Tokuma Shoten"),
+    ("87", "Tsukuda Original"), ("91", "Chunsoft"), ("92", "Video System"),
+    ("93", "Ocean/Acclaim"), ("95", "Varie"), ("96", "Yonezawa/S'Pal"),
+    ("97", "Kaneko"), ("99", "Pack-In-Video"), ("9H", "Bottom Up"),
+    ("A4", "Konami"), ("BL", "MTO"), ("DK", "Kodansha"),
+];
+
+#[rustfmt::skip]
+const OLD_LICENSEES: [(u8, &str); 146] = [
+    (0x00, "None"), (0x01, "Nintendo"), (0x08, "Capcom"), (0x09, "HOT-B"),
+    (0x0A, "Jaleco"), (0x0B, "Coconuts Japan"), (0x0C, "Elite Systems"),
+    (0x13, "Electronic Arts"), (0x18, "Hudson Soft"),
+    (0x19, "ITC Entertainment"), (0x1A, "Yanoman"), (0x1D, "Japan Clary"),
+    (0x1F, "Virgin Games"), (0x24, "PCM Complete"), (0x25, "San-X"),
+    (0x28, "Kemco"), (0x29, "SETA"), (0x30, "Infogrames"),
+    (0x31, "Nintendo"), (0x32, "Bandai"), (0x34, "Konami"),
+    (0x35, "HectorSoft"), (0x38, "Capcom"), (0x39, "Banpresto"),
+    (0x3C, "Entertainment Interactive"), (0x3E, "Gremlin"),
+    (0x41, "Ubi Soft"), (0x42, "Atlus"), (0x44, "Malibu"), (0x46, "Angel"),
+    (0x47, "Spectrum HoloByte"), (0x49, "Irem"), (0x4A, "Virgin Games"),
+    (0x4D, "Malibu"), (0x4F, "U.S. Gold"), (0x50, "Absolute"),
+    (0x51, "Acclaim"), (0x52, "Activision"), (0x53, "Sammy USA"),
+    (0x54, "GameTek"), (0x55, "Park Place"), (0x56, "LJN"),
+    (0x57, "Matchbox"), (0x59, "Milton Bradley"), (0x5A, "Mindscape"),
+    (0x5B, "Romstar"), (0x5C, "Naxat Soft"), (0x5D, "Tradewest"),
+    (0x60, "Titus"), (0x61, "Virgin Games"), (0x67, "Ocean"),
+    (0x69, "Electronic Arts"), (0x6E, "Elite Systems"),
+    (0x6F, "Electro Brain"), (0x70, "Infogrames"), (0x71, "Interplay"),
+    (0x72, "Broderbund"), (0x73, "Sculptured Software"),
+    (0x75, "The Sales Curve"), (0x78, "THQ"), (0x79, "Accolade"),
+    (0x7A, "Triffix Entertainment"), (0x7C, "MicroProse"), (0x7F, "Kemco"),
+    (0x80, "Misawa"), (0x83, "LOZC"), (0x86, "Tokuma Shoten"),
+    (0x8B, "Bullet-Proof Software"), (0x8C, "Vic Tokai"), (0x8E, "Ape"),
+    (0x8F, "I'Max"), (0x91, "Chunsoft"), (0x92, "Video System"),
+    (0x93, "Tsubaraya Productions"), (0x95, "Varie"),
+    (0x96, "Yonezawa/S'Pal"), (0x97, "Kaneko"), (0x99, "Arc"),
+    (0x9A, "Nihon Bussan"), (0x9B, "Tecmo"), (0x9C, "Imagineer"),
+    (0x9D, "Banpresto"), (0x9F, "Nova"), (0xA1, "Hori Electric"),
+    (0xA2, "Bandai"), (0xA4, "Konami"), (0xA6, "Kawada"), (0xA7, "Takara"),
+    (0xA9, "Technos Japan"), (0xAA, "Broderbund"), (0xAC, "Toei Animation"),
+    (0xAD, "Toho"), (0xAF, "Namco"), (0xB0, "Acclaim"),
+    (0xB1, "ASCII/Nexsoft"), (0xB2, "Bandai"), (0xB4, "Square Enix"),
+    (0xB6, "HAL Laboratory"), (0xB7, "SNK"), (0xB9, "Pony Canyon"),
+    (0xBA, "Culture Brain"), (0xBB, "Sunsoft"), (0xBD, "Sony Imagesoft"),
+    (0xBF, "Sammy"), (0xC0, "Taito"), (0xC2, "Kemco"), (0xC3, "Square"),
+    (0xC4, "Tokuma Shoten"), (0xC5, "Data East"), (0xC6, "Tonkin House"),
+    (0xC8, "Koei"), (0xC9, "UFL"), (0xCA, "Ultra"), (0xCB, "Vap"),
+    (0xCC, "Use"), (0xCD, "Meldac"), (0xCE, "Pony Canyon"), (0xCF, "Angel"),
+    (0xD0, "Taito"), (0xD1, "Sofel"), (0xD2, "Quest"),
+    (0xD3, "Sigma Enterprises"), (0xD4, "ASK Kodansha"),
+    (0xD6, "Naxat Soft"), (0xD7, "Copya System"), (0xD9, "Banpresto"),
+    (0xDA, "Tomy"), (0xDB, "LJN"), (0xDD, "NCS"), (0xDE, "Human"),
+    (0xDF, "Altron"), (0xE0, "Jaleco"), (0xE1, "Towa Chiki"),
+    (0xE2, "Yutaka"), (0xE3, "Varie"), (0xE5, "Epoch"), (0xE7, "Athena"),
+    (0xE8, "Asmik"), (0xE9, "Natsume"), (0xEA, "King Records"),
+    (0xEB, "Atlus"), (0xEC, "Epic/Sony Records"), (0xEE, "IGS"),
+    (0xF0, "A Wave"), (0xF3, "Extreme Entertainment"), (0xFF, "LJN"),
+];
+
+/// Printable ASCII up to the first NUL. CGB titles end early, where the CGB
+/// flag (0x80 or 0xC0) takes the last byte.
+pub fn title(rom: &[u8]) -> String {
+    rom.get(TITLE_START..=TITLE_END)
+        .unwrap_or(&[])
+        .iter()
+        .take_while(|b| **b != 0 && **b < 0x80)
+        .map(|b| {
+            if b.is_ascii_graphic() {
+                *b as char
+            } else {
+                ' '
+            }
+        })
+        .collect::<String>()
+        .trim_end()
+        .to_string()
+}
+
+/// Whether the game supports (0x80) or requires (0xC0) the CGB.
+pub fn cgb(rom: &[u8]) -> bool {
+    rom.get(CGB_FLAG).is_some_and(|flag| flag & 0x80 != 0)
+}
+
+/// Whether the game supports SGB functions, which also takes the old
+/// licensee code 0x33.
+pub fn sgb(rom: &[u8]) -> bool {
+    rom.get(SGB_FLAG) == Some(&0x03) && rom.get(OLD_LICENSEE) == Some(&0x33)
+}
+
+/// Big-endian sum of every ROM byte except these two.
+pub fn global_checksum(rom: &[u8]) -> Option<u16> {
+    let bytes = rom.get(GLOBAL_CHECKSUM..GLOBAL_CHECKSUM + 2)?;
+
+    Some(u16::from_be_bytes([bytes[0], bytes[1]]))
+}
+
+/// The decoded header, with its checksums and logo checked.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Info {
+    pub title: String,
+    /// Four upper case characters in later games' titles.
+    pub manufacturer: Option<String>,
+    pub cgb_flag: u8,
+    pub sgb_flag: u8,
+    pub cartridge_type: u8,
+    pub rom_size: u8,
+    pub ram_size: u8,
+    pub old_licensee: u8,
+    pub new_licensee: String,
+    pub version: u8,
+    pub header_checksum: u8,
+    pub header_checksum_ok: bool,
+    pub global_checksum: u16,
+    pub global_checksum_ok: bool,
+    pub logo_ok: bool,
+}
+
+impl Info {
+    pub fn new(rom: &[u8]) -> Result<Self, String> {
+        if rom.len() <= HEADER_END {
+            return Err("too small for a cartridge header".to_string());
+        }
+
+        let text = |range: std::ops::Range<usize>| {
+            String::from_utf8_lossy(&rom[range]).to_string()
+        };
+
+        let manufacturer = text(MANUFACTURER_START..CGB_FLAG);
+        let manufacturer = if manufacturer
+            .bytes()
+            .all(|b| b.is_ascii_uppercase() || b.is_ascii_digit())
+        {
+            Some(manufacturer)
+        } else {
+            None
+        };
+
+        // x = x - byte - 1 over the title to the version
+        let header_checksum = rom[TITLE_START..HEADER_CHECKSUM]
+            .iter()
+            .fold(0u8, |x, b| x.wrapping_sub(*b).wrapping_sub(1));
+
+        let sum = rom
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| *i != GLOBAL_CHECKSUM && *i != HEADER_END)
+            .fold(0u16, |sum, (_, b)| sum.wrapping_add(*b as u16));
+
+        Ok(Self {
+            title: title(rom),
+            manufacturer,
+            cgb_flag: rom[CGB_FLAG],
+            sgb_flag: rom[SGB_FLAG],
+            cartridge_type: rom[CARTRIDGE_TYPE],
+            rom_size: rom[ROM_SIZE],
+            ram_size: rom[RAM_SIZE],
+            old_licensee: rom[OLD_LICENSEE],
+            new_licensee: text(NEW_LICENSEE..SGB_FLAG),
+            version: rom[VERSION],
+            header_checksum: rom[HEADER_CHECKSUM],
+            header_checksum_ok: rom[HEADER_CHECKSUM] == header_checksum,
+            global_checksum: global_checksum(rom).unwrap_or(0),
+            global_checksum_ok: global_checksum(rom) == Some(sum),
+            logo_ok: rom[LOGO_START..LOGO_START + LOGO.len()] == LOGO,
+        })
+    }
+
+    pub fn cartridge_type_name(&self) -> Option<&'static str> {
+        CARTRIDGE_TYPES
+            .iter()
+            .find(|(code, _)| *code == self.cartridge_type)
+            .map(|(_, name)| *name)
+    }
+
+    /// The publisher, from the new code if the old one says so.
+    pub fn licensee(&self) -> Option<&'static str> {
+        if self.old_licensee == USE_NEW_LICENSEE {
+            NEW_LICENSEES
+                .iter()
+                .find(|(code, _)| *code == self.new_licensee)
+                .map(|(_, name)| *name)
+        } else {
+            OLD_LICENSEES
+                .iter()
+                .find(|(code, _)| *code == self.old_licensee)
+                .map(|(_, name)| *name)
+        }
+    }
+
+    /// 32 KiB shifted left by the size code.
+    pub fn rom_bytes(&self) -> Option<usize> {
+        match self.rom_size {
+            0..=8 => Some(0x8000 << self.rom_size),
+            _ => None,
+        }
+    }
+
+    pub fn ram_bytes(&self) -> Option<usize> {
+        match self.ram_size {
+            0 => Some(0),
+            2 => Some(0x2000),
+            3 => Some(0x8000),
+            4 => Some(0x20000),
+            5 => Some(0x10000),
+            _ => None,
+        }
+    }
+
+    /// Whether the MMU can map this cartridge.
+    pub fn mapper_supported(&self) -> bool {
+        SUPPORTED_TYPES.contains(&self.cartridge_type)
+            && self.rom_bytes() == Some(SUPPORTED_ROM_SIZE)
+    }
+
+    pub fn checks_ok(&self) -> bool {
+        self.header_checksum_ok && self.global_checksum_ok && self.logo_ok
+    }
+
+    /// One JSON object, for auditing collections with other tools.
+    pub fn to_json(&self) -> String {
+        let string = |s: Option<&str>| match s {
+            Some(s) => json_string(s),
+            None => "null".to_string(),
+        };
+        let size = |bytes: Option<usize>| match bytes {
+            Some(bytes) => bytes.to_string(),
+            None => "null".to_string(),
+        };
+
+        let fields = [
+            ("title", json_string(&self.title)),
+            ("manufacturer", string(self.manufacturer.as_deref())),
+            ("cgb_flag", self.cgb_flag.to_string()),
+            ("cgb", cgb_mode(self.cgb_flag).is_some().to_string()),
+            ("sgb", (self.sgb_flag == 0x03).to_string()),
+            ("old_licensee", self.old_licensee.to_string()),
+            ("new_licensee", json_string(&self.new_licensee)),
+            ("licensee", string(self.licensee())),
+            ("cartridge_type", self.cartridge_type.to_string()),
+            ("cartridge_type_name", string(self.cartridge_type_name())),
+            ("rom_size", size(self.rom_bytes())),
+            ("ram_size", size(self.ram_bytes())),
+            ("version", self.version.to_string()),
+            ("header_checksum", self.header_checksum.to_string()),
+            ("header_checksum_ok", self.header_checksum_ok.to_string()),
+            ("global_checksum", self.global_checksum.to_string()),
+            ("global_checksum_ok", self.global_checksum_ok.to_string()),
+            ("logo_ok", self.logo_ok.to_string()),
+            ("mapper_supported", self.mapper_supported().to_string()),
+        ];
+
+        let fields: Vec<String> = fields
+            .iter()
+            .map(|(key, value)| format!("\"{}\":{}", key, value))
+            .collect();
+
+        format!("{{{}}}", fields.join(","))
+    }
+}
+
+impl fmt::Display for Info {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let ok = |ok: bool| if ok { "ok" } else { "FAIL" };
+        let size = |bytes: Option<usize>| match bytes {
+            Some(bytes) => format!("{} KiB", bytes / 1024),
+            None => "unknown".to_string(),
+        };
+
+        writeln!(f, "Title:            {}", self.title)?;
+        writeln!(
+            f,
+            "Manufacturer:     {}",
+            self.manufacturer.as_deref().unwrap_or("-")
+        )?;
+        writeln!(
+            f,
+            "CGB:              {:02X} ({})",
+            self.cgb_flag,
+            cgb_mode(self.cgb_flag).unwrap_or("no")
+        )?;
+        writeln!(
+            f,
+            "SGB:              {:02X} ({})",
+            self.sgb_flag,
+            if self.sgb_flag == 0x03 { "yes" } else { "no" }
+        )?;
+        writeln!(
+            f,
+            "Licensee:         old {:02X}, new {:?} ({})",
+            self.old_licensee,
+            self.new_licensee,
+            self.licensee().unwrap_or("unknown")
+        )?;
+        writeln!(
+            f,
+            "Cartridge type:   {:02X} ({})",
+            self.cartridge_type,
+            self.cartridge_type_name().unwrap_or("unknown")
+        )?;
+        writeln!(
+            f,
+            "ROM size:         {:02X} ({})",
+            self.rom_size,
+            size(self.rom_bytes())
+        )?;
+        writeln!(
+            f,
+            "RAM size:         {:02X} ({})",
+            self.ram_size,
+            size(self.ram_bytes())
+        )?;
+        writeln!(f, "Version:          {}", self.version)?;
+        writeln!(
+            f,
+            "Header checksum:  {:02X} ({})",
+            self.header_checksum,
+            ok(self.header_checksum_ok)
+        )?;
+        writeln!(
+            f,
+            "Global checksum:  {:04X} ({})",
+            self.global_checksum,
+            ok(self.global_checksum_ok)
+        )?;
+        writeln!(f, "Logo:             {}", ok(self.logo_ok))?;
+        write!(
+            f,
+            "Mapper:           {}",
+            if self.mapper_supported() {
+                "supported"
+            } else {
+                "not supported"
+            }
+        )
+    }
+}
+
+/// The `info` subcommand's report on a ROM file, as text or one line of
+/// JSON, and its exit code: 1 if a check fails, 2 if it can't be read.
+pub fn report(path: &str, json: bool) -> (String, i32) {
+    let info = archive::load_rom(path, None).and_then(|rom| {
+        Info::new(&rom).map_err(|e| format!("{}: {}", path, e))
+    });
+
+    let code = match &info {
+        Ok(info) if info.checks_ok() => 0,
+        Ok(_) => 1,
+        Err(_) => 2,
+    };
+
+    let text = match (info, json) {
+        (Ok(info), false) => format!("{}\n{}\n", path, info),
+        (Ok(info), true) => format!(
+            "{{\"path\":{},\"info\":{}}}",
+            json_string(path),
+            info.to_json()
+        ),
+        (Err(e), false) => format!("{}\n", e),
+        (Err(e), true) => format!(
+            "{{\"path\":{},\"error\":{}}}",
+            json_string(path),
+            json_string(&e)
+        ),
+    };
+
+    (text, code)
+}
+
+fn cgb_mode(flag: u8) -> Option<&'static str> {
+    match flag {
+        0xC0 => Some("CGB only"),
+        0x80 => Some("CGB enhanced"),
+        _ => None,
+    }
+}
+
+fn json_string(s: &str) -> String {
+    let mut out = String::from("\"");
+
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            c if (c as u32) < 0x20 => {
+                out.push_str(&format!("\\u{:04x}", c as u32))
+            }
+            c => out.push(c),
+        }
+    }
+
+    out.push('"');
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_reads_titles_and_checksums() {
+        let mut rom = vec![0; 0x150];
+        rom[TITLE_START..TITLE_START + 6].copy_from_slice(b"TETRIS");
+        rom[GLOBAL_CHECKSUM] = 0x16;
+        rom[GLOBAL_CHECKSUM + 1] = 0xBF;
+
+        assert_eq!(title(&rom), "TETRIS");
+        assert_eq!(global_checksum(&rom), Some(0x16BF));
+
+        rom[TITLE_START..=TITLE_END].copy_from_slice(b"POKEMON YELLOW\0\x80");
+        assert_eq!(title(&rom), "POKEMON YELLOW");
+        assert!(cgb(&rom));
+        assert!(!sgb(&rom));
+
+        rom[SGB_FLAG] = 0x03;
+        rom[OLD_LICENSEE] = 0x33;
+        assert!(sgb(&rom));
+
+        assert_eq!(title(&[]), "");
+        assert_eq!(global_checksum(&[]), None);
+    }
+
+    #[test]
+    fn it_decodes_and_checks_headers() {
+        let mut rom = vec![0; 0x8000];
+        rom[LOGO_START..LOGO_START + LOGO.len()].copy_from_slice(&LOGO);
+        rom[TITLE_START..TITLE_START + 4].copy_from_slice(b"GAME");
+        rom[NEW_LICENSEE..SGB_FLAG].copy_from_slice(b"01");
+        rom[OLD_LICENSEE] = USE_NEW_LICENSEE;
+        rom[CARTRIDGE_TYPE] = 0x09;
+        rom[RAM_SIZE] = 2;
+        rom[HEADER_CHECKSUM] = 0x2E;
+
+        let mut info = Info::new(&rom).unwrap();
+        assert!(info.header_checksum_ok);
+        assert!(!info.global_checksum_ok);
+        assert_eq!(info.manufacturer, None);
+        assert_eq!(info.licensee(), Some("Nintendo R&D1"));
+        assert_eq!(info.cartridge_type_name(), Some("ROM+RAM+BATTERY"));
+        assert_eq!(info.rom_bytes(), Some(0x8000));
+        assert_eq!(info.ram_bytes(), Some(0x2000));
+        assert!(info.mapper_supported());
+
+        let sum = rom.iter().fold(0u16, |s, b| s.wrapping_add(*b as u16));
+        rom[GLOBAL_CHECKSUM..=HEADER_END].copy_from_slice(&sum.to_be_bytes());
+        assert!(Info::new(&rom).unwrap().checks_ok());
+
+        rom[LOGO_START] ^= 1;
+        assert!(!Info::new(&rom).unwrap().logo_ok);
+
+        info.title = "A \"B\"".to_string();
+        info.cartridge_type = 0x13;
+        assert!(!info.mapper_supported());
+        assert!(info.to_json().starts_with(r#"{"title":"A \"B\"","#));
+        assert!(info.to_json().contains(r#""cartridge_type_name":"MBC3+"#));
+
+        assert!(Info::new(&rom[..0x100]).is_err());
+    }
+}
@@ -0,0 +1,234 @@
+use crate::{debugger::Debugger, wav, Cpu};
+
+/// T-cycles per frame: 154 lines of 456 ticks.
+pub const CYCLES_PER_FRAME: u64 = 70224;
+
+const LD_B_B: u8 = 0x40;
+
+/// Executes one instruction and returns its length in single-speed cycles,
+/// which is what frames are measured in.
+pub fn step(cpu: &mut Cpu) -> Result<u64, String> {
+    let double_speed = cpu.bus.double_speed();
+    let ticks = cpu.step()? as u64;
+
+    Ok(if double_speed { ticks / 2 } else { ticks })
+}
+
+/// Steps through one frame's worth of cycles. An instruction running past
+/// the end of the frame borrows the excess from the next one.
+pub fn run_frame(cpu: &mut Cpu, overrun: &mut u64) -> Result<(), String> {
+    let mut cycles = *overrun;
+
+    while cycles < CYCLES_PER_FRAME {
+        cycles += step(cpu)?;
+    }
+
+    *overrun = cycles - CYCLES_PER_FRAME;
+
+    Ok(())
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Outcome {
+    Serial,
+    Memory,
+    Breakpoint(u16),
+    SoftwareBreakpoint,
+    Timeout,
+}
+
+/// Runs a ROM without a display until a condition holds or the frame budget
+/// runs out. Frames are counted in cycles, so a program that keeps the LCD
+/// off still times out.
+pub struct Headless {
+    pub frames: u64,
+    pub serial: Option<String>,
+    pub memory: Option<(u16, u8)>,
+    /// Stop after `LD B, B`, the software breakpoint Mooneye test ROMs
+    /// signal completion with.
+    pub ld_b_b: bool,
+    /// Joypad state per frame, e.g. from a movie.
+    pub inputs: Vec<u8>,
+    /// Where to write the sound, see `wav::Dump`.
+    pub audio: Option<wav::Dump>,
+    pub debugger: Debugger,
+}
+
+impl Headless {
+    pub fn new(frames: u64) -> Self {
+        Self {
+            frames,
+            serial: None,
+            memory: None,
+            ld_b_b: false,
+            inputs: Vec::new(),
+            audio: None,
+            debugger: Debugger::new(),
+        }
+    }
+
+    /// Fails on an instruction the CPU can't execute.
+    pub fn run(&mut self, cpu: &mut Cpu) -> Result<Outcome, String> {
+        cpu.bus.apu.set_capture(self.audio.is_some());
+
+        let outcome = self.run_until_stopped(cpu);
+
+        if let Some(audio) = &mut self.audio {
+            audio.write(&cpu.bus.apu.take_samples());
+        }
+
+        outcome
+    }
+
+    fn run_until_stopped(&mut self, cpu: &mut Cpu) -> Result<Outcome, String> {
+        let budget = self.frames * CYCLES_PER_FRAME;
+
+        let mut cycles: u64 = 0;
+        let mut serial_len = cpu.bus.serial_output().len();
+        let mut next_frame = 0;
+
+        while cycles < budget {
+            if cycles >= next_frame as u64 * CYCLES_PER_FRAME {
+                if let Some(buttons) = self.inputs.get(next_frame) {
+                    cpu.bus.joypad.set(*buttons);
+                }
+
+                if let Some(audio) = &mut self.audio {
+                    audio.write(&cpu.bus.apu.take_samples());
+                }
+
+                next_frame += 1;
+            }
+
+            let opcode = cpu.bus.peek_byte(cpu.pc.get());
+
+            cycles += step(cpu)?;
+
+            if self.ld_b_b && opcode == Some(LD_B_B) {
+                return Ok(Outcome::SoftwareBreakpoint);
+            }
+
+            if self.debugger.is_breakpoint(cpu, cpu.pc.get()) {
+                return Ok(Outcome::Breakpoint(cpu.pc.get()));
+            }
+
+            if let Some((address, val)) = self.memory {
+                if cpu.bus.peek_byte(address) == Some(val) {
+                    return Ok(Outcome::Memory);
+                }
+            }
+
+            let output = cpu.bus.serial_output();
+
+            if output.len() != serial_len {
+                serial_len = output.len();
+
+                let found = self.serial.as_ref().is_some_and(|text| {
+                    String::from_utf8_lossy(output).contains(text.as_str())
+                });
+
+                if found {
+                    return Ok(Outcome::Serial);
+                }
+            }
+        }
+
+        Ok(Outcome::Timeout)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{joypad, mmu::ROM_BANK_0_SIZE};
+
+    fn cpu(program: &[u8]) -> Cpu {
+        let mut rom = vec![0; ROM_BANK_0_SIZE * 2];
+        rom[0x100..0x100 + program.len()].copy_from_slice(program);
+
+        Cpu::post_boot(rom)
+    }
+
+    #[rustfmt::skip]
+    const SERIAL: [u8; 11] = [
+        0x3E, b'O',       // LD A, 'O'
+        0xE0, 0x01,       // LD (FF00+01), A
+        0x3E, 0x81,       // LD A, 0x81
+        0xE0, 0x02,       // LD (FF00+02), A
+        0x00,             // NOP
+        0x18, 0xFD,       // JR -3
+    ];
+
+    #[test]
+    fn it_stops_on_serial_output() {
+        let mut cpu = cpu(&SERIAL);
+        let mut headless = Headless::new(1);
+        headless.serial = Some("O".to_string());
+
+        assert_eq!(headless.run(&mut cpu), Ok(Outcome::Serial));
+        assert_eq!(cpu.bus.serial_output(), b"O");
+    }
+
+    #[test]
+    fn it_stops_on_memory_values() {
+        let mut cpu = cpu(&SERIAL);
+        let mut headless = Headless::new(1);
+        headless.memory = Some((0xFF01, b'O'));
+
+        assert_eq!(headless.run(&mut cpu), Ok(Outcome::Memory));
+        assert_eq!(cpu.pc.get(), 0x104);
+    }
+
+    #[test]
+    fn it_stops_on_breakpoints() {
+        let mut cpu = cpu(&SERIAL);
+        let mut headless = Headless::new(1);
+        headless.debugger.add_breakpoint(0x108);
+
+        assert_eq!(headless.run(&mut cpu), Ok(Outcome::Breakpoint(0x108)));
+    }
+
+    #[test]
+    fn it_stops_after_ld_b_b() {
+        let mut cpu = cpu(&[0x06, 0x03, 0x40, 0x18, 0xFE]);
+        let mut headless = Headless::new(1);
+        headless.ld_b_b = true;
+
+        assert_eq!(headless.run(&mut cpu), Ok(Outcome::SoftwareBreakpoint));
+        assert_eq!(cpu.pc.get(), 0x103);
+        assert_eq!(cpu.registers.b, 3);
+    }
+
+    #[test]
+    fn it_applies_inputs_per_frame() {
+        let mut cpu = cpu(&SERIAL);
+        let mut headless = Headless::new(2);
+        headless.inputs = vec![joypad::UP, joypad::START];
+
+        headless.run(&mut cpu).unwrap();
+
+        assert_eq!(cpu.bus.joypad.pressed(), joypad::START);
+    }
+
+    #[test]
+    fn it_times_out_after_the_frame_budget() {
+        let mut cpu = cpu(&SERIAL);
+        let mut headless = Headless::new(2);
+        headless.serial = Some("Passed".to_string());
+
+        assert_eq!(headless.run(&mut cpu), Ok(Outcome::Timeout));
+        assert_eq!(cpu.bus.gpu.frames(), 2);
+    }
+
+    #[test]
+    fn it_fails_on_unimplemented_instructions() {
+        // NOP, then DI
+        let mut cpu = cpu(&[0x00, 0xF3]);
+
+        assert_eq!(
+            Headless::new(1).run(&mut cpu),
+            Err("unimplemented instruction DI at 0101".to_string())
+        );
+        assert_eq!(cpu.pc.get(), 0x101);
+    }
+}
@@ -0,0 +1,441 @@
+//! DEFLATE (RFC 1951) decompression, with the zlib (RFC 1950) and gzip
+//! (RFC 1952) wrappers.
+
+use crate::checksum::{adler32, crc32};
+
+const MAX_BITS: usize = 15;
+
+const LENGTH_BASE: [u16; 29] = [
+    3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59,
+    67, 83, 99, 115, 131, 163, 195, 227, 258,
+];
+const LENGTH_EXTRA: [u8; 29] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5,
+    5, 5, 5, 0,
+];
+const DIST_BASE: [u16; 30] = [
+    1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513,
+    769, 1025, 1537, 2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577,
+];
+const DIST_EXTRA: [u8; 30] = [
+    0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10,
+    11, 11, 12, 12, 13, 13,
+];
+// Order code length code lengths are stored in.
+const CL_ORDER: [usize; 19] = [
+    16, 17, 18, 0, 8, 7, 9, 6, 10, 5, 11, 4, 12, 3, 13, 2, 14, 1, 15,
+];
+
+struct Bits<'a> {
+    data: &'a [u8],
+    pos: usize,
+    bit: u32,
+    count: u32,
+}
+
+impl<'a> Bits<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self {
+            data,
+            pos: 0,
+            bit: 0,
+            count: 0,
+        }
+    }
+
+    fn need(&mut self, n: u32) -> Result<u32, String> {
+        while self.count < n {
+            let byte = *self.data.get(self.pos).ok_or("unexpected end")?;
+
+            self.pos += 1;
+            self.bit |= (byte as u32) << self.count;
+            self.count += 8;
+        }
+
+        let val = self.bit & ((1 << n) - 1);
+
+        self.bit >>= n;
+        self.count -= n;
+
+        Ok(val)
+    }
+
+    fn align(&mut self) {
+        self.bit = 0;
+        self.count = 0;
+    }
+}
+
+/// Canonical Huffman code: symbol counts per length and symbols by code.
+struct Huffman {
+    counts: [u16; MAX_BITS + 1],
+    symbols: Vec<u16>,
+}
+
+impl Huffman {
+    fn new(lengths: &[u8]) -> Result<Self, String> {
+        let mut counts = [0; MAX_BITS + 1];
+
+        for len in lengths {
+            counts[*len as usize] += 1;
+        }
+
+        counts[0] = 0;
+
+        // Codes left unused: over-subscribed lengths would decode past
+        // `symbols`, and only a lone one-bit code may leave some unused.
+        let mut left: i32 = 1;
+
+        for count in &counts[1..] {
+            left = (left << 1) - *count as i32;
+
+            if left < 0 {
+                return Err("invalid Huffman code".to_string());
+            }
+        }
+
+        if left > 0 && counts[2..].iter().any(|count| *count > 0) {
+            return Err("invalid Huffman code".to_string());
+        }
+
+        let mut offsets = [0; MAX_BITS + 2];
+
+        for len in 1..=MAX_BITS {
+            offsets[len + 1] = offsets[len] + counts[len];
+        }
+
+        let mut symbols = vec![0; lengths.len()];
+
+        for (symbol, len) in lengths.iter().enumerate() {
+            if *len != 0 {
+                symbols[offsets[*len as usize] as usize] = symbol as u16;
+                offsets[*len as usize] += 1;
+            }
+        }
+
+        Ok(Self { counts, symbols })
+    }
+
+    fn decode(&self, bits: &mut Bits) -> Result<u16, String> {
+        let mut code: i32 = 0;
+        let mut first: i32 = 0;
+        let mut index: i32 = 0;
+
+        for len in 1..=MAX_BITS {
+            code |= bits.need(1)? as i32;
+
+            let count = self.counts[len] as i32;
+
+            if code - count < first {
+                return Ok(self.symbols[(index + (code - first)) as usize]);
+            }
+
+            index += count;
+            first += count;
+            first <<= 1;
+            code <<= 1;
+        }
+
+        Err("invalid Huffman code".to_string())
+    }
+}
+
+fn fixed() -> (Huffman, Huffman) {
+    let mut lengths = [0u8; 288];
+
+    lengths[..144].iter_mut().for_each(|l| *l = 8);
+    lengths[144..256].iter_mut().for_each(|l| *l = 9);
+    lengths[256..280].iter_mut().for_each(|l| *l = 7);
+    lengths[280..].iter_mut().for_each(|l| *l = 8);
+
+    (
+        Huffman::new(&lengths).unwrap(),
+        // Distance codes 30 and 31 exist but are invalid
+        Huffman::new(&[5; 32]).unwrap(),
+    )
+}
+
+fn dynamic(bits: &mut Bits) -> Result<(Huffman, Huffman), String> {
+    let nlen = bits.need(5)? as usize + 257;
+    let ndist = bits.need(5)? as usize + 1;
+    let ncode = bits.need(4)? as usize + 4;
+
+    let mut cl_lengths = [0u8; 19];
+
+    for i in CL_ORDER.iter().take(ncode) {
+        cl_lengths[*i] = bits.need(3)? as u8;
+    }
+
+    let cl = Huffman::new(&cl_lengths)?;
+
+    let mut lengths = vec![0u8; nlen + ndist];
+    let mut i = 0;
+
+    while i < nlen + ndist {
+        let symbol = cl.decode(bits)?;
+
+        let (val, repeat) = match symbol {
+            0..=15 => (symbol as u8, 1),
+            16 => {
+                let prev =
+                    *lengths[..i].last().ok_or("repeat with no length")?;
+
+                (prev, 3 + bits.need(2)? as usize)
+            }
+            17 => (0, 3 + bits.need(3)? as usize),
+            _ => (0, 11 + bits.need(7)? as usize),
+        };
+
+        if i + repeat > lengths.len() {
+            return Err("too many code lengths".to_string());
+        }
+
+        lengths[i..i + repeat].iter_mut().for_each(|l| *l = val);
+        i += repeat;
+    }
+
+    Ok((
+        Huffman::new(&lengths[..nlen])?,
+        Huffman::new(&lengths[nlen..])?,
+    ))
+}
+
+fn codes(
+    bits: &mut Bits,
+    out: &mut Vec<u8>,
+    lit: &Huffman,
+    dist: &Huffman,
+) -> Result<(), String> {
+    loop {
+        let symbol = lit.decode(bits)? as usize;
+
+        match symbol {
+            0..=255 => out.push(symbol as u8),
+            256 => return Ok(()),
+            _ => {
+                let i = symbol - 257;
+
+                if i >= LENGTH_BASE.len() {
+                    return Err("invalid length symbol".to_string());
+                }
+
+                let len = LENGTH_BASE[i] as usize
+                    + bits.need(LENGTH_EXTRA[i] as u32)? as usize;
+
+                let d = dist.decode(bits)? as usize;
+
+                if d >= DIST_BASE.len() {
+                    return Err("invalid distance symbol".to_string());
+                }
+
+                let distance = DIST_BASE[d] as usize
+                    + bits.need(DIST_EXTRA[d] as u32)? as usize;
+
+                if distance > out.len() {
+                    return Err("distance too far back".to_string());
+                }
+
+                let start = out.len() - distance;
+
+                for k in 0..len {
+                    out.push(out[start + k]);
+                }
+            }
+        }
+    }
+}
+
+/// Decompresses a raw DEFLATE stream; returns the data and the number of
+/// input bytes consumed.
+pub fn inflate(data: &[u8]) -> Result<(Vec<u8>, usize), String> {
+    let mut bits = Bits::new(data);
+    let mut out = Vec::new();
+
+    loop {
+        let last = bits.need(1)? == 1;
+
+        match bits.need(2)? {
+            0 => {
+                bits.align();
+
+                let header =
+                    data.get(bits.pos..bits.pos + 4).ok_or("unexpected end")?;
+                let len = u16::from_le_bytes([header[0], header[1]]) as usize;
+                let nlen = u16::from_le_bytes([header[2], header[3]]);
+
+                if len as u16 != !nlen {
+                    return Err("stored block length mismatch".to_string());
+                }
+
+                let start = bits.pos + 4;
+                let block =
+                    data.get(start..start + len).ok_or("unexpected end")?;
+
+                out.extend_from_slice(block);
+                bits.pos = start + len;
+            }
+            1 => {
+                let (lit, dist) = fixed();
+
+                codes(&mut bits, &mut out, &lit, &dist)?;
+            }
+            2 => {
+                let (lit, dist) = dynamic(&mut bits)?;
+
+                codes(&mut bits, &mut out, &lit, &dist)?;
+            }
+            _ => return Err("invalid block type".to_string()),
+        }
+
+        if last {
+            return Ok((out, bits.pos));
+        }
+    }
+}
+
+/// Decompresses a zlib stream and checks its Adler-32.
+pub fn zlib(data: &[u8]) -> Result<Vec<u8>, String> {
+    if data.len() < 6 || data[0] & 0x0F != 8 {
+        return Err("not a zlib stream".to_string());
+    }
+
+    if !((data[0] as u16) << 8 | data[1] as u16).is_multiple_of(31) {
+        return Err("bad zlib header".to_string());
+    }
+
+    if data[1] & 0x20 != 0 {
+        return Err("zlib preset dictionaries are unsupported".to_string());
+    }
+
+    let (out, used) = inflate(&data[2..])?;
+
+    let trailer = data.get(2 + used..2 + used + 4).ok_or("unexpected end")?;
+
+    if u32::from_be_bytes([trailer[0], trailer[1], trailer[2], trailer[3]])
+        != adler32(&out)
+    {
+        return Err("zlib checksum mismatch".to_string());
+    }
+
+    Ok(out)
+}
+
+/// Decompresses the first member of a gzip file and checks its CRC-32.
+pub fn gzip(data: &[u8]) -> Result<Vec<u8>, String> {
+    const FHCRC: u8 = 1 << 1;
+    const FEXTRA: u8 = 1 << 2;
+    const FNAME: u8 = 1 << 3;
+    const FCOMMENT: u8 = 1 << 4;
+
+    if data.len() < 18 || data[..3] != [0x1F, 0x8B, 8] {
+        return Err("not a gzip file".to_string());
+    }
+
+    let flags = data[3];
+    let mut pos = 10;
+
+    if flags & FEXTRA != 0 {
+        let len = *data.get(pos).ok_or("unexpected end")? as usize
+            | (*data.get(pos + 1).ok_or("unexpected end")? as usize) << 8;
+
+        pos += 2 + len;
+    }
+
+    for flag in [FNAME, FCOMMENT].iter() {
+        if flags & flag != 0 {
+            let end = data
+                .get(pos..)
+                .and_then(|rest| rest.iter().position(|b| *b == 0))
+                .ok_or("unexpected end")?;
+
+            pos += end + 1;
+        }
+    }
+
+    if flags & FHCRC != 0 {
+        pos += 2;
+    }
+
+    let (out, used) = inflate(data.get(pos..).ok_or("unexpected end")?)?;
+
+    let trailer = data
+        .get(pos + used..pos + used + 8)
+        .ok_or("unexpected end")?;
+
+    let crc =
+        u32::from_le_bytes([trailer[0], trailer[1], trailer[2], trailer[3]]);
+
+    if crc != crc32(&out) {
+        return Err("gzip checksum mismatch".to_string());
+    }
+
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // zlib.compress(b"Hello, Hello, Hello, rboy!")
+    const FIXED: [u8; 22] = [
+        0x78, 0x9C, 0xF3, 0x48, 0xCD, 0xC9, 0xC9, 0xD7, 0x51, 0xF0, 0x40, 0xA1,
+        0x8A, 0x92, 0xF2, 0x2B, 0x15, 0x01, 0x74, 0xF1, 0x08, 0x9E,
+    ];
+
+    // zlib.compress(bytes((i * i // 7) % 13 + 65 for i in range(600)), 9)
+    const DYNAMIC: [u8; 76] = [
+        0x78, 0xDA, 0xED, 0xCC, 0xD1, 0x0D, 0xC0, 0x30, 0x08, 0x03, 0xD1, 0xD9,
+        0x0C, 0x06, 0x5C, 0x42, 0xF6, 0x5F, 0xA7, 0xA9, 0xD4, 0x2D, 0xDA, 0xFB,
+        0x7E, 0x3A, 0x00, 0xE6, 0x4C, 0xF5, 0x58, 0x68, 0xBC, 0x96, 0x6B, 0xE7,
+        0xC4, 0x8A, 0x49, 0x5C, 0x1C, 0x71, 0x77, 0x11, 0xD3, 0xAA, 0x0C, 0x9E,
+        0x22, 0xEB, 0x58, 0xB0, 0x7A, 0x53, 0xC3, 0x0B, 0x2F, 0xDE, 0xF2, 0x55,
+        0x3E, 0x0A, 0x3B, 0x36, 0xE9, 0x86, 0xA7, 0x7F, 0xFD, 0xD5, 0xF5, 0x0D,
+        0x91, 0x3F, 0xA5, 0xBD,
+    ];
+
+    // gzip.compress(b"rboy\n" * 10, mtime=0)
+    const GZIP: [u8; 28] = [
+        0x1F, 0x8B, 0x08, 0x00, 0x00, 0x00, 0x00, 0x00, 0x02, 0x03, 0x2B, 0x4A,
+        0xCA, 0xAF, 0xE4, 0x2A, 0x22, 0x85, 0x00, 0x00, 0x86, 0xF7, 0x76, 0x9F,
+        0x32, 0x00, 0x00, 0x00,
+    ];
+
+    #[test]
+    fn it_inflates_fixed_huffman_blocks() {
+        assert_eq!(zlib(&FIXED).unwrap(), b"Hello, Hello, Hello, rboy!");
+    }
+
+    #[test]
+    fn it_inflates_dynamic_huffman_blocks() {
+        let expected: Vec<u8> =
+            (0..600u32).map(|i| ((i * i / 7) % 13 + 65) as u8).collect();
+
+        assert_eq!(zlib(&DYNAMIC).unwrap(), expected);
+    }
+
+    #[test]
+    fn it_unwraps_gzip() {
+        assert_eq!(gzip(&GZIP).unwrap(), b"rboy\n".repeat(10));
+    }
+
+    #[test]
+    fn it_rejects_corrupt_streams() {
+        let mut data = FIXED;
+        data[20] ^= 0xFF;
+
+        assert!(zlib(&data).is_err());
+        assert!(zlib(&FIXED[..10]).is_err());
+        assert!(gzip(&FIXED).is_err());
+    }
+
+    #[test]
+    fn it_rejects_invalid_huffman_codes() {
+        // Over-subscribed, then incomplete
+        assert!(Huffman::new(&[1, 1, 1]).is_err());
+        assert!(Huffman::new(&[2, 2, 2]).is_err());
+
+        assert!(Huffman::new(&[1, 2, 2]).is_ok());
+        assert!(Huffman::new(&[0, 1]).is_ok());
+        assert!(Huffman::new(&[0; 4]).is_ok());
+    }
+}
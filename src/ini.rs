@@ -0,0 +1,82 @@
+//! The INI dialect config and palette files are written in: `[section]`
+//! headers, `key = value` lines and `#` or `;` comments.
+
+#[derive(Debug, PartialEq)]
+pub struct Entry {
+    /// 1-based, for error messages.
+    pub line: usize,
+    pub key: String,
+    pub value: String,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Section {
+    /// Empty for entries before the first header.
+    pub name: String,
+    pub entries: Vec<Entry>,
+}
+
+pub fn parse(text: &str) -> Result<Vec<Section>, String> {
+    let mut sections = vec![Section {
+        name: String::new(),
+        entries: Vec::new(),
+    }];
+
+    for (n, line) in text.lines().enumerate() {
+        let line = line.trim();
+
+        if line.is_empty() || line.starts_with('#') || line.starts_with(';') {
+            continue;
+        }
+
+        if line.starts_with('[') && line.ends_with(']') {
+            sections.push(Section {
+                name: line[1..line.len() - 1].trim().to_string(),
+                entries: Vec::new(),
+            });
+
+            continue;
+        }
+
+        let mut parts = line.splitn(2, '=');
+        let key = parts.next().unwrap_or("").trim();
+
+        let value = parts
+            .next()
+            .ok_or_else(|| format!("line {}: expected key = value", n + 1))?;
+
+        sections.last_mut().unwrap().entries.push(Entry {
+            line: n + 1,
+            key: key.to_string(),
+            value: value.trim().to_string(),
+        });
+    }
+
+    Ok(sections)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_parses_sections_and_entries() {
+        let sections =
+            parse("top = 1\n\n# comment\n[ a b ]\nkey = some value\n").unwrap();
+
+        assert_eq!(sections.len(), 2);
+        assert_eq!(sections[0].name, "");
+        assert_eq!(sections[0].entries[0].key, "top");
+        assert_eq!(sections[1].name, "a b");
+        assert_eq!(
+            sections[1].entries,
+            vec![Entry {
+                line: 5,
+                key: "key".to_string(),
+                value: "some value".to_string(),
+            }]
+        );
+
+        assert!(parse("[x]\njust a line").is_err());
+    }
+}
@@ -0,0 +1,289 @@
+//! The hardware registers at 0xFF00-0xFF7F and IE, decoded into their
+//! fields, for the debugger's `io` command and the live panel.
+
+use crate::mmu::Mmu;
+
+/// A field's name, lowest bit and width in bits.
+type Field = (&'static str, u8, u8);
+
+pub struct Register {
+    pub address: u16,
+    pub name: &'static str,
+    /// Only there in CGB mode.
+    pub cgb: bool,
+    pub fields: &'static [Field],
+}
+
+const fn reg(
+    address: u16,
+    name: &'static str,
+    fields: &'static [Field],
+) -> Register {
+    Register {
+        address,
+        name,
+        cgb: false,
+        fields,
+    }
+}
+
+const fn cgb(
+    address: u16,
+    name: &'static str,
+    fields: &'static [Field],
+) -> Register {
+    Register {
+        address,
+        name,
+        cgb: true,
+        fields,
+    }
+}
+
+const INTERRUPTS: &[Field] = &[
+    ("joypad", 4, 1),
+    ("serial", 3, 1),
+    ("timer", 2, 1),
+    ("STAT", 1, 1),
+    ("VBlank", 0, 1),
+];
+
+const LENGTH_DUTY: &[Field] = &[("duty", 6, 2), ("length", 0, 6)];
+
+const ENVELOPE: &[Field] =
+    &[("volume", 4, 4), ("increase", 3, 1), ("pace", 0, 3)];
+
+const PERIOD_HIGH: &[Field] = &[
+    ("trigger", 7, 1),
+    ("length enable", 6, 1),
+    ("period high", 0, 3),
+];
+
+const SHADES: &[Field] = &[
+    ("color 3", 6, 2),
+    ("color 2", 4, 2),
+    ("color 1", 2, 2),
+    ("color 0", 0, 2),
+];
+
+const PALETTE_SPEC: &[Field] = &[("auto increment", 7, 1), ("address", 0, 6)];
+
+#[rustfmt::skip]
+pub const REGISTERS: [Register; 75] = [
+    reg(0xFF00, "P1", &[
+        ("select buttons", 5, 1), ("select d-pad", 4, 1), ("inputs", 0, 4),
+    ]),
+    reg(0xFF01, "SB", &[]),
+    reg(0xFF02, "SC", &[("transfer", 7, 1), ("internal clock", 0, 1)]),
+    reg(0xFF04, "DIV", &[]),
+    reg(0xFF05, "TIMA", &[]),
+    reg(0xFF06, "TMA", &[]),
+    reg(0xFF07, "TAC", &[("enable", 2, 1), ("clock", 0, 2)]),
+    reg(0xFF0F, "IF", INTERRUPTS),
+    reg(0xFF10, "NR10", &[
+        ("pace", 4, 3), ("decrease", 3, 1), ("step", 0, 3),
+    ]),
+    reg(0xFF11, "NR11", LENGTH_DUTY),
+    reg(0xFF12, "NR12", ENVELOPE),
+    reg(0xFF13, "NR13", &[]),
+    reg(0xFF14, "NR14", PERIOD_HIGH),
+    reg(0xFF16, "NR21", LENGTH_DUTY),
+    reg(0xFF17, "NR22", ENVELOPE),
+    reg(0xFF18, "NR23", &[]),
+    reg(0xFF19, "NR24", PERIOD_HIGH),
+    reg(0xFF1A, "NR30", &[("DAC on", 7, 1)]),
+    reg(0xFF1B, "NR31", &[]),
+    reg(0xFF1C, "NR32", &[("volume", 5, 2)]),
+    reg(0xFF1D, "NR33", &[]),
+    reg(0xFF1E, "NR34", PERIOD_HIGH),
+    reg(0xFF20, "NR41", &[("length", 0, 6)]),
+    reg(0xFF21, "NR42", ENVELOPE),
+    reg(0xFF22, "NR43", &[
+        ("shift", 4, 4), ("7-bit", 3, 1), ("divider", 0, 3),
+    ]),
+    reg(0xFF23, "NR44", &[("trigger", 7, 1), ("length enable", 6, 1)]),
+    reg(0xFF24, "NR50", &[
+        ("VIN left", 7, 1), ("left volume", 4, 3),
+        ("VIN right", 3, 1), ("right volume", 0, 3),
+    ]),
+    reg(0xFF25, "NR51", &[
+        ("4 left", 7, 1), ("3 left", 6, 1), ("2 left", 5, 1),
+        ("1 left", 4, 1), ("4 right", 3, 1), ("3 right", 2, 1),
+        ("2 right", 1, 1), ("1 right", 0, 1),
+    ]),
+    reg(0xFF26, "NR52", &[
+        ("audio on", 7, 1), ("4 on", 3, 1), ("3 on", 2, 1), ("2 on", 1, 1),
+        ("1 on", 0, 1),
+    ]),
+    reg(0xFF30, "WAVE0", &[]), reg(0xFF31, "WAVE1", &[]),
+    reg(0xFF32, "WAVE2", &[]), reg(0xFF33, "WAVE3", &[]),
+    reg(0xFF34, "WAVE4", &[]), reg(0xFF35, "WAVE5", &[]),
+    reg(0xFF36, "WAVE6", &[]), reg(0xFF37, "WAVE7", &[]),
+    reg(0xFF38, "WAVE8", &[]), reg(0xFF39, "WAVE9", &[]),
+    reg(0xFF3A, "WAVEA", &[]), reg(0xFF3B, "WAVEB", &[]),
+    reg(0xFF3C, "WAVEC", &[]), reg(0xFF3D, "WAVED", &[]),
+    reg(0xFF3E, "WAVEE", &[]), reg(0xFF3F, "WAVEF", &[]),
+    reg(0xFF40, "LCDC", &[
+        ("LCD on", 7, 1), ("window map", 6, 1), ("window on", 5, 1),
+        ("tile data", 4, 1), ("BG map", 3, 1), ("OBJ size", 2, 1),
+        ("OBJ on", 1, 1), ("BG on", 0, 1),
+    ]),
+    reg(0xFF41, "STAT", &[
+        ("LYC int", 6, 1), ("mode 2 int", 5, 1), ("mode 1 int", 4, 1),
+        ("mode 0 int", 3, 1), ("LY=LYC", 2, 1), ("mode", 0, 2),
+    ]),
+    reg(0xFF42, "SCY", &[]),
+    reg(0xFF43, "SCX", &[]),
+    reg(0xFF44, "LY", &[]),
+    reg(0xFF45, "LYC", &[]),
+    reg(0xFF46, "DMA", &[]),
+    reg(0xFF47, "BGP", SHADES),
+    reg(0xFF48, "OBP0", SHADES),
+    reg(0xFF49, "OBP1", SHADES),
+    reg(0xFF4A, "WY", &[]),
+    reg(0xFF4B, "WX", &[]),
+    cgb(0xFF4D, "KEY1", &[("double speed", 7, 1), ("switch armed", 0, 1)]),
+    cgb(0xFF4F, "VBK", &[("bank", 0, 1)]),
+    reg(0xFF50, "BOOT", &[("boot ROM off", 0, 1)]),
+    cgb(0xFF51, "HDMA1", &[]),
+    cgb(0xFF52, "HDMA2", &[]),
+    cgb(0xFF53, "HDMA3", &[]),
+    cgb(0xFF54, "HDMA4", &[]),
+    cgb(0xFF55, "HDMA5", &[("idle", 7, 1), ("blocks left", 0, 7)]),
+    cgb(0xFF56, "RP", &[
+        ("read enable", 6, 2), ("receiving", 1, 1), ("LED on", 0, 1),
+    ]),
+    cgb(0xFF68, "BCPS", PALETTE_SPEC),
+    cgb(0xFF69, "BCPD", &[]),
+    cgb(0xFF6A, "OCPS", PALETTE_SPEC),
+    cgb(0xFF6B, "OCPD", &[]),
+    cgb(0xFF6C, "OPRI", &[("by coordinate", 0, 1)]),
+    cgb(0xFF70, "SVBK", &[("bank", 0, 3)]),
+    cgb(0xFF76, "PCM12", &[("2 output", 4, 4), ("1 output", 0, 4)]),
+    cgb(0xFF77, "PCM34", &[("4 output", 4, 4), ("3 output", 0, 4)]),
+    reg(0xFFFF, "IE", INTERRUPTS),
+];
+
+/// The registers' values at one point, to tell what changed since.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Snapshot {
+    cgb: bool,
+    values: Vec<u8>,
+}
+
+impl Snapshot {
+    pub fn new(mmu: &Mmu) -> Self {
+        Self {
+            cgb: mmu.gpu.is_cgb(),
+            values: REGISTERS
+                .iter()
+                .map(|register| mmu.peek_byte(register.address).unwrap_or(0xFF))
+                .collect(),
+        }
+    }
+
+    pub fn get(&self, address: u16) -> Option<u8> {
+        REGISTERS
+            .iter()
+            .position(|register| register.address == address)
+            .map(|i| self.values[i])
+    }
+}
+
+/// `name=value` for each field of `value`.
+pub fn decode(register: &Register, value: u8) -> String {
+    register
+        .fields
+        .iter()
+        .map(|(name, shift, width)| {
+            let mask = (1u16 << width) - 1;
+
+            format!("{}={}", name, (value as u16 >> shift) & mask)
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// One line per register, marking those that differ from `previous` with
+/// `*`, or in inverse video with `ansi`. CGB registers are left out in DMG
+/// mode.
+pub fn dump(
+    current: &Snapshot,
+    previous: Option<&Snapshot>,
+    ansi: bool,
+) -> String {
+    REGISTERS
+        .iter()
+        .zip(current.values.iter())
+        .enumerate()
+        .filter(|(_, (register, _))| current.cgb || !register.cgb)
+        .map(|(i, (register, value))| {
+            let changed =
+                previous.is_some_and(|previous| previous.values[i] != *value);
+
+            let line = format!(
+                "{:04X} {:<5} {:02X}  {}",
+                register.address,
+                register.name,
+                value,
+                decode(register, *value)
+            );
+            let line = line.trim_end();
+
+            match (changed, ansi) {
+                (true, true) => format!("\x1b[7m{}\x1b[0m", line),
+                (true, false) => format!("*{}", line),
+                (false, _) => format!(" {}", line),
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{bus::Bus, mmu::ROM_BANK_0_SIZE};
+
+    #[test]
+    fn it_decodes_fields() {
+        let lcdc = REGISTERS.iter().find(|r| r.name == "LCDC").unwrap();
+
+        assert_eq!(
+            decode(lcdc, 0x91),
+            "LCD on=1, window map=0, window on=0, tile data=1, BG map=0, \
+             OBJ size=0, OBJ on=0, BG on=1"
+        );
+
+        let nr12 = REGISTERS.iter().find(|r| r.name == "NR12").unwrap();
+        assert_eq!(decode(nr12, 0xF3), "volume=15, increase=0, pace=3");
+
+        // Sorted and unique
+        assert!(REGISTERS.windows(2).all(|w| w[0].address < w[1].address));
+    }
+
+    #[test]
+    fn it_marks_changed_registers() {
+        let mut mmu = Mmu::new(Vec::new(), vec![0; ROM_BANK_0_SIZE]).unwrap();
+        mmu.write_byte(0xFF42, 0x10);
+
+        let previous = Snapshot::new(&mmu);
+        mmu.write_byte(0xFF42, 0x20);
+        let current = Snapshot::new(&mmu);
+
+        assert_eq!(current.get(0xFF42), Some(0x20));
+        assert_eq!(current.get(0xFF03), None);
+
+        let dump = dump(&current, Some(&previous), false);
+        let changed: Vec<&str> =
+            dump.lines().filter(|line| line.starts_with('*')).collect();
+
+        assert_eq!(changed, vec!["*FF42 SCY   20"]);
+        assert!(!dump.contains("HDMA1"));
+        assert!(!dump.contains("PCM12"));
+        assert!(dump.lines().last().unwrap().starts_with(" FFFF IE"));
+
+        let ansi = super::dump(&current, Some(&previous), true);
+        assert!(ansi.contains("\x1b[7mFF42 SCY   20\x1b[0m"));
+    }
+}
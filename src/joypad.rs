@@ -0,0 +1,95 @@
+use crate::state::{Reader, Writer};
+
+// Button bits, as recorded in movies.
+pub const RIGHT: u8 = 1 << 0;
+pub const LEFT: u8 = 1 << 1;
+pub const UP: u8 = 1 << 2;
+pub const DOWN: u8 = 1 << 3;
+pub const A: u8 = 1 << 4;
+pub const B: u8 = 1 << 5;
+pub const SELECT: u8 = 1 << 6;
+pub const START: u8 = 1 << 7;
+
+const SELECT_DIRECTIONS: u8 = 1 << 4;
+const SELECT_ACTIONS: u8 = 1 << 5;
+
+/// The P1 register (0xFF00).
+// TODO: Request the joypad interrupt once interrupts are implemented
+#[derive(Clone)]
+pub struct Joypad {
+    pressed: u8,
+    select: u8,
+}
+
+impl Default for Joypad {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Joypad {
+    pub fn new() -> Self {
+        Self {
+            pressed: 0,
+            select: SELECT_DIRECTIONS | SELECT_ACTIONS,
+        }
+    }
+
+    pub fn pressed(&self) -> u8 {
+        self.pressed
+    }
+
+    pub fn set(&mut self, pressed: u8) {
+        self.pressed = pressed;
+    }
+
+    pub fn read(&self) -> u8 {
+        let mut low = 0;
+
+        if self.select & SELECT_DIRECTIONS == 0 {
+            low |= self.pressed & 0x0F;
+        }
+
+        if self.select & SELECT_ACTIONS == 0 {
+            low |= self.pressed >> 4;
+        }
+
+        // Lines are active low; unused bits read as 1
+        0xC0 | self.select | (!low & 0x0F)
+    }
+
+    pub fn write(&mut self, byte: u8) {
+        self.select = byte & (SELECT_DIRECTIONS | SELECT_ACTIONS);
+    }
+
+    pub fn save(&self, w: &mut Writer) {
+        w.u8(self.pressed);
+        w.u8(self.select);
+    }
+
+    pub fn load(&mut self, r: &mut Reader) -> Result<(), String> {
+        self.pressed = r.u8()?;
+        self.select = r.u8()?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_reads_the_selected_group() {
+        let mut joypad = Joypad::new();
+        joypad.set(DOWN | A | START);
+
+        assert_eq!(joypad.read(), 0xFF);
+
+        joypad.write(SELECT_ACTIONS);
+        assert_eq!(joypad.read(), 0xC0 | SELECT_ACTIONS | 0b0111);
+
+        joypad.write(SELECT_DIRECTIONS);
+        assert_eq!(joypad.read(), 0xC0 | SELECT_DIRECTIONS | 0b0110);
+    }
+}
@@ -0,0 +1,38 @@
+pub mod apu;
+pub mod archive;
+pub mod bus;
+pub mod cheats;
+pub mod checksum;
+pub mod config;
+pub mod cpu;
+pub mod debugger;
+pub mod disasm;
+pub mod filter;
+pub mod golden;
+pub mod gpu;
+pub mod header;
+pub mod headless;
+pub mod inflate;
+pub mod ini;
+pub mod instr;
+pub mod io_registers;
+pub mod joypad;
+mod microcode;
+pub mod mmu;
+pub mod movie;
+pub mod oam;
+pub mod pacing;
+pub mod palette;
+pub mod patch;
+pub mod png;
+pub mod registers;
+pub mod screenshot;
+pub mod search;
+pub mod sgb;
+pub mod state;
+pub mod symbols;
+pub mod video;
+pub mod vram;
+pub mod wav;
+
+pub use cpu::Cpu;
@@ -1,21 +1,28 @@
 extern crate minifb;
 
-mod cpu;
-mod gpu;
-mod instr;
-mod microcode;
-mod mmu;
-mod registers;
+use minifb::{Key, KeyRepeat, Scale, ScaleMode, Window, WindowOptions};
 
-use minifb::{Key, Window, WindowOptions};
-
-use cpu::Cpu;
-use mmu::Mmu;
-
-struct Board {
-    cpu: Cpu,
-    mmu: Mmu,
-}
+use rboy::{
+    archive,
+    cheats::Cheats,
+    config::{self, Config, Settings},
+    debugger::{Debugger, GdbStub},
+    filter::{Blender, Filter, FILTERS},
+    gpu::{SCREEN_HEIGHT, SCREEN_WIDTH},
+    header,
+    headless::run_frame,
+    io_registers::{self, Snapshot},
+    joypad,
+    movie::Movie,
+    oam,
+    pacing::{Pacer, FRAME_DURATION},
+    palette, patch, screenshot,
+    sgb::{BORDER_HEIGHT, BORDER_WIDTH},
+    symbols::Symbols,
+    video::Recorder,
+    vram, wav, Cpu,
+};
+use std::{fs::File, io::BufWriter, path::PathBuf};
 
 fn buffer_from_file(path: &str) -> Vec<u8> {
     use std::io::Read;
@@ -28,20 +35,718 @@ fn buffer_from_file(path: &str) -> Vec<u8> {
     buffer
 }
 
+// Joypad buttons, their names in the config's `[keys]` and default keys.
+const BUTTONS: [(&str, u8, Key); 8] = [
+    ("right", joypad::RIGHT, Key::Right),
+    ("left", joypad::LEFT, Key::Left),
+    ("up", joypad::UP, Key::Up),
+    ("down", joypad::DOWN, Key::Down),
+    ("a", joypad::A, Key::Z),
+    ("b", joypad::B, Key::X),
+    ("select", joypad::SELECT, Key::Backspace),
+    ("start", joypad::START, Key::Enter),
+];
+
+// Keys that can be bound besides letters and digits.
+const KEY_NAMES: [(&str, Key); 16] = [
+    ("Right", Key::Right),
+    ("Left", Key::Left),
+    ("Up", Key::Up),
+    ("Down", Key::Down),
+    ("Enter", Key::Enter),
+    ("Space", Key::Space),
+    ("Backspace", Key::Backspace),
+    ("LeftShift", Key::LeftShift),
+    ("RightShift", Key::RightShift),
+    ("LeftCtrl", Key::LeftCtrl),
+    ("RightCtrl", Key::RightCtrl),
+    ("LeftAlt", Key::LeftAlt),
+    ("RightAlt", Key::RightAlt),
+    ("Comma", Key::Comma),
+    ("Period", Key::Period),
+    ("Slash", Key::Slash),
+];
+
+// Command line options and the settings they override.
+const FLAGS: [(&str, &str); 9] = [
+    ("--boot-rom", "boot_rom"),
+    ("--scale", "scale"),
+    ("--filter", "filter"),
+    ("--palette", "palette"),
+    ("--palettes", "palettes"),
+    ("--speed", "speed"),
+    ("--save-dir", "save_dir"),
+    ("--screenshot-scale", "screenshot_scale"),
+    ("--cheats", "cheats"),
+];
+
+fn option<'a>(args: &'a [String], name: &str) -> Option<&'a String> {
+    args.iter()
+        .position(|arg| arg == name)
+        .and_then(|i| args.get(i + 1))
+}
+
+fn key_by_name(name: &str) -> Option<Key> {
+    #[rustfmt::skip]
+    const LETTERS: [Key; 26] = [
+        Key::A, Key::B, Key::C, Key::D, Key::E, Key::F, Key::G, Key::H,
+        Key::I, Key::J, Key::K, Key::L, Key::M, Key::N, Key::O, Key::P,
+        Key::Q, Key::R, Key::S, Key::T, Key::U, Key::V, Key::W, Key::X,
+        Key::Y, Key::Z,
+    ];
+
+    #[rustfmt::skip]
+    const DIGITS: [Key; 10] = [
+        Key::Key0, Key::Key1, Key::Key2, Key::Key3, Key::Key4, Key::Key5,
+        Key::Key6, Key::Key7, Key::Key8, Key::Key9,
+    ];
+
+    match name.as_bytes() {
+        [c] if c.is_ascii_alphabetic() => {
+            Some(LETTERS[(c.to_ascii_uppercase() - b'A') as usize])
+        }
+        [c] if c.is_ascii_digit() => Some(DIGITS[(c - b'0') as usize]),
+        _ => KEY_NAMES
+            .iter()
+            .find(|(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|(_, key)| *key),
+    }
+}
+
+/// The key for each button, from `keys.<button>` settings.
+fn key_bindings(settings: &Settings) -> Result<Vec<(Key, u8)>, String> {
+    BUTTONS
+        .iter()
+        .map(|(name, button, default)| {
+            let setting = format!("keys.{}", name);
+
+            let key = match settings.get(&setting) {
+                Some(key) => key_by_name(key)
+                    .ok_or_else(|| format!("invalid {}: {}", setting, key))?,
+                None => *default,
+            };
+
+            Ok((key, *button))
+        })
+        .collect()
+}
+
+/// The settings the frontend runs with, checked up front so a typo in the
+/// config stops it before the window opens.
+struct Options {
+    boot_rom: String,
+    sgb: bool,
+    blend: bool,
+    audio: bool,
+    scale: usize,
+    screenshot_scale: usize,
+    speed: Option<f64>,
+    filter: Filter,
+    palette: Option<String>,
+    save_dir: String,
+    keys: Vec<(Key, u8)>,
+}
+
+impl Options {
+    fn from_settings(settings: &Settings) -> Result<Self, String> {
+        if settings.get("rewind").is_some() {
+            return Err("rewind isn't supported yet".to_string());
+        }
+
+        let filter = match settings.get("filter") {
+            Some(name) => Filter::from_name(name)
+                .ok_or_else(|| format!("invalid filter: {}", name))?,
+            None => Filter::Nearest,
+        };
+
+        Ok(Self {
+            boot_rom: settings
+                .get("boot_rom")
+                .unwrap_or("b_rom.gb")
+                .to_string(),
+            sgb: settings.flag("sgb")?.unwrap_or(false),
+            blend: settings.flag("blend")?.unwrap_or(false),
+            audio: settings.flag("audio.enabled")?.unwrap_or(true),
+            scale: settings.parse("scale")?.unwrap_or(3),
+            screenshot_scale: settings.parse("screenshot_scale")?.unwrap_or(1),
+            speed: settings.parse("speed")?,
+            filter,
+            palette: settings.get("palette").map(str::to_string),
+            save_dir: settings.get("save_dir").unwrap_or(".").to_string(),
+            keys: key_bindings(settings)?,
+        })
+    }
+}
+
+/// Reports a bad setting or option and exits.
+fn invalid(message: &str) -> ! {
+    eprintln!("{}", message);
+
+    std::process::exit(2);
+}
+
+fn pressed_buttons(window: &Window, keys: &[(Key, u8)]) -> u8 {
+    keys.iter()
+        .filter(|(key, _)| window.is_key_down(*key))
+        .fold(0, |buttons, (_, button)| buttons | button)
+}
+
+/// A video recording and the sound that goes with it, if enabled.
+type Video = (Recorder<BufWriter<File>>, Option<wav::Dump>);
+
+/// Starts recording to a timestamped Y4M and WAV file pair in `dir`, or
+/// stops the current recording.
+fn toggle_video(
+    cpu: &mut Cpu,
+    video: Option<Video>,
+    dir: &str,
+    audio: bool,
+) -> Option<Video> {
+    if let Some((recorder, audio)) = video {
+        let frames = recorder.frames();
+
+        cpu.bus.apu.set_capture(false);
+
+        let audio = audio.map_or(Ok(0), |audio| audio.finish());
+
+        match recorder.finish().and(audio) {
+            Ok(_) => println!("Recorded {} video frames", frames),
+            Err(e) => eprintln!("Video recording failed: {}", e),
+        }
+
+        return None;
+    }
+
+    let path = screenshot::timestamped_path(dir, "y4m");
+
+    let video = Recorder::create(&path).and_then(|recorder| {
+        let wav = if audio {
+            Some(wav::Dump::create(path.with_extension("wav"), false)?)
+        } else {
+            None
+        };
+
+        Ok((recorder, wav))
+    });
+
+    match video {
+        Ok(video) => {
+            println!("Recording video to {}", path.display());
+
+            cpu.bus.apu.set_capture(audio);
+
+            Some(video)
+        }
+        Err(e) => {
+            eprintln!("Video recording failed: {}", e);
+
+            None
+        }
+    }
+}
+
+/// The VRAM viewer: the tile sheet and both BG maps, shown at twice their
+/// size.
+fn vram_window(cpu: &Cpu) -> Window {
+    let image = vram::overview(&cpu.bus.gpu);
+
+    let mut window = Window::new(
+        "VRAM",
+        image.width,
+        image.height,
+        WindowOptions {
+            scale: Scale::X2,
+            ..WindowOptions::default()
+        },
+    )
+    .unwrap_or_else(|e| panic!("{}", e));
+
+    window.limit_update_rate(None);
+
+    window
+}
+
+/// The OAM inspector: every sprite, framed green if selected for the line
+/// picked with Up and Down, red if dropped.
+fn oam_window(cpu: &Cpu, line: u8) -> Window {
+    let image = oam::view(&cpu.bus.gpu, line);
+
+    let mut window = Window::new(
+        &oam_title(line),
+        image.width,
+        image.height,
+        WindowOptions {
+            scale: Scale::X2,
+            ..WindowOptions::default()
+        },
+    )
+    .unwrap_or_else(|e| panic!("{}", e));
+
+    window.limit_update_rate(None);
+
+    window
+}
+
+fn oam_title(line: u8) -> String {
+    format!("OAM, line {}", line)
+}
+
 fn main() {
-    let mut window = Window::new("Game On", 160, 144, WindowOptions::default())
-        .unwrap_or_else(|e| {
-            panic!("{}", e);
+    let args: Vec<String> = std::env::args().collect();
+
+    // `rboy info [--json] <rom>...` prints the headers without running
+    if args.get(1).map(String::as_str) == Some("info") {
+        let json = args.iter().any(|arg| arg == "--json");
+        let mut code = 0;
+
+        for path in args[2..].iter().filter(|arg| *arg != "--json") {
+            let (text, result) = header::report(path, json);
+
+            println!("{}", text);
+            code = code.max(result);
+        }
+
+        std::process::exit(code);
+    }
+
+    let rom_path = match args.get(1) {
+        Some(path) if !path.starts_with("--") => path.as_str(),
+        _ => "tetris_rom.gb",
+    };
+
+    // Zipped or gzipped ROMs are unpacked; --entry picks one from a zip
+    let mut game_rom_buffer = archive::load_rom(
+        rom_path,
+        option(&args, "--entry").map(String::as_str),
+    )
+    .unwrap_or_else(|e| panic!("{}", e));
+
+    // A patch named after the ROM applies unless one is given
+    let patch_path = option(&args, "--patch")
+        .map(PathBuf::from)
+        .or_else(|| patch::for_rom(rom_path));
+
+    if let Some(path) = patch_path {
+        game_rom_buffer = patch::apply_file(&game_rom_buffer, &path)
+            .unwrap_or_else(|e| panic!("{}", e));
+
+        println!("Applied {}", path.display());
+    }
+
+    let config_path = option(&args, "--config")
+        .map(PathBuf::from)
+        .or_else(config::default_path);
+
+    let config = match &config_path {
+        Some(path) if path.exists() => Config::load(path)
+            .unwrap_or_else(|e| invalid(&format!("{}: {}", path.display(), e))),
+        _ => Config::default(),
+    };
+
+    // Command line options win over the config file
+    let mut settings = config.settings(&game_rom_buffer);
+
+    for (flag, key) in &FLAGS {
+        if let Some(value) = option(&args, flag) {
+            settings.set(key, value);
+        }
+    }
+
+    if args.iter().any(|arg| arg == "--blend") {
+        settings.set("blend", "true");
+    }
+
+    if args.iter().any(|arg| arg == "--sgb") {
+        settings.set("sgb", "true");
+    }
+
+    let options =
+        Options::from_settings(&settings).unwrap_or_else(|e| invalid(&e));
+
+    let play_path = option(&args, "--play");
+
+    let playback = play_path.map(|path| {
+        Movie::load(path)
+            .unwrap_or_else(|e| invalid(&format!("--play {}: {}", path, e)))
+    });
+
+    let record_path = option(&args, "--record");
+
+    let mut recording = record_path.map(|_| Movie::power_on(&game_rom_buffer));
+
+    // An empty boot_rom starts without one, as do movies
+    let boot_rom = options.boot_rom.as_str();
+
+    let mut cpu = if let (Some(movie), Some(path)) = (&playback, play_path) {
+        let cpu = movie
+            .start(game_rom_buffer.clone())
+            .unwrap_or_else(|e| invalid(&format!("--play {}: {}", path, e)));
+
+        // Recording while playing starts where the played movie does
+        if let Some(movie) = &mut recording {
+            *movie = Movie::from_state(&game_rom_buffer, &cpu);
+        }
+
+        cpu
+    } else if recording.is_some() || boot_rom.is_empty() {
+        Cpu::post_boot(game_rom_buffer)
+    } else {
+        Cpu::new(buffer_from_file(boot_rom), game_rom_buffer, None)
+            .unwrap_or_else(|e| invalid(&format!("{}: {}", boot_rom, e)))
+    };
+
+    if options.sgb {
+        cpu.bus.set_sgb(true);
+
+        if cpu.bus.sgb.is_none() {
+            println!("The ROM doesn't support the Super Game Boy");
+        }
+    }
+
+    cpu.symbols = Symbols::for_rom(rom_path);
+
+    // Cheats from <rom>.cht unless another file is given; F6 toggles them
+    let cheats_path = settings
+        .get("cheats")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| Cheats::path_for_rom(rom_path));
+
+    if cheats_path.exists() {
+        cpu.bus.cheats = Cheats::load(&cheats_path).unwrap_or_else(|e| {
+            panic!("cheats {}: {}", cheats_path.display(), e)
         });
 
-    window.limit_update_rate(Some(std::time::Duration::from_micros(64400)));
+        println!(
+            "Loaded {} cheats from {}",
+            cpu.bus.cheats.iter().count(),
+            cheats_path.display()
+        );
+    }
+    cpu.trace = args.iter().any(|arg| arg == "--trace");
+
+    let gdb_port = option(&args, "--gdb")
+        .map(|port| port.parse::<u16>().expect("invalid --gdb port"));
+
+    if let Some(port) = gdb_port {
+        let stub = GdbStub::bind(("127.0.0.1", port)).unwrap();
+
+        println!("Waiting for gdb on {}", stub.local_addr().unwrap());
+
+        stub.serve(&mut cpu, &mut Debugger::new()).unwrap();
+
+        return;
+    }
+
+    if args.iter().any(|arg| arg == "--debug") {
+        Debugger::new().repl(&mut cpu).unwrap();
+
+        return;
+    }
+
+    let Options {
+        screenshot_scale,
+        save_dir,
+        audio,
+        keys,
+        ..
+    } = options;
+
+    // Built-in palettes, then any from the palettes file; F2 cycles them
+    let mut palettes: Vec<(String, palette::Palettes)> = palette::BUILTIN
+        .iter()
+        .map(|(name, palettes)| (name.to_string(), *palettes))
+        .collect();
 
-    let boot_rom_buffer = buffer_from_file("b_rom.gb");
-    let game_rom_buffer = buffer_from_file("tetris_rom.gb");
+    if let Some(path) = settings.get("palettes") {
+        palettes.extend(
+            palette::load(path)
+                .unwrap_or_else(|e| panic!("palettes {}: {}", path, e)),
+        );
+    }
+
+    let mut palette_index = options.palette.as_ref().map_or(0, |name| {
+        palettes
+            .iter()
+            .position(|(n, _)| n.eq_ignore_ascii_case(name))
+            .unwrap_or_else(|| invalid(&format!("invalid palette: {}", name)))
+    });
+
+    cpu.bus.gpu.set_palettes(palettes[palette_index].1);
+
+    let window_scale = options.scale;
+
+    // F3 cycles through the filters, F4 toggles frame blending
+    let mut filter = options.filter;
+
+    let mut blender = None;
+
+    if options.blend {
+        blender = Some(Blender::new());
+    }
+
+    // The SGB border frames the screen
+    let (frame_width, frame_height) = if cpu.bus.sgb.is_some() {
+        (BORDER_WIDTH, BORDER_HEIGHT)
+    } else {
+        (SCREEN_WIDTH, SCREEN_HEIGHT)
+    };
+
+    // The frame is scaled by whole factors and centered in the window
+    let mut window = Window::new(
+        "Game On",
+        frame_width * window_scale,
+        frame_height * window_scale,
+        WindowOptions {
+            resize: true,
+            scale_mode: ScaleMode::Center,
+            ..WindowOptions::default()
+        },
+    )
+    .unwrap_or_else(|e| {
+        panic!("{}", e);
+    });
+
+    // Pacing is done by the emulator, see below
+    window.limit_update_rate(None);
 
-    let mut cpu = Cpu::new(boot_rom_buffer, game_rom_buffer, None);
+    let mut pacer = Pacer::new();
+
+    if let Some(speed) = options.speed {
+        pacer.set_speed(speed);
+    }
+
+    let mut overrun = 0;
+    let mut frame = 0;
+    let mut video: Option<Video> = None;
+    let mut paused = false;
+    let mut viewer: Option<Window> = None;
+    let mut inspector: Option<Window> = None;
+    let mut inspected_line = 0;
+    let mut io_panel: Option<Snapshot> = None;
 
     while window.is_open() && !window.is_key_down(Key::Escape) {
-        cpu.step();
+        if window.is_key_pressed(Key::P, KeyRepeat::No) {
+            paused = !paused;
+            pacer.reset();
+        }
+
+        if window.is_key_pressed(Key::Minus, KeyRepeat::No) {
+            pacer.set_speed(pacer.speed() / 2.0);
+            println!("Speed {}x", pacer.speed());
+        }
+
+        if window.is_key_pressed(Key::Equal, KeyRepeat::No) {
+            pacer.set_speed(pacer.speed() * 2.0);
+            println!("Speed {}x", pacer.speed());
+        }
+
+        // N steps a single frame while paused
+        if paused && !window.is_key_pressed(Key::N, KeyRepeat::Yes) {
+            window.update();
+            std::thread::sleep(FRAME_DURATION);
+
+            continue;
+        }
+
+        // Once the movie runs out, the keyboard takes over
+        let buttons = playback
+            .as_ref()
+            .and_then(|movie| movie.inputs.get(frame).copied())
+            .unwrap_or_else(|| pressed_buttons(&window, &keys));
+
+        if let Some(movie) = &mut recording {
+            movie.record(buttons);
+        }
+
+        cpu.bus.joypad.set(buttons);
+
+        if let Err(e) = run_frame(&mut cpu, &mut overrun) {
+            eprintln!("{}", e);
+
+            break;
+        }
+
+        frame += 1;
+
+        let (pixels, framed) = match &cpu.bus.sgb {
+            Some(sgb) => {
+                let screen = cpu.bus.gpu.screen();
+
+                (sgb.colorize(screen), sgb.frame(screen))
+            }
+            None => {
+                let pixels = cpu.bus.gpu.frame_rgb();
+
+                (pixels.clone(), pixels)
+            }
+        };
+
+        let shown = match &mut blender {
+            Some(blender) => blender.blend(&framed),
+            None => framed,
+        };
+
+        let (width, height) = window.get_size();
+        let factor = (width / frame_width).min(height / frame_height);
+        let factor = filter.factor(factor.max(1));
+
+        window
+            .update_with_buffer(
+                &filter.apply(&shown, frame_width, factor),
+                frame_width * factor,
+                frame_height * factor,
+            )
+            .unwrap();
+
+        if let Some((recorder, audio)) = &mut video {
+            if let Some(audio) = audio {
+                audio.write(&cpu.bus.apu.take_samples());
+            }
+
+            if let Err(e) = recorder.frame(&pixels) {
+                eprintln!("Video recording failed: {}", e);
+
+                cpu.bus.apu.set_capture(false);
+                video = None;
+            }
+        }
+
+        if window.is_key_pressed(Key::F12, KeyRepeat::No) {
+            match screenshot::save(&cpu.bus.gpu, &save_dir, screenshot_scale) {
+                Ok(path) => println!("Saved {}", path.display()),
+                Err(e) => eprintln!("Screenshot failed: {}", e),
+            }
+        }
+
+        if window.is_key_pressed(Key::F2, KeyRepeat::No) {
+            palette_index = (palette_index + 1) % palettes.len();
+            cpu.bus.gpu.set_palettes(palettes[palette_index].1);
+
+            println!("Palette {}", palettes[palette_index].0);
+        }
+
+        if window.is_key_pressed(Key::F3, KeyRepeat::No) {
+            let next = FILTERS.iter().position(|f| *f == filter).unwrap() + 1;
+            filter = FILTERS[next % FILTERS.len()];
+
+            println!("Filter {}", filter.name());
+        }
+
+        if window.is_key_pressed(Key::F4, KeyRepeat::No) {
+            blender = match blender {
+                Some(_) => None,
+                None => Some(Blender::new()),
+            };
+
+            println!("Frame blending {}", blender.is_some());
+        }
+
+        if window.is_key_pressed(Key::F6, KeyRepeat::No) {
+            let active = !cpu.bus.cheats.is_active();
+            cpu.bus.cheats.set_active(active);
+
+            println!("Cheats {}", active);
+        }
+
+        // F7 opens the VRAM viewer, which refreshes every frame
+        if window.is_key_pressed(Key::F7, KeyRepeat::No) {
+            viewer = match viewer {
+                Some(_) => None,
+                None => Some(vram_window(&cpu)),
+            };
+        }
+
+        if let Some(vram_window) = &mut viewer {
+            if vram_window.is_open() {
+                let image = vram::overview(&cpu.bus.gpu);
+
+                vram_window
+                    .update_with_buffer(
+                        &image.pixels,
+                        image.width,
+                        image.height,
+                    )
+                    .unwrap();
+            } else {
+                viewer = None;
+            }
+        }
+
+        // F8 opens the OAM inspector
+        if window.is_key_pressed(Key::F8, KeyRepeat::No) {
+            inspector = match inspector {
+                Some(_) => None,
+                None => Some(oam_window(&cpu, inspected_line)),
+            };
+        }
+
+        if let Some(oam_window) = &mut inspector {
+            if oam_window.is_open() {
+                let last = SCREEN_HEIGHT as u8 - 1;
+
+                if oam_window.is_key_pressed(Key::Up, KeyRepeat::Yes) {
+                    inspected_line = inspected_line.saturating_sub(1);
+                    oam_window.set_title(&oam_title(inspected_line));
+                }
+
+                if oam_window.is_key_pressed(Key::Down, KeyRepeat::Yes) {
+                    inspected_line = (inspected_line + 1).min(last);
+                    oam_window.set_title(&oam_title(inspected_line));
+                }
+
+                let image = oam::view(&cpu.bus.gpu, inspected_line);
+
+                oam_window
+                    .update_with_buffer(
+                        &image.pixels,
+                        image.width,
+                        image.height,
+                    )
+                    .unwrap();
+            } else {
+                inspector = None;
+            }
+        }
+
+        // F10 shows the IO registers in the terminal, redrawn every frame
+        // with the ones that changed highlighted
+        if window.is_key_pressed(Key::F10, KeyRepeat::No) {
+            io_panel = match io_panel {
+                Some(_) => None,
+                None => Some(Snapshot::new(&cpu.bus)),
+            };
+        }
+
+        if let Some(previous) = &mut io_panel {
+            let current = Snapshot::new(&cpu.bus);
+
+            println!(
+                "\x1b[H\x1b[2J{}",
+                io_registers::dump(&current, Some(previous), true)
+            );
+
+            *previous = current;
+        }
+
+        if window.is_key_pressed(Key::F9, KeyRepeat::No) {
+            video = toggle_video(&mut cpu, video, &save_dir, audio);
+        }
+
+        // Tab fast-forwards as fast as the host allows
+        if window.is_key_down(Key::Tab) {
+            pacer.reset();
+        } else if !paused {
+            pacer.wait();
+        }
+    }
+
+    if video.is_some() {
+        toggle_video(&mut cpu, video, &save_dir, audio);
+    }
+
+    if let (Some(movie), Some(path)) = (&recording, record_path) {
+        movie.save(path).unwrap();
+
+        println!("Recorded {} frames to {}", movie.inputs.len(), path);
     }
 }
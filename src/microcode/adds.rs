@@ -1,6 +1,7 @@
 use crate::{
+    bus::Bus,
     instr::Instr,
-    microcode::{op_to_u16_reg, op_to_u8_reg, Exec, ExecRes},
+    microcode::{next, op_to_u16_reg, op_to_u8_reg, Exec, ExecRes},
     registers::FlagsRegister,
     Cpu,
 };
@@ -17,27 +18,22 @@ fn next_flags(data: FlagsData) -> Option<FlagsRegister> {
     })
 }
 
-pub struct Add<'a>(pub &'a mut Cpu);
+pub struct Add<'a, B: Bus>(pub &'a mut Cpu<B>);
 
-impl Exec for Add<'_> {
+impl<B: Bus> Exec for Add<'_, B> {
     type FlagsData = FlagsData;
 
     fn run(&mut self, instr: Instr) -> Option<ExecRes> {
         let val = op_to_u8_reg(&instr.rhs.unwrap(), &self.0.registers);
         let (new_val, carry) = self.0.registers.a.overflowing_add(val);
 
-        self.next_flags((new_val, carry))
-            .map(|f| self.0.registers.f = f);
+        if let Some(f) = self.next_flags((new_val, carry)) {
+            self.0.registers.f = f;
+        }
 
-        self.0.pc.add(1);
-        self.0.clock.add(4);
+        self.0.registers.a = new_val;
 
-        Some(ExecRes {
-            ticks: 4,
-            length: 1,
-            instr,
-            trace: None,
-        })
+        Some(next(self.0, instr))
     }
 
     fn next_flags(&self, data: Self::FlagsData) -> Option<FlagsRegister> {
@@ -45,9 +41,9 @@ impl Exec for Add<'_> {
     }
 }
 
-pub struct Adc<'a>(pub &'a mut Cpu);
+pub struct Adc<'a, B: Bus>(pub &'a mut Cpu<B>);
 
-impl Exec for Adc<'_> {
+impl<B: Bus> Exec for Adc<'_, B> {
     type FlagsData = FlagsData;
 
     fn run(&mut self, instr: Instr) -> Option<ExecRes> {
@@ -58,20 +54,13 @@ impl Exec for Adc<'_> {
         let (mid_value, mid_carry) = cpu.registers.a.overflowing_add(val);
         let (new_val, carry) = mid_value.overflowing_add(additinal_carry);
 
-        self.next_flags((new_val, mid_carry || carry))
-            .map(|f| self.0.registers.f = f);
+        if let Some(f) = self.next_flags((new_val, mid_carry || carry)) {
+            self.0.registers.f = f;
+        }
 
         self.0.registers.a = new_val;
 
-        self.0.pc.add(1);
-        self.0.clock.add(4);
-
-        Some(ExecRes {
-            ticks: 4,
-            length: 1,
-            instr,
-            trace: None,
-        })
+        Some(next(self.0, instr))
     }
 
     fn next_flags(&self, data: Self::FlagsData) -> Option<FlagsRegister> {
@@ -79,9 +68,9 @@ impl Exec for Adc<'_> {
     }
 }
 
-pub struct AddHl<'a>(pub &'a mut Cpu);
+pub struct AddHl<'a, B: Bus>(pub &'a mut Cpu<B>);
 
-impl Exec for AddHl<'_> {
+impl<B: Bus> Exec for AddHl<'_, B> {
     type FlagsData = (FlagsRegister, u16, bool);
 
     fn run(&mut self, instr: Instr) -> Option<ExecRes> {
@@ -90,18 +79,14 @@ impl Exec for AddHl<'_> {
         let (new_value, carry) = curr_hl.overflowing_add(val);
 
         self.0.registers.set_hl(new_value);
-        self.next_flags((self.0.registers.f, new_value, carry))
-            .map(|f| self.0.registers.f = f);
 
-        self.0.pc.add(1);
-        self.0.clock.add(8);
+        let flags = self.next_flags((self.0.registers.f, new_value, carry));
 
-        Some(ExecRes {
-            ticks: 8,
-            length: 1,
-            instr,
-            trace: None,
-        })
+        if let Some(f) = flags {
+            self.0.registers.f = f;
+        }
+
+        Some(next(self.0, instr))
     }
 
     fn next_flags(&self, data: Self::FlagsData) -> Option<FlagsRegister> {
@@ -118,10 +103,32 @@ impl Exec for AddHl<'_> {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::{Cpu, Registers};
+    use crate::{
+        microcode::decoded,
+        mmu::{BOOT_ROM_SIZE, ROM_BANK_0_SIZE},
+        registers::{Reg16Kind, Reg8Kind, Registers},
+        Cpu,
+    };
 
     fn cpu(registers: Registers) -> Cpu {
-        Cpu::new(vec![], vec![], Some(registers))
+        Cpu::new(
+            vec![0; BOOT_ROM_SIZE],
+            vec![0; ROM_BANK_0_SIZE],
+            Some(registers),
+        )
+        .unwrap()
+    }
+
+    fn add(cpu: &mut Cpu, reg: Reg8Kind) {
+        Add(cpu).run(decoded(&format!("ADD A, {:?}", reg)));
+    }
+
+    fn adc(cpu: &mut Cpu, reg: Reg8Kind) {
+        Adc(cpu).run(decoded(&format!("ADC A, {:?}", reg)));
+    }
+
+    fn add_hl(cpu: &mut Cpu, reg: Reg16Kind) {
+        AddHl(cpu).run(decoded(&format!("ADD HL, {:?}", reg)));
     }
 
     #[test]
@@ -1,13 +1,14 @@
 use crate::{
+    bus::Bus,
     instr::Instr,
-    microcode::{op_to_u8_reg, Exec, ExecRes},
+    microcode::{next, op_to_u8_reg, Exec, ExecRes},
     registers::FlagsRegister,
     Cpu,
 };
 
-pub struct And<'a>(pub &'a mut Cpu);
+pub struct And<'a, B: Bus>(pub &'a mut Cpu<B>);
 
-impl Exec for And<'_> {
+impl<B: Bus> Exec for And<'_, B> {
     type FlagsData = u8;
 
     fn run(&mut self, instr: Instr) -> Option<ExecRes> {
@@ -16,17 +17,11 @@ impl Exec for And<'_> {
         let new_val = self.0.registers.a & val;
 
         self.0.registers.a = new_val;
-        self.next_flags(new_val).map(|f| self.0.registers.f = f);
+        if let Some(f) = self.next_flags(new_val) {
+            self.0.registers.f = f;
+        }
 
-        self.0.pc.add(1);
-        self.0.clock.add(4);
-
-        Some(ExecRes {
-            ticks: 4,
-            length: 1,
-            instr,
-            trace: None,
-        })
+        Some(next(self.0, instr))
     }
 
     fn next_flags(&self, data: Self::FlagsData) -> Option<FlagsRegister> {
@@ -42,10 +37,24 @@ impl Exec for And<'_> {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::{Cpu, Registers};
+    use crate::{
+        microcode::decoded,
+        mmu::{BOOT_ROM_SIZE, ROM_BANK_0_SIZE},
+        registers::{Reg8Kind, Registers},
+        Cpu,
+    };
 
     fn cpu(registers: Registers) -> Cpu {
-        Cpu::new(vec![], vec![], Some(registers))
+        Cpu::new(
+            vec![0; BOOT_ROM_SIZE],
+            vec![0; ROM_BANK_0_SIZE],
+            Some(registers),
+        )
+        .unwrap()
+    }
+
+    fn and(cpu: &mut Cpu, reg: Reg8Kind) {
+        And(cpu).run(decoded(&format!("AND A, {:?}", reg)));
     }
 
     #[test]
@@ -60,7 +69,7 @@ mod tests {
 
         assert_eq!(cpu.registers.a, 0b10001001);
 
-        assert_eq!(cpu.registers.f.zero, false);
+        assert!(!cpu.registers.f.zero);
     }
 
     #[test]
@@ -73,10 +82,10 @@ mod tests {
 
         and(&mut cpu, Reg8Kind::D);
 
-        assert_eq!(cpu.registers.f.zero, false);
-        assert_eq!(cpu.registers.f.subtract, false);
-        assert_eq!(cpu.registers.f.half_carry, true);
-        assert_eq!(cpu.registers.f.carry, false);
+        assert!(!cpu.registers.f.zero);
+        assert!(!cpu.registers.f.subtract);
+        assert!(cpu.registers.f.half_carry);
+        assert!(!cpu.registers.f.carry);
     }
 
     #[test]
@@ -1,13 +1,14 @@
 use crate::{
+    bus::Bus,
     instr::{Instr, Operand},
-    microcode::{op_to_u8_reg, Exec, ExecRes},
+    microcode::{next, op_to_u8_reg, Exec, ExecRes},
     registers::FlagsRegister,
     Cpu,
 };
 
-pub struct Bit<'a>(pub &'a mut Cpu);
+pub struct Bit<'a, B: Bus>(pub &'a mut Cpu<B>);
 
-impl Bit<'_> {
+impl<B: Bus> Bit<'_, B> {
     fn bit_position(&self, instr: &Instr) -> u8 {
         match instr.lhs {
             Some(Operand::BitPos(n)) => n,
@@ -27,7 +28,7 @@ impl Bit<'_> {
     }
 }
 
-impl Exec for Bit<'_> {
+impl<B: Bus> Exec for Bit<'_, B> {
     type FlagsData = (FlagsRegister, u8);
 
     fn run(&mut self, instr: Instr) -> Option<ExecRes> {
@@ -36,17 +37,13 @@ impl Exec for Bit<'_> {
 
         let bit = (val >> bit_position) & 0b1;
 
-        self.next_flags((self.0.registers.f, bit))
-            .map(|f| self.0.registers.f = f);
-
-        self.0.pc.add(2);
-        self.0.clock.add(8);
+        if let Some(f) = self.next_flags((self.0.registers.f, bit)) {
+            self.0.registers.f = f;
+        }
 
         Some(ExecRes {
-            ticks: 8,
-            length: 2,
-            instr,
             trace: Some((bit as u16, val as u16)),
+            ..next(self.0, instr)
         })
     }
 
@@ -1,84 +1,65 @@
 use crate::{
+    bus::Bus,
     instr::Instr,
-    microcode::{should_jump, Exec, ExecRes},
+    microcode::{branch, next, should_jump, Exec, ExecRes},
     Cpu,
 };
 
-pub struct Call<'a>(pub &'a mut Cpu);
+pub struct Call<'a, B: Bus>(pub &'a mut Cpu<B>);
 
-impl Exec for Call<'_> {
+impl<B: Bus> Exec for Call<'_, B> {
     type FlagsData = ();
 
     fn run(&mut self, instr: Instr) -> Option<ExecRes> {
-        let next_pc = self.0.pc.get().wrapping_add(3);
+        let next_pc = self.0.pc.get().wrapping_add(instr.length as u16);
 
-        let mut ticks = 12;
-
-        let trace;
-
-        if should_jump(self.0, instr.lhs.unwrap()) {
-            let hi = ((next_pc & 0xFF00) >> 8) as u8;
-            let lo = (next_pc & 0xFF) as u8;
-
-            self.0.sp = self.0.sp.wrapping_sub(1);
-            self.0.bus.write_byte(self.0.sp, hi);
-
-            self.0.sp = self.0.sp.wrapping_sub(1);
-            self.0.bus.write_byte(self.0.sp, lo);
-
-            let jump_addr = self.0.read_next_word();
+        if !should_jump(self.0, instr.lhs.unwrap()) {
+            return Some(ExecRes {
+                trace: Some((0, next_pc)),
+                ..next(self.0, instr)
+            });
+        }
 
-            trace = (1, jump_addr);
+        let hi = ((next_pc & 0xFF00) >> 8) as u8;
+        let lo = (next_pc & 0xFF) as u8;
 
-            self.0.pc.set(jump_addr);
+        self.0.sp = self.0.sp.wrapping_sub(1);
+        self.0.bus.write_byte(self.0.sp, hi);
 
-            ticks = 24;
-        } else {
-            trace = (0, next_pc);
+        self.0.sp = self.0.sp.wrapping_sub(1);
+        self.0.bus.write_byte(self.0.sp, lo);
 
-            self.0.pc.set(next_pc);
-        }
+        let jump_addr = self.0.read_next_word();
 
-        self.0.clock.add(ticks);
+        self.0.pc.set(jump_addr);
 
         Some(ExecRes {
-            ticks,
-            length: next_pc,
-            instr,
-            trace: Some(trace),
+            trace: Some((1, jump_addr)),
+            ..branch(self.0, instr)
         })
     }
 }
 
-pub struct Ret<'a>(pub &'a mut Cpu);
+pub struct Ret<'a, B: Bus>(pub &'a mut Cpu<B>);
 
-impl Exec for Ret<'_> {
+impl<B: Bus> Exec for Ret<'_, B> {
     type FlagsData = ();
 
     fn run(&mut self, instr: Instr) -> Option<ExecRes> {
-        let next_pc = self.0.pc.get();
-
-        if should_jump(self.0, instr.lhs.unwrap()) {
-            let lo = self.0.bus.read_byte(self.0.sp) as u16;
-            self.0.sp = self.0.sp.wrapping_add(1);
+        if !should_jump(self.0, instr.lhs.unwrap()) {
+            return Some(next(self.0, instr));
+        }
 
-            let hi = self.0.bus.read_byte(self.0.sp) as u16;
-            self.0.sp = self.0.sp.wrapping_add(1);
+        let lo = self.0.bus.read_byte(self.0.sp) as u16;
+        self.0.sp = self.0.sp.wrapping_add(1);
 
-            let address = (hi << 8) | lo;
+        let hi = self.0.bus.read_byte(self.0.sp) as u16;
+        self.0.sp = self.0.sp.wrapping_add(1);
 
-            self.0.pc.set(address);
-        } else {
-            self.0.pc.add(1);
-        }
+        let address = (hi << 8) | lo;
 
-        self.0.clock.add(16);
+        self.0.pc.set(address);
 
-        Some(ExecRes {
-            ticks: 16,
-            length: next_pc,
-            instr,
-            trace: None,
-        })
+        Some(branch(self.0, instr))
     }
 }
@@ -1,19 +1,18 @@
 use crate::{
+    bus::Bus,
     instr::{Instr, Operand},
-    microcode::{Exec, ExecRes},
+    microcode::{next, Exec, ExecRes},
     Cpu,
 };
 
-pub struct Dec<'a>(pub &'a mut Cpu);
+pub struct Dec<'a, B: Bus>(pub &'a mut Cpu<B>);
 
-impl Exec for Dec<'_> {
+impl<B: Bus> Exec for Dec<'_, B> {
     type FlagsData = ();
 
     fn run(&mut self, instr: Instr) -> Option<ExecRes> {
         let cpu = &mut self.0;
 
-        let mut ticks = 4;
-
         match instr.rhs {
             Some(Operand::Reg8(kind)) => {
                 let orig_val = cpu.registers.get_8(&kind);
@@ -30,8 +29,6 @@ impl Exec for Dec<'_> {
                 let val = cpu.registers.get_16(&kind).wrapping_sub(1);
 
                 cpu.registers.set_16(&kind, val);
-
-                ticks += 4;
             }
 
             Some(Operand::Reg16Indir(kind)) => {
@@ -45,8 +42,6 @@ impl Exec for Dec<'_> {
                 cpu.registers.f.zero = val == 0;
                 cpu.registers.f.subtract = true;
                 cpu.registers.f.half_carry = orig_val & 0xF == 0x0;
-
-                ticks += 8;
             }
 
             _ => {
@@ -54,14 +49,6 @@ impl Exec for Dec<'_> {
             }
         };
 
-        cpu.pc.add(1);
-        cpu.clock.add(ticks);
-
-        Some(ExecRes {
-            ticks,
-            length: 1,
-            instr,
-            trace: None,
-        })
+        Some(next(cpu, instr))
     }
 }
@@ -1,80 +1,53 @@
 use crate::{
+    bus::Bus,
     instr::Instr,
-    microcode::{should_jump, Exec, ExecRes},
+    microcode::{branch, next, should_jump, Exec, ExecRes},
     Cpu,
 };
 
-pub struct Jp<'a>(pub &'a mut Cpu);
+pub struct Jp<'a, B: Bus>(pub &'a mut Cpu<B>);
 
-impl Exec for Jp<'_> {
+impl<B: Bus> Exec for Jp<'_, B> {
     type FlagsData = ();
 
     fn run(&mut self, instr: Instr) -> Option<ExecRes> {
         let cpu = &mut self.0;
 
         if !should_jump(cpu, instr.lhs.unwrap()) {
-            cpu.pc.add(3);
-            cpu.clock.add(12);
-
-            return Some(ExecRes {
-                ticks: 12,
-                length: 3,
-                instr,
-                trace: None,
-            });
+            return Some(next(cpu, instr));
         }
 
-        let lsb = cpu.bus.read_byte(cpu.pc.get() + 1) as u16;
-        let msb = cpu.bus.read_byte(cpu.pc.get() + 2) as u16;
-
-        let address = (msb << 8) | lsb;
+        let address = cpu.read_next_word();
 
         cpu.pc.set(address);
-        cpu.clock.add(16);
 
-        Some(ExecRes {
-            ticks: 16,
-            length: 3,
-            instr,
-            trace: None,
-        })
+        Some(branch(cpu, instr))
     }
 }
 
-pub struct Jr<'a>(pub &'a mut Cpu);
+pub struct Jr<'a, B: Bus>(pub &'a mut Cpu<B>);
 
-impl Exec for Jr<'_> {
+impl<B: Bus> Exec for Jr<'_, B> {
     type FlagsData = ();
 
     fn run(&mut self, instr: Instr) -> Option<ExecRes> {
         let cpu = &mut self.0;
 
-        let next_step = cpu.pc.get().wrapping_add(2);
-        let mut ticks = 8;
-
         if !should_jump(cpu, instr.lhs.unwrap()) {
-            // do not jump
-            cpu.pc.set(next_step);
-        } else {
-            let offset = cpu.read_next_byte() as i8;
+            return Some(next(cpu, instr));
+        }
 
-            let next_pc = if offset >= 0 {
-                next_step.wrapping_add(offset as u16)
-            } else {
-                next_step.wrapping_sub(offset.abs() as u16)
-            };
+        let next_step = cpu.pc.get().wrapping_add(instr.length as u16);
+        let offset = cpu.read_next_byte() as i8;
 
-            cpu.pc.set(next_pc);
-            ticks += 4;
-        }
+        let next_pc = if offset >= 0 {
+            next_step.wrapping_add(offset as u16)
+        } else {
+            next_step.wrapping_sub(offset.unsigned_abs() as u16)
+        };
 
-        cpu.clock.add(ticks);
+        cpu.pc.set(next_pc);
 
-        Some(ExecRes {
-            ticks,
-            length: 3,
-            instr,
-            trace: None,
-        })
+        Some(branch(cpu, instr))
     }
 }
@@ -1,13 +1,14 @@
 use crate::{
+    bus::Bus,
     instr::{Instr, Operand, PostOp},
-    microcode::{Exec, ExecRes},
+    microcode::{next, Exec, ExecRes},
     registers::{Reg16Kind, Reg8Kind},
     Cpu,
 };
 
-pub struct Ld<'a>(pub &'a mut Cpu);
+pub struct Ld<'a, B: Bus>(pub &'a mut Cpu<B>);
 
-impl Ld<'_> {
+impl<B: Bus> Ld<'_, B> {
     fn rhs(&self, instr: &Instr) -> u8 {
         use Reg8Kind::*;
 
@@ -89,7 +90,7 @@ impl Ld<'_> {
     }
 }
 
-impl Exec for Ld<'_> {
+impl<B: Bus> Exec for Ld<'_, B> {
     type FlagsData = ();
 
     fn run(&mut self, instr: Instr) -> Option<ExecRes> {
@@ -97,34 +98,6 @@ impl Exec for Ld<'_> {
 
         let lhs = self.lhs(&instr, rhs);
 
-        let mut length = 1;
-        let mut ticks = 8;
-
-        match &instr.rhs {
-            Some(Operand::U8Indir(_)) => {
-                length += 1;
-                ticks += 4;
-            }
-            Some(Operand::U8) => {
-                length += 1;
-                ticks += 4;
-            }
-            _ => match &instr.lhs {
-                Some(Operand::U8Indir(_)) => {
-                    length += 1;
-                    ticks += 4;
-                }
-                Some(Operand::U16Indir) => {
-                    length += 1;
-                    ticks += 4;
-                }
-                _ => {}
-            },
-        };
-
-        self.0.pc.add(length);
-        self.0.clock.add(ticks);
-
         match &instr.post_op {
             Some(PostOp::Dec(Reg16Kind::HL)) => self
                 .0
@@ -139,10 +112,8 @@ impl Exec for Ld<'_> {
         };
 
         Some(ExecRes {
-            ticks,
-            length,
-            instr,
             trace: Some((lhs, rhs as u16)),
+            ..next(self.0, instr)
         })
     }
 }
@@ -1,13 +1,14 @@
 use crate::{
+    bus::Bus,
     instr::{Instr, Operand},
-    microcode::{Exec, ExecRes},
+    microcode::{next, Exec, ExecRes},
     registers::Reg16Kind,
     Cpu,
 };
 
-pub struct LdWord<'a>(pub &'a mut Cpu);
+pub struct LdWord<'a, B: Bus>(pub &'a mut Cpu<B>);
 
-impl Exec for LdWord<'_> {
+impl<B: Bus> Exec for LdWord<'_, B> {
     type FlagsData = ();
 
     fn run(&mut self, instr: Instr) -> Option<ExecRes> {
@@ -35,14 +36,6 @@ impl Exec for LdWord<'_> {
             }
         };
 
-        self.0.pc.add(3);
-        self.0.clock.add(12);
-
-        Some(ExecRes {
-            ticks: 12,
-            length: 3,
-            instr,
-            trace: None,
-        })
+        Some(next(self.0, instr))
     }
 }
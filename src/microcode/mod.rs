@@ -14,6 +14,7 @@ mod subs;
 mod xor;
 
 use crate::{
+    bus::Bus,
     instr::{CondKind, Instr, Operand},
     registers::{FlagsRegister, Reg16Kind, Reg8Kind, Registers},
     Cpu,
@@ -39,28 +40,52 @@ pub trait Exec {
 
     fn run(&mut self, instr: Instr) -> Option<ExecRes>;
 
-    fn res(&self, ticks: u8, length: u16, instr: Instr) -> ExecRes {
-        ExecRes {
-            ticks,
-            length,
-            instr,
-            trace: None,
-        }
-    }
-
     fn next_flags(&self, _data: Self::FlagsData) -> Option<FlagsRegister> {
         None
     }
 }
 
+/// The unprefixed instruction `tag`, for running executors on their own.
+#[cfg(test)]
+fn decoded(tag: &str) -> Instr {
+    *crate::instr::OPCODES
+        .iter()
+        .find(|instr| instr.tag == tag)
+        .unwrap()
+}
+
 pub struct ExecRes {
     pub ticks: u8,
-    pub length: u16,
     pub instr: Instr,
+    /// Operand values for `--trace`.
     pub trace: Option<(u16, u16)>,
 }
 
-pub fn should_jump(cpu: &Cpu, op: Operand) -> bool {
+/// Moves past `instr` and charges its cycles, which for a conditional
+/// instruction are those of the branch not taken.
+pub fn next<B: Bus>(cpu: &mut Cpu<B>, instr: Instr) -> ExecRes {
+    cpu.pc.add(instr.length as u16);
+    cpu.clock.add(instr.cycles);
+
+    ExecRes {
+        ticks: instr.cycles,
+        instr,
+        trace: None,
+    }
+}
+
+/// Charges the cycles of a taken branch, which has already set PC.
+pub fn branch<B: Bus>(cpu: &mut Cpu<B>, instr: Instr) -> ExecRes {
+    cpu.clock.add(instr.branch_cycles);
+
+    ExecRes {
+        ticks: instr.branch_cycles,
+        instr,
+        trace: None,
+    }
+}
+
+pub fn should_jump<B: Bus>(cpu: &Cpu<B>, op: Operand) -> bool {
     use Operand::*;
 
     match op {
@@ -1,40 +1,37 @@
 use crate::{
+    bus::Bus,
     instr::Instr,
-    microcode::{op_to_u8_reg, Exec, ExecRes},
+    microcode::{next, op_to_u8_reg, Exec, ExecRes},
     registers::FlagsRegister,
     Cpu,
 };
 
-pub struct Or<'a>(pub &'a mut Cpu);
+pub struct Or<'a, B: Bus>(pub &'a mut Cpu<B>);
 
-impl Exec for Or<'_> {
+impl<B: Bus> Exec for Or<'_, B> {
     type FlagsData = u8;
 
     fn run(&mut self, instr: Instr) -> Option<ExecRes> {
-        let cpu = &mut self.0;
+        let val = op_to_u8_reg(&instr.rhs?, &self.0.registers);
 
-        let val = op_to_u8_reg(&instr.rhs?, &cpu.registers);
+        let next_val = self.0.registers.a | val;
 
-        let next_val = cpu.registers.a | val;
+        if let Some(f) = self.next_flags(next_val) {
+            self.0.registers.f = f;
+        }
 
-        cpu.registers.a = next_val;
+        let cpu = &mut self.0;
 
-        cpu.pc.add(1);
-        cpu.clock.add(4);
+        cpu.registers.a = next_val;
 
-        Some(ExecRes {
-            ticks: 4,
-            length: 1,
-            instr,
-            trace: None,
-        })
+        Some(next(cpu, instr))
     }
 
     fn next_flags(&self, data: Self::FlagsData) -> Option<FlagsRegister> {
         Some(FlagsRegister {
             zero: data == 0,
             subtract: false,
-            half_carry: true,
+            half_carry: false,
             carry: false,
         })
     }
@@ -43,10 +40,24 @@ impl Exec for Or<'_> {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::{Cpu, Registers};
+    use crate::{
+        microcode::decoded,
+        mmu::{BOOT_ROM_SIZE, ROM_BANK_0_SIZE},
+        registers::{Reg8Kind, Registers},
+        Cpu,
+    };
 
     fn cpu(registers: Registers) -> Cpu {
-        Cpu::new(vec![], vec![], Some(registers))
+        Cpu::new(
+            vec![0; BOOT_ROM_SIZE],
+            vec![0; ROM_BANK_0_SIZE],
+            Some(registers),
+        )
+        .unwrap()
+    }
+
+    fn or(cpu: &mut Cpu, reg: Reg8Kind) {
+        Or(cpu).run(decoded(&format!("OR A, {:?}", reg)));
     }
 
     #[test]
@@ -61,7 +72,7 @@ mod tests {
 
         assert_eq!(cpu.registers.a, 0b1010_1111);
 
-        assert_eq!(cpu.registers.f.zero, false);
+        assert!(!cpu.registers.f.zero);
     }
 
     #[test]
@@ -74,10 +85,10 @@ mod tests {
 
         or(&mut cpu, Reg8Kind::D);
 
-        assert_eq!(cpu.registers.f.zero, false);
-        assert_eq!(cpu.registers.f.subtract, false);
-        assert_eq!(cpu.registers.f.half_carry, true);
-        assert_eq!(cpu.registers.f.carry, false);
+        assert!(!cpu.registers.f.zero);
+        assert!(!cpu.registers.f.subtract);
+        assert!(!cpu.registers.f.half_carry);
+        assert!(!cpu.registers.f.carry);
     }
 
     #[test]
@@ -1,12 +1,13 @@
 use crate::{
+    bus::Bus,
     instr::{Instr, Operand, PostOp},
-    microcode::{Exec, ExecRes},
+    microcode::{next, Exec, ExecRes},
     Cpu,
 };
 
-pub struct Rot<'a>(pub &'a mut Cpu);
+pub struct Rot<'a, B: Bus>(pub &'a mut Cpu<B>);
 
-impl Exec for Rot<'_> {
+impl<B: Bus> Exec for Rot<'_, B> {
     type FlagsData = ();
 
     fn run(&mut self, instr: Instr) -> Option<ExecRes> {
@@ -22,7 +23,7 @@ impl Exec for Rot<'_> {
 
         match instr.rhs {
             Some(Operand::RotLeft) => {
-                val = val << 1;
+                val <<= 1;
             }
 
             _ => {
@@ -34,7 +35,7 @@ impl Exec for Rot<'_> {
             Some(PostOp::CarryToB0) => {
                 let carry = if cpu.registers.f.carry { 1 } else { 0 };
 
-                val = val | carry;
+                val |= carry;
             }
 
             _ => panic!("{}: unsupported post_op {:?}", instr, instr.post_op),
@@ -44,22 +45,13 @@ impl Exec for Rot<'_> {
 
         // TODO: Flags
 
-        cpu.pc.add(2);
-        // TODO:
-        cpu.clock.add(8);
-
-        Some(ExecRes {
-            ticks: 8,
-            length: 2,
-            instr,
-            trace: None,
-        })
+        Some(next(cpu, instr))
     }
 }
 
-pub struct RotA<'a>(pub &'a mut Cpu);
+pub struct RotA<'a, B: Bus>(pub &'a mut Cpu<B>);
 
-impl Exec for RotA<'_> {
+impl<B: Bus> Exec for RotA<'_, B> {
     type FlagsData = ();
 
     fn run(&mut self, instr: Instr) -> Option<ExecRes> {
@@ -69,7 +61,7 @@ impl Exec for RotA<'_> {
             Some(Operand::RotLeft) => {
                 self.0.registers.f.carry = (val & 0x80) == 0x80;
 
-                val = val << 1;
+                val <<= 1;
             }
 
             _ => {
@@ -81,7 +73,7 @@ impl Exec for RotA<'_> {
             Some(PostOp::CarryToB0) => {
                 let carry = if self.0.registers.f.carry { 1 } else { 0 };
 
-                val = val | carry;
+                val |= carry;
             }
 
             _ => panic!("{}: unsupported post_op {:?}", instr, instr.post_op),
@@ -93,14 +85,6 @@ impl Exec for RotA<'_> {
         self.0.registers.f.subtract = false;
         self.0.registers.f.half_carry = false;
 
-        self.0.pc.add(1);
-        self.0.clock.add(4);
-
-        Some(ExecRes {
-            ticks: 4,
-            length: 1,
-            instr,
-            trace: None,
-        })
+        Some(next(self.0, instr))
     }
 }
@@ -1,12 +1,13 @@
 use crate::{
+    bus::Bus,
     instr::Instr,
-    microcode::{op_to_u16_reg, op_to_u16_reg_w, Exec, ExecRes},
+    microcode::{next, op_to_u16_reg, op_to_u16_reg_w, Exec, ExecRes},
     Cpu,
 };
 
-pub struct Push<'a>(pub &'a mut Cpu);
+pub struct Push<'a, B: Bus>(pub &'a mut Cpu<B>);
 
-impl Exec for Push<'_> {
+impl<B: Bus> Exec for Push<'_, B> {
     type FlagsData = ();
 
     fn run(&mut self, instr: Instr) -> Option<ExecRes> {
@@ -23,21 +24,13 @@ impl Exec for Push<'_> {
         cpu.sp = cpu.sp.wrapping_sub(1);
         cpu.bus.write_byte(cpu.sp, lo);
 
-        cpu.pc.add(1);
-        cpu.clock.add(16);
-
-        Some(ExecRes {
-            ticks: 16,
-            length: 1,
-            instr,
-            trace: None,
-        })
+        Some(next(cpu, instr))
     }
 }
 
-pub struct Pop<'a>(pub &'a mut Cpu);
+pub struct Pop<'a, B: Bus>(pub &'a mut Cpu<B>);
 
-impl Exec for Pop<'_> {
+impl<B: Bus> Exec for Pop<'_, B> {
     type FlagsData = ();
 
     fn run(&mut self, instr: Instr) -> Option<ExecRes> {
@@ -53,13 +46,6 @@ impl Exec for Pop<'_> {
 
         op_to_u16_reg_w(&instr.rhs?, &mut cpu.registers, data);
 
-        cpu.pc.add(1);
-
-        Some(ExecRes {
-            ticks: 12,
-            length: 1,
-            instr,
-            trace: None,
-        })
+        Some(next(cpu, instr))
     }
 }
@@ -1,12 +1,13 @@
 use crate::{
+    bus::Bus,
     instr::{Instr, Operand},
-    microcode::{op_to_u8_reg, Exec, ExecRes},
+    microcode::{next, op_to_u8_reg, Exec, ExecRes},
     Cpu,
 };
 
-pub struct Sub<'a>(pub &'a mut Cpu);
+pub struct Sub<'a, B: Bus>(pub &'a mut Cpu<B>);
 
-impl Exec for Sub<'_> {
+impl<B: Bus> Exec for Sub<'_, B> {
     type FlagsData = ();
 
     fn run(&mut self, instr: Instr) -> Option<ExecRes> {
@@ -24,32 +25,20 @@ impl Exec for Sub<'_> {
         cpu.registers.f.carry = carry;
 
         cpu.registers.a = new_value;
-        cpu.pc.add(1);
-        cpu.clock.add(4);
-
-        Some(ExecRes {
-            ticks: 4,
-            length: 1,
-            instr,
-            trace: None,
-        })
+        Some(next(cpu, instr))
     }
 }
 
-pub struct Cp<'a>(pub &'a mut Cpu);
+pub struct Cp<'a, B: Bus>(pub &'a mut Cpu<B>);
 
-impl Exec for Cp<'_> {
+impl<B: Bus> Exec for Cp<'_, B> {
     type FlagsData = ();
 
     fn run(&mut self, instr: Instr) -> Option<ExecRes> {
         let cpu = &mut self.0;
 
         let val = match instr.rhs {
-            Some(Operand::U8) => {
-                cpu.pc.add(1);
-                cpu.clock.add(4);
-                cpu.read_next_byte()
-            }
+            Some(Operand::U8) => cpu.read_next_byte(),
             _ => panic!(
                 "[{:X} | {}] unsupported operand {:?}",
                 instr.pos, instr.tag, instr.rhs
@@ -66,21 +55,13 @@ impl Exec for Cp<'_> {
         cpu.registers.f.half_carry = (a & 0xF) < (val & 0xF);
         cpu.registers.f.carry = carry;
 
-        cpu.pc.add(1);
-        cpu.clock.add(4);
-
-        Some(ExecRes {
-            ticks: 4,
-            length: 1,
-            instr,
-            trace: None,
-        })
+        Some(next(cpu, instr))
     }
 }
 
-pub struct Sbc<'a>(pub &'a mut Cpu);
+pub struct Sbc<'a, B: Bus>(pub &'a mut Cpu<B>);
 
-impl Exec for Sbc<'_> {
+impl<B: Bus> Exec for Sbc<'_, B> {
     type FlagsData = ();
 
     fn run(&mut self, instr: Instr) -> Option<ExecRes> {
@@ -100,29 +81,38 @@ impl Exec for Sbc<'_> {
         cpu.registers.f.carry = mid_carry || carry;
 
         cpu.registers.a = new_value;
-        cpu.pc.add(1);
-        cpu.clock.add(4);
-
-        Some(ExecRes {
-            ticks: 4,
-            length: 1,
-            instr,
-            trace: None,
-        })
+        Some(next(cpu, instr))
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::{memory_bus, Cpu, Registers};
+    use crate::{
+        microcode::decoded,
+        mmu::{BOOT_ROM_SIZE, ROM_BANK_0_SIZE},
+        registers::Registers,
+        Cpu,
+    };
 
     fn cpu(registers: Registers) -> Cpu {
         Cpu::new(
-            vec![0; memory_bus::BOOT_ROM_SIZE],
-            vec![0; memory_bus::ROM_BANK_0_SIZE],
+            vec![0; BOOT_ROM_SIZE],
+            vec![0; ROM_BANK_0_SIZE],
             Some(registers),
         )
+        .unwrap()
+    }
+
+    // SUB and SBC of a value, through B
+    fn sub(cpu: &mut Cpu, val: u8) {
+        cpu.registers.b = val;
+        Sub(cpu).run(decoded("SUB A, B"));
+    }
+
+    fn sbc(cpu: &mut Cpu, val: u8) {
+        cpu.registers.b = val;
+        Sbc(cpu).run(decoded("SBC A, B"));
     }
 
     #[test]
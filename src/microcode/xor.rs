@@ -1,12 +1,13 @@
 use crate::{
+    bus::Bus,
     instr::Instr,
-    microcode::{op_to_u8_reg, Exec, ExecRes},
+    microcode::{next, op_to_u8_reg, Exec, ExecRes},
     Cpu,
 };
 
-pub struct Xor<'a>(pub &'a mut Cpu);
+pub struct Xor<'a, B: Bus>(pub &'a mut Cpu<B>);
 
-impl Exec for Xor<'_> {
+impl<B: Bus> Exec for Xor<'_, B> {
     type FlagsData = ();
 
     fn run(&mut self, instr: Instr) -> Option<ExecRes> {
@@ -22,25 +23,31 @@ impl Exec for Xor<'_> {
         cpu.registers.f.carry = false;
 
         cpu.registers.a = new_value;
-        cpu.pc.add(1);
-        cpu.clock.add(4);
-
-        Some(ExecRes {
-            ticks: 4,
-            length: 1,
-            instr,
-            trace: None,
-        })
+        Some(next(cpu, instr))
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::{Cpu, Registers};
+    use crate::{
+        microcode::decoded,
+        mmu::{BOOT_ROM_SIZE, ROM_BANK_0_SIZE},
+        registers::{Reg8Kind, Registers},
+        Cpu,
+    };
 
     fn cpu(registers: Registers) -> Cpu {
-        Cpu::new(vec![], vec![], Some(registers))
+        Cpu::new(
+            vec![0; BOOT_ROM_SIZE],
+            vec![0; ROM_BANK_0_SIZE],
+            Some(registers),
+        )
+        .unwrap()
+    }
+
+    fn xor(cpu: &mut Cpu, reg: Reg8Kind) {
+        Xor(cpu).run(decoded(&format!("XOR A, {:?}", reg)));
     }
 
     #[test]
@@ -55,7 +62,7 @@ mod tests {
 
         assert_eq!(cpu.registers.a, 0b0010_0110);
 
-        assert_eq!(cpu.registers.f.zero, false);
+        assert!(!cpu.registers.f.zero);
     }
 
     #[test]
@@ -68,10 +75,10 @@ mod tests {
 
         xor(&mut cpu, Reg8Kind::D);
 
-        assert_eq!(cpu.registers.f.zero, false);
-        assert_eq!(cpu.registers.f.subtract, false);
-        assert_eq!(cpu.registers.f.half_carry, false);
-        assert_eq!(cpu.registers.f.carry, false);
+        assert!(!cpu.registers.f.zero);
+        assert!(!cpu.registers.f.subtract);
+        assert!(!cpu.registers.f.half_carry);
+        assert!(!cpu.registers.f.carry);
     }
 
     #[test]
@@ -1,5 +1,15 @@
-use crate::gpu::Gpu;
-use std::convert::TryInto;
+use crate::{
+    apu::Apu,
+    bus::Bus,
+    cheats::Cheats,
+    checksum::crc32,
+    gpu::Gpu,
+    header,
+    joypad::Joypad,
+    sgb::Sgb,
+    state::{Reader, Writer},
+};
+use std::{cell::Cell, convert::TryInto};
 
 const BOOT_ROM_START: usize = 0x00;
 const BOOT_ROM_END: usize = 0xFF;
@@ -24,16 +34,19 @@ const E_RAM_SIZE: usize = E_RAM_END - E_RAM_START + 1;
 const W_RAM_START: usize = 0xC000;
 const W_RAM_END: usize = 0xDFFF;
 const W_RAM_SIZE: usize = W_RAM_END - W_RAM_START + 1;
+const W_RAM_BANK_SIZE: usize = W_RAM_SIZE / 2;
+const W_RAM_BANKS: usize = 8;
 
 const W_RAM_SHAD_START: usize = 0xE000;
 const W_RAM_SHAD_END: usize = 0xFDFF;
-const W_RAM_SHAD_SIZE: usize = W_RAM_SHAD_END - W_RAM_SHAD_START + 1;
 
-// TODO: Maps to GPU; 160B data, rest 0.
 const OAM_START: usize = 0xFE00;
 const OAM_END: usize = 0xFE9F;
 pub const OAM_SIZE: usize = OAM_END - OAM_START + 1;
 
+const UNUSABLE_START: usize = 0xFEA0;
+const UNUSABLE_END: usize = 0xFEFF;
+
 const IO_REGS_START: usize = 0xFF00;
 const IO_REGS_END: usize = 0xFF7F;
 const IO_REGS_SIZE: usize = IO_REGS_END - IO_REGS_START + 1;
@@ -42,110 +55,767 @@ const Z_RAM_START: usize = 0xFF80;
 const Z_RAM_END: usize = 0xFFFE;
 const Z_RAM_SIZE: usize = Z_RAM_END - Z_RAM_START + 1;
 
+const IE_REG: usize = 0xFFFF;
+
+const P1_REG: u16 = 0xFF00;
+const SB_REG: u16 = 0xFF01;
+const SC_REG: u16 = 0xFF02;
+const KEY1_REG: u16 = 0xFF4D;
+const BOOT_REG: u16 = 0xFF50;
+const HDMA1_REG: u16 = 0xFF51;
+const HDMA5_REG: u16 = 0xFF55;
+const SVBK_REG: u16 = 0xFF70;
+
+const KEY1_PREPARE: u8 = 1 << 0;
+const KEY1_DOUBLE_SPEED: u8 = 1 << 7;
+const HDMA_HBLANK: u8 = 1 << 7;
+// Length of a 16-byte DMA block in single-speed cycles, which is twice as
+// many CPU cycles in double speed.
+const HDMA_BLOCK_TICKS: u8 = 32;
+const STAT_MODE: u8 = 0b11;
+const MODE_HBLANK: u8 = 0;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum WatchKind {
+    Read,
+    Write,
+    Access,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Watchpoint {
+    pub kind: WatchKind,
+    pub address: u16,
+    pub length: u16,
+}
+
+impl Watchpoint {
+    fn matches(&self, address: u16, write: bool) -> bool {
+        let kind = match (self.kind, write) {
+            (WatchKind::Access, _) => true,
+            (WatchKind::Write, write) => write,
+            (WatchKind::Read, write) => !write,
+        };
+
+        kind && address.wrapping_sub(self.address) < self.length.max(1)
+    }
+}
+
+#[derive(Clone)]
 pub struct Mmu {
     in_bios: bool,
     boot_rom: [u8; BOOT_ROM_SIZE],
     rom_bank_0: [u8; ROM_BANK_0_SIZE],
     rom_bank_n: [u8; ROM_BANK_N_SIZE],
     e_ram: [u8; E_RAM_SIZE],
-    w_ram: [u8; W_RAM_SIZE],
+    // Bank 0 at 0xC000, then banks 1-7 for 0xD000 (only 1 on the DMG).
+    w_ram: [u8; W_RAM_BANK_SIZE * W_RAM_BANKS],
     z_ram: [u8; Z_RAM_SIZE],
+    io: [u8; IO_REGS_SIZE],
+    ie: u8,
+    serial: Vec<u8>,
+    cgb: bool,
+    svbk: u8,
+    key1: u8,
+    // HDMA1-4 as written, then the blocks left in an HBlank transfer.
+    hdma: [u8; 4],
+    hdma_blocks: Option<u8>,
+    // HDMA5 while no HBlank transfer runs: 0xFF, or bit 7 and the blocks
+    // left minus one if the last one was cancelled.
+    hdma_stopped: u8,
+    // CPU cycles spent waiting on general purpose DMA, see `take_stall`.
+    stall: u32,
     pub gpu: Gpu,
+    pub apu: Apu,
+    pub joypad: Joypad,
+    /// Only with `set_sgb`.
+    pub sgb: Option<Sgb>,
+    /// Not saved in states.
+    pub cheats: Cheats,
+    watchpoints: Vec<Watchpoint>,
+    // Reads go through &self, so the hit is latched in a Cell.
+    watch_hit: Cell<Option<(Watchpoint, u16)>>,
+}
+
+/// Checks the boot ROM's size up front, where failing is cheap; the whole
+/// `Mmu` is too large to pass through `?` in debug builds.
+pub(crate) fn boot_rom(
+    buffer: Vec<u8>,
+) -> Result<Option<[u8; BOOT_ROM_SIZE]>, String> {
+    if buffer.is_empty() {
+        return Ok(None);
+    }
+
+    buffer
+        .try_into()
+        .map(Some)
+        .map_err(|_| format!("boot ROM must be {} bytes", BOOT_ROM_SIZE))
 }
 
 impl Mmu {
-    pub fn new(boot_rom_buffer: Vec<u8>, game_rom_buffer: Vec<u8>) -> Self {
-        let boot_rom: [u8; BOOT_ROM_SIZE] = boot_rom_buffer.try_into().unwrap();
+    /// An empty `boot_rom_buffer` starts with the cartridge mapped at 0x0000.
+    pub fn new(
+        boot_rom_buffer: Vec<u8>,
+        game_rom_buffer: Vec<u8>,
+    ) -> Result<Self, String> {
+        let boot_rom = boot_rom(boot_rom_buffer)?;
+
+        Ok(Self::with_boot_rom(boot_rom, game_rom_buffer))
+    }
+
+    pub(crate) fn with_boot_rom(
+        boot_rom: Option<[u8; BOOT_ROM_SIZE]>,
+        game_rom_buffer: Vec<u8>,
+    ) -> Self {
+        let in_bios = boot_rom.is_some();
+        let boot_rom = boot_rom.unwrap_or([0; BOOT_ROM_SIZE]);
 
         let mut rom_bank_0 = [0; ROM_BANK_0_SIZE];
+        let mut rom_bank_n = [0; ROM_BANK_N_SIZE];
 
-        for i in 0..ROM_BANK_0_SIZE {
-            rom_bank_0[i] = game_rom_buffer[i];
+        for (i, byte) in game_rom_buffer.iter().enumerate() {
+            match i {
+                ROM_BANK_0_START..=ROM_BANK_0_END => rom_bank_0[i] = *byte,
+                ROM_BANK_N_START..=ROM_BANK_N_END => {
+                    rom_bank_n[i - ROM_BANK_N_START] = *byte
+                }
+                // TODO: Switchable banks need an MBC
+                _ => break,
+            }
         }
 
+        let cgb = header::cgb(&game_rom_buffer);
+
+        let mut gpu = Gpu::new();
+        gpu.set_cgb(cgb);
+
         Self {
-            in_bios: false,
+            in_bios,
             boot_rom,
             rom_bank_0,
-            // TODO: Is this correct? How do the switchable banks (if present) map?
-            rom_bank_n: [0; ROM_BANK_N_SIZE],
+            rom_bank_n,
             e_ram: [0; E_RAM_SIZE],
-            w_ram: [0; W_RAM_SIZE],
+            w_ram: [0; W_RAM_BANK_SIZE * W_RAM_BANKS],
             z_ram: [0; Z_RAM_SIZE],
+            io: [0; IO_REGS_SIZE],
+            ie: 0,
+            serial: Vec::new(),
+            cgb,
+            svbk: 0,
+            key1: 0,
+            hdma: [0; 4],
+            hdma_blocks: None,
+            hdma_stopped: 0xFF,
+            stall: 0,
             // TODO: Gpu needs to have acces to current clock
-            gpu: Gpu::new(),
+            gpu,
+            apu: Apu::new(),
+            joypad: Joypad::new(),
+            sgb: None,
+            cheats: Cheats::default(),
+            watchpoints: Vec::new(),
+            watch_hit: Cell::new(None),
+        }
+    }
+
+    /// IO state the boot ROM leaves behind, for starting without one.
+    pub fn post_boot(&mut self) {
+        self.write_byte(0xFF26, 0xF1);
+        self.write_byte(0xFF25, 0xF3);
+        self.write_byte(0xFF24, 0x77);
+        self.write_byte(0xFF40, 0x91);
+        self.write_byte(0xFF47, 0xFC);
+        self.write_byte(0xFF48, 0xFF);
+        self.write_byte(0xFF49, 0xFF);
+        self.write_byte(BOOT_REG, 0x01);
+
+        if self.cgb {
+            // All white, as the CGB boot ROM leaves them for CGB games
+            for cps in &[0xFF68, 0xFF6A] {
+                self.write_byte(*cps, 0x80);
+
+                for _ in 0..64 {
+                    self.write_byte(cps + 1, 0xFF);
+                }
+            }
+        }
+    }
+
+    /// Runs as a Super Game Boy, if the cartridge supports it.
+    pub fn set_sgb(&mut self, enabled: bool) {
+        self.sgb = if enabled && header::sgb(&self.rom_bank_0) {
+            Some(Sgb::new())
+        } else {
+            None
+        };
+    }
+
+    /// Whether the cartridge runs in CGB mode.
+    pub fn is_cgb(&self) -> bool {
+        self.cgb
+    }
+
+    pub fn double_speed(&self) -> bool {
+        self.key1 & KEY1_DOUBLE_SPEED != 0
+    }
+
+    /// Index into `w_ram` for 0xC000-0xDFFF or its echo.
+    fn w_ram_index(&self, address: usize) -> usize {
+        let offset = match address {
+            W_RAM_SHAD_START..=W_RAM_SHAD_END => address - W_RAM_SHAD_START,
+            _ => address - W_RAM_START,
+        };
+
+        if offset < W_RAM_BANK_SIZE {
+            offset
+        } else {
+            self.w_ram_bank() * W_RAM_BANK_SIZE + offset - W_RAM_BANK_SIZE
+        }
+    }
+
+    fn w_ram_bank(&self) -> usize {
+        (self.svbk as usize & 0b111).max(1)
+    }
+
+    /// Applies the GameShark codes. They only write RAM, and bypass
+    /// watchpoints.
+    fn apply_cheats(&mut self) {
+        for (bank, address, byte) in self.cheats.ram_writes() {
+            let address = address as usize;
+
+            match (address, bank) {
+                (0xD000..=W_RAM_END, Some(bank)) => {
+                    let bank = (bank as usize).max(1);
+
+                    self.w_ram[bank * W_RAM_BANK_SIZE + address - 0xD000] = byte
+                }
+                (E_RAM_START..=E_RAM_END, _) => {
+                    self.e_ram[address - E_RAM_START] = byte
+                }
+                (W_RAM_START..=W_RAM_SHAD_END, _) => {
+                    let i = self.w_ram_index(address);
+
+                    self.w_ram[i] = byte
+                }
+                (Z_RAM_START..=Z_RAM_END, _) => {
+                    self.z_ram[address - Z_RAM_START] = byte
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn hdma_source(&self) -> u16 {
+        u16::from_be_bytes([self.hdma[0], self.hdma[1]]) & 0xFFF0
+    }
+
+    fn hdma_destination(&self) -> u16 {
+        u16::from_be_bytes([self.hdma[2], self.hdma[3]]) & 0x1FF0
+    }
+
+    /// Copies 16 bytes to VRAM and advances both addresses.
+    fn hdma_block(&mut self) {
+        let (source, destination) =
+            (self.hdma_source(), self.hdma_destination());
+
+        for i in 0..16 {
+            let byte = self.fetch(source.wrapping_add(i));
+
+            self.gpu
+                .write_vram(((destination + i) & 0x1FFF) as usize, byte);
+        }
+
+        let source = source.wrapping_add(16).to_be_bytes();
+        let destination = (destination + 16).to_be_bytes();
+
+        self.hdma = [source[0], source[1], destination[0], destination[1]];
+    }
+
+    fn write_hdma5(&mut self, byte: u8) {
+        let blocks = (byte & 0x7F) + 1;
+
+        if let (Some(left), 0) = (self.hdma_blocks, byte & HDMA_HBLANK) {
+            // Cancels the HBlank transfer in progress
+            self.hdma_blocks = None;
+            self.hdma_stopped = HDMA_HBLANK | (left - 1);
+        } else if byte & HDMA_HBLANK != 0 {
+            self.hdma_blocks = Some(blocks);
+        } else {
+            // General purpose DMA, all at once while the CPU waits
+            let ticks = if self.double_speed() {
+                HDMA_BLOCK_TICKS * 2
+            } else {
+                HDMA_BLOCK_TICKS
+            };
+
+            for _ in 0..blocks {
+                self.hdma_block();
+                self.tick(ticks);
+                self.stall += ticks as u32;
+            }
+
+            self.hdma_stopped = 0xFF;
+        }
+    }
+
+    fn read_hdma5(&self) -> u8 {
+        match self.hdma_blocks {
+            Some(blocks) => blocks - 1,
+            None => self.hdma_stopped,
+        }
+    }
+
+    /// CRC-32 of the mapped cartridge ROM.
+    pub fn rom_checksum(&self) -> u32 {
+        let mut rom = self.rom_bank_0.to_vec();
+        rom.extend_from_slice(&self.rom_bank_n);
+
+        crc32(&rom)
+    }
+
+    /// Watchpoints are debugger state and not saved.
+    pub fn save(&self, w: &mut Writer) {
+        w.bool(self.in_bios);
+        w.bytes(&self.e_ram);
+        w.bytes(&self.w_ram);
+        w.bytes(&self.z_ram);
+        w.bytes(&self.io);
+        w.u8(self.ie);
+        w.vec(&self.serial);
+
+        for reg in &[self.svbk, self.key1] {
+            w.u8(*reg);
+        }
+
+        w.bytes(&self.hdma);
+        w.bool(self.hdma_blocks.is_some());
+        w.u8(self.hdma_blocks.unwrap_or(0));
+        w.u8(self.hdma_stopped);
+
+        self.gpu.save(w);
+        self.apu.save(w);
+        self.joypad.save(w);
+
+        w.bool(self.sgb.is_some());
+
+        if let Some(sgb) = &self.sgb {
+            sgb.save(w);
+        }
+    }
+
+    pub fn load(&mut self, r: &mut Reader) -> Result<(), String> {
+        self.in_bios = r.bool()?;
+        r.fill(&mut self.e_ram)?;
+        r.fill(&mut self.w_ram)?;
+        r.fill(&mut self.z_ram)?;
+        r.fill(&mut self.io)?;
+        self.ie = r.u8()?;
+        self.serial = r.vec()?;
+        self.svbk = r.u8()?;
+        self.key1 = r.u8()?;
+        r.fill(&mut self.hdma)?;
+
+        let hdma = r.bool()?;
+        let blocks = r.u8()?;
+        self.hdma_blocks = if hdma { Some(blocks) } else { None };
+        self.hdma_stopped = r.u8()?;
+
+        self.gpu.load(r)?;
+        self.apu.load(r)?;
+        self.joypad.load(r)?;
+
+        match (&mut self.sgb, r.bool()?) {
+            (Some(sgb), true) => sgb.load(r),
+            (None, false) => Ok(()),
+            _ => Err("save state is for a different SGB setting".to_string()),
+        }
+    }
+
+    /// Bytes sent out through the serial port so far.
+    pub fn serial_output(&self) -> &[u8] {
+        &self.serial
+    }
+
+    pub fn add_watchpoint(&mut self, watchpoint: Watchpoint) {
+        if !self.watchpoints.contains(&watchpoint) {
+            self.watchpoints.push(watchpoint);
+        }
+    }
+
+    pub fn remove_watchpoint(&mut self, watchpoint: Watchpoint) -> bool {
+        let len = self.watchpoints.len();
+
+        self.watchpoints.retain(|w| *w != watchpoint);
+
+        self.watchpoints.len() != len
+    }
+
+    /// Returns the first watchpoint triggered since the last call, along
+    /// with the accessed address.
+    pub fn take_watch_hit(&self) -> Option<(Watchpoint, u16)> {
+        self.watch_hit.take()
+    }
+
+    fn watch(&self, address: u16, write: bool) {
+        if self.watch_hit.get().is_some() {
+            return;
+        }
+
+        if let Some(w) =
+            self.watchpoints.iter().find(|w| w.matches(address, write))
+        {
+            self.watch_hit.set(Some((*w, address)));
+        }
+    }
+
+    /// Side-effect free read for debuggers, `None` where nothing is mapped.
+    pub fn peek_byte(&self, address: u16) -> Option<u8> {
+        match address as usize {
+            UNUSABLE_START..=UNUSABLE_END => None,
+            _ => Some(self.fetch(address)),
+        }
+    }
+
+    /// Side-effect free write for debuggers. Fails for ROM, for IO registers,
+    /// which could start transfers or reset timers, and where nothing is
+    /// mapped.
+    pub fn poke_byte(&mut self, address: u16, byte: u8) -> bool {
+        let address = address as usize;
+
+        match address {
+            V_RAM_START..=V_RAM_END => {
+                self.gpu.write_vram(address - V_RAM_START, byte);
+            }
+            E_RAM_START..=E_RAM_END => {
+                self.e_ram[address - E_RAM_START] = byte;
+            }
+            W_RAM_START..=W_RAM_SHAD_END => {
+                self.w_ram[self.w_ram_index(address)] = byte;
+            }
+            OAM_START..=OAM_END => {
+                self.gpu.oam[address - OAM_START] = byte;
+            }
+            Z_RAM_START..=Z_RAM_END => {
+                self.z_ram[address - Z_RAM_START] = byte;
+            }
+            IE_REG => self.ie = byte,
+            _ => return false,
         }
+
+        true
     }
 
-    pub fn read_byte(&self, address: u16) -> u8 {
+    fn fetch(&self, address: u16) -> u8 {
         let address = address as usize;
 
         match address {
-            BOOT_ROM_START..=BOOT_ROM_END => {
-                return self.boot_rom[address];
-
-                // TODO:
-                // if self.in_bios {
-                // if address < 0x0100 {
-                // return self.boot_rom[address];
-                // TODO: Check if PC points to 0x0100 here
-                // This means we're first time behind the boot (0xFF)
-                // How can be PC passed though?
-                // Can there be some simpler approach without need for mut?
-                // CPU should tell membus when to switch
-                // } else if false {
-                // self.in_bios = false;
-                // }
-                // };
-
-                // self.rom_bank_0[address]
-            }
-            ROM_BANK_0_START..=ROM_BANK_0_END => self.rom_bank_0[address],
-            ROM_BANK_N_START..=ROM_BANK_N_END => {
-                panic!("ROM N read at {:x}", address);
+            BOOT_ROM_START..=BOOT_ROM_END if self.in_bios => {
+                self.boot_rom[address]
             }
-            IO_REGS_START..=IO_REGS_END => {
-                if address == 0xFF44 {
-                    panic!("FF41 Bit 4 - Mode 1 V-Blank Interrupt");
-                }
-                // TODO: Read IO
-                0
+            ROM_BANK_0_START..=ROM_BANK_0_END => self
+                .cheats
+                .patch_rom(address as u16, self.rom_bank_0[address]),
+            ROM_BANK_N_START..=ROM_BANK_N_END => self.cheats.patch_rom(
+                address as u16,
+                self.rom_bank_n[address - ROM_BANK_N_START],
+            ),
+            V_RAM_START..=V_RAM_END => {
+                self.gpu.read_vram(address - V_RAM_START)
             }
             E_RAM_START..=E_RAM_END => self.e_ram[address - E_RAM_START],
-            W_RAM_START..=W_RAM_END => self.w_ram[address - W_RAM_START],
-            V_RAM_START..=V_RAM_END => self.gpu.v_ram[address - V_RAM_START],
+            W_RAM_START..=W_RAM_SHAD_END => {
+                self.w_ram[self.w_ram_index(address)]
+            }
+            OAM_START..=OAM_END => self.gpu.oam[address - OAM_START],
+            UNUSABLE_START..=UNUSABLE_END => 0xFF,
+            IO_REGS_START..=IO_REGS_END => self.read_io(address as u16),
             Z_RAM_START..=Z_RAM_END => self.z_ram[address - Z_RAM_START],
-            _ => {
-                panic!("unimplemented mem read at: 0x{:x}", address);
+            IE_REG => self.ie,
+            _ => unreachable!(),
+        }
+    }
+
+    fn read_io(&self, address: u16) -> u8 {
+        match address {
+            P1_REG => match &self.sgb {
+                Some(sgb) => sgb.read(self.joypad.read()),
+                None => self.joypad.read(),
+            },
+            0xFF10..=0xFF3F => self.apu.read_reg(address),
+            0xFF40..=0xFF4B => self.gpu.read_reg(address),
+            0xFF4F | 0xFF68..=0xFF6B => self.gpu.read_reg(address),
+            KEY1_REG if self.cgb => 0x7E | self.key1,
+            HDMA5_REG if self.cgb => self.read_hdma5(),
+            SVBK_REG if self.cgb => 0xF8 | self.svbk,
+            KEY1_REG | HDMA1_REG..=HDMA5_REG | SVBK_REG => 0xFF,
+            // TODO: Timer, interrupts
+            _ => self.io[address as usize - IO_REGS_START],
+        }
+    }
+
+    fn write_io(&mut self, address: u16, byte: u8) {
+        match address {
+            // Transfers complete at once; there's no link partner to wait for.
+            SC_REG if byte & 0x81 == 0x81 => {
+                self.serial.push(self.io[SB_REG as usize - IO_REGS_START]);
+                self.io[SC_REG as usize - IO_REGS_START] = byte & 0x7F;
+
+                return;
+            }
+            P1_REG => {
+                if let Some(sgb) = &mut self.sgb {
+                    sgb.write(byte);
+                }
+
+                self.joypad.write(byte);
             }
+            0xFF10..=0xFF3F => self.apu.write_reg(address, byte),
+            0xFF40..=0xFF4B => self.gpu.write_reg(address, byte),
+            0xFF4F | 0xFF68..=0xFF6B => self.gpu.write_reg(address, byte),
+            KEY1_REG if self.cgb => {
+                self.key1 = (self.key1 & KEY1_DOUBLE_SPEED) | (byte & 1)
+            }
+            HDMA1_REG..=0xFF54 if self.cgb => {
+                self.hdma[(address - HDMA1_REG) as usize] = byte
+            }
+            HDMA5_REG if self.cgb => self.write_hdma5(byte),
+            SVBK_REG if self.cgb => self.svbk = byte & 0b111,
+            BOOT_REG if byte != 0 => self.in_bios = false,
+            _ => {}
+        }
+
+        self.io[address as usize - IO_REGS_START] = byte;
+    }
+}
+
+impl Bus for Mmu {
+    fn read_byte(&self, address: u16) -> u8 {
+        if !self.watchpoints.is_empty() {
+            self.watch(address, false);
         }
+
+        self.fetch(address)
+    }
+
+    fn fetch_byte(&self, address: u16) -> u8 {
+        self.fetch(address)
     }
 
-    pub fn write_byte(&mut self, address: u16, byte: u8) {
+    fn write_byte(&mut self, address: u16, byte: u8) {
+        if !self.watchpoints.is_empty() {
+            self.watch(address, true);
+        }
+
         let address = address as usize;
 
         match address {
-            ROM_BANK_0_START..=ROM_BANK_0_END => {
-                self.rom_bank_0[address] = byte;
+            ROM_BANK_0_START..=ROM_BANK_N_END => {
+                // TODO: MBC registers
             }
-            IO_REGS_START..=IO_REGS_END => {
-                // TODO: Write IO
+            V_RAM_START..=V_RAM_END => {
+                self.gpu.write_vram(address - V_RAM_START, byte);
             }
             E_RAM_START..=E_RAM_END => {
                 self.e_ram[address - E_RAM_START] = byte;
             }
-            W_RAM_START..=W_RAM_END => {
-                self.w_ram[address - W_RAM_START] = byte;
+            W_RAM_START..=W_RAM_SHAD_END => {
+                self.w_ram[self.w_ram_index(address)] = byte;
             }
-            V_RAM_START..=V_RAM_END => {
-                self.gpu.v_ram[address - V_RAM_START] = byte;
+            OAM_START..=OAM_END => {
+                self.gpu.oam[address - OAM_START] = byte;
+            }
+            UNUSABLE_START..=UNUSABLE_END => {}
+            IO_REGS_START..=IO_REGS_END => {
+                self.write_io(address as u16, byte);
             }
             Z_RAM_START..=Z_RAM_END => {
                 self.z_ram[address - Z_RAM_START] = byte;
             }
-            _ => {
-                panic!("unimplemented mem write at: 0x{:x}", address);
+            IE_REG => self.ie = byte,
+            _ => unreachable!(),
+        }
+    }
+
+    /// Bank mapped at `address`, numbered the way RGBDS symbol files do.
+    fn bank_at(&self, address: u16) -> u16 {
+        match address as usize {
+            // TODO: Follow the MBC once banks can be switched
+            ROM_BANK_N_START..=ROM_BANK_N_END => 1,
+            0xD000..=W_RAM_END => self.w_ram_bank() as u16,
+            _ => 0,
+        }
+    }
+
+    /// `ticks` are CPU cycles, so in double speed the rest of the system
+    /// sees half as many.
+    fn tick(&mut self, ticks: u8) {
+        let ticks = if self.double_speed() {
+            ticks / 2
+        } else {
+            ticks
+        };
+
+        let mode = self.gpu.read_reg(0xFF41) & STAT_MODE;
+        let frames = self.gpu.frames();
+
+        self.gpu.step(ticks);
+        self.apu.step(ticks);
+
+        if self.gpu.frames() != frames {
+            if let Some(sgb) = &mut self.sgb {
+                sgb.end_frame(self.gpu.screen());
+            }
+
+            self.apply_cheats();
+        }
+
+        let hblank = self.gpu.read_reg(0xFF41) & STAT_MODE == MODE_HBLANK;
+
+        if let (Some(blocks), true) = (self.hdma_blocks, hblank) {
+            if mode != MODE_HBLANK {
+                self.hdma_block();
+
+                self.hdma_blocks =
+                    if blocks > 1 { Some(blocks - 1) } else { None };
+                self.hdma_stopped = 0xFF;
             }
         }
     }
+
+    fn take_stall(&mut self) -> u32 {
+        std::mem::take(&mut self.stall)
+    }
+
+    /// Switches speed if KEY1 asked for it.
+    fn stop(&mut self) {
+        if self.key1 & KEY1_PREPARE != 0 {
+            self.key1 = (self.key1 ^ KEY1_DOUBLE_SPEED) & KEY1_DOUBLE_SPEED;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cgb() -> Mmu {
+        let mut rom = vec![0; ROM_BANK_0_SIZE * 2];
+        rom[0x143] = 0x80;
+
+        let mut mmu = Mmu::new(Vec::new(), rom).unwrap();
+        mmu.post_boot();
+
+        mmu
+    }
+
+    #[test]
+    fn it_rejects_boot_roms_of_the_wrong_size() {
+        let rom = vec![0; ROM_BANK_0_SIZE * 2];
+
+        assert_eq!(
+            Mmu::new(vec![0; BOOT_ROM_SIZE - 1], rom.clone()).err(),
+            Some("boot ROM must be 256 bytes".to_string())
+        );
+        assert!(Mmu::new(vec![0; BOOT_ROM_SIZE], rom).is_ok());
+    }
+
+    #[test]
+    fn it_switches_wram_banks() {
+        let mut mmu = cgb();
+
+        mmu.write_byte(0xD000, 1);
+        mmu.write_byte(SVBK_REG, 2);
+        assert_eq!(mmu.read_byte(0xD000), 0);
+        assert_eq!(mmu.bank_at(0xD000), 2);
+
+        mmu.write_byte(0xD000, 2);
+        assert_eq!(mmu.read_byte(0xF000), 2);
+
+        // Bank 0 selects bank 1
+        mmu.write_byte(SVBK_REG, 0);
+        assert_eq!(mmu.read_byte(0xD000), 1);
+        assert_eq!(mmu.read_byte(SVBK_REG), 0xF8);
+    }
+
+    #[test]
+    fn it_copies_to_vram_with_hdma() {
+        let mut mmu = cgb();
+
+        for i in 0..0x40 {
+            mmu.write_byte(0xC000 + i, i as u8);
+        }
+
+        for (reg, byte) in &[(0xFF51, 0xC0), (0xFF52, 0), (0xFF53, 0x80)] {
+            mmu.write_byte(*reg, *byte);
+        }
+
+        mmu.write_byte(0xFF54, 0);
+        mmu.write_byte(0xFF4F, 1);
+
+        // General purpose: two blocks at once
+        mmu.write_byte(HDMA5_REG, 0x01);
+        assert_eq!(mmu.read_byte(0x801F), 0x1F);
+        assert_eq!(mmu.read_byte(HDMA5_REG), 0xFF);
+        assert_eq!(mmu.gpu.v_ram[0x1F], 0);
+
+        // HBlank: one block per line
+        mmu.write_byte(HDMA5_REG, 0x80);
+        assert_eq!(mmu.read_byte(HDMA5_REG), 0);
+        assert_eq!(mmu.read_byte(0x8020), 0);
+
+        for _ in 0..456 / 4 {
+            mmu.tick(4);
+        }
+
+        assert_eq!(mmu.read_byte(0x802F), 0x2F);
+        assert_eq!(mmu.read_byte(HDMA5_REG), 0xFF);
+    }
+
+    #[test]
+    fn it_reports_the_blocks_left_after_cancelling_hdma() {
+        let mut mmu = cgb();
+
+        mmu.write_byte(HDMA5_REG, 0x82);
+
+        for _ in 0..456 / 4 {
+            mmu.tick(4);
+        }
+
+        assert_eq!(mmu.read_byte(HDMA5_REG), 0x01);
+
+        mmu.write_byte(HDMA5_REG, 0);
+        assert_eq!(mmu.read_byte(HDMA5_REG), 0x81);
+
+        // Until the next transfer completes
+        mmu.write_byte(HDMA5_REG, 0);
+        assert_eq!(mmu.read_byte(HDMA5_REG), 0xFF);
+    }
+
+    #[test]
+    fn it_stalls_the_cpu_during_general_purpose_dma() {
+        let mut mmu = cgb();
+
+        mmu.write_byte(HDMA5_REG, 0x01);
+        assert_eq!(mmu.take_stall(), 2 * HDMA_BLOCK_TICKS as u32);
+        assert_eq!(mmu.take_stall(), 0);
+
+        mmu.write_byte(KEY1_REG, KEY1_PREPARE);
+        mmu.stop();
+
+        mmu.write_byte(HDMA5_REG, 0x00);
+        assert_eq!(mmu.take_stall(), 2 * HDMA_BLOCK_TICKS as u32);
+    }
+
+    #[test]
+    fn it_switches_speed_on_stop() {
+        let mut mmu = cgb();
+
+        mmu.stop();
+        assert!(!mmu.double_speed());
+
+        mmu.write_byte(KEY1_REG, KEY1_PREPARE);
+        mmu.stop();
+        assert!(mmu.double_speed());
+        assert_eq!(mmu.read_byte(KEY1_REG), 0xFE);
+    }
 }
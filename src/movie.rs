@@ -0,0 +1,209 @@
+//! Input movies: the joypad state for every frame, and where the run starts.
+//! Frames are counted in cycles like `headless::run_frame`, so a movie
+//! replays identically whether or not the LCD is on.
+
+use crate::{
+    checksum::crc32,
+    headless::run_frame,
+    state::{Reader, Writer},
+    Cpu,
+};
+use std::{fs, io, path::Path};
+
+const MAGIC: &[u8; 4] = b"RBMV";
+const VERSION: u8 = 1;
+
+#[derive(Debug, PartialEq)]
+pub enum Start {
+    /// Post-boot state without a boot ROM, see `Cpu::post_boot`.
+    PowerOn,
+    State(Vec<u8>),
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Movie {
+    /// CRC-32 of the whole ROM file.
+    pub rom_checksum: u32,
+    pub start: Start,
+    /// Pressed buttons per frame, see `joypad`.
+    pub inputs: Vec<u8>,
+}
+
+impl Movie {
+    pub fn power_on(rom: &[u8]) -> Self {
+        Self {
+            rom_checksum: crc32(rom),
+            start: Start::PowerOn,
+            inputs: Vec::new(),
+        }
+    }
+
+    /// A movie starting from wherever `cpu` is now.
+    pub fn from_state(rom: &[u8], cpu: &Cpu) -> Self {
+        Self {
+            rom_checksum: crc32(rom),
+            start: Start::State(cpu.save_state()),
+            inputs: Vec::new(),
+        }
+    }
+
+    pub fn record(&mut self, buttons: u8) {
+        self.inputs.push(buttons);
+    }
+
+    /// Sets up the machine the movie starts on.
+    pub fn start(&self, rom: Vec<u8>) -> Result<Cpu, String> {
+        if crc32(&rom) != self.rom_checksum {
+            return Err(format!(
+                "movie was recorded with a different ROM (CRC-32 {:08X})",
+                self.rom_checksum
+            ));
+        }
+
+        let mut cpu = Cpu::post_boot(rom);
+
+        if let Start::State(state) = &self.start {
+            cpu.load_state(state)?;
+        }
+
+        Ok(cpu)
+    }
+
+    /// Runs every recorded frame.
+    pub fn play(&self, cpu: &mut Cpu) -> Result<(), String> {
+        let mut overrun = 0;
+
+        for buttons in &self.inputs {
+            cpu.bus.joypad.set(*buttons);
+
+            run_frame(cpu, &mut overrun)?;
+        }
+
+        Ok(())
+    }
+
+    pub fn encode(&self) -> Vec<u8> {
+        let mut w = Writer::new();
+
+        w.bytes(MAGIC);
+        w.u8(VERSION);
+        w.u32(self.rom_checksum);
+
+        match &self.start {
+            Start::PowerOn => w.u8(0),
+            Start::State(state) => {
+                w.u8(1);
+                w.vec(state);
+            }
+        }
+
+        w.vec(&self.inputs);
+
+        w.finish()
+    }
+
+    pub fn decode(data: &[u8]) -> Result<Self, String> {
+        let mut r = Reader::new(data);
+
+        if r.bytes(4)? != MAGIC || r.u8()? != VERSION {
+            return Err("not a movie of this version".to_string());
+        }
+
+        let rom_checksum = r.u32()?;
+
+        let start = match r.u8()? {
+            0 => Start::PowerOn,
+            1 => Start::State(r.vec()?),
+            kind => return Err(format!("invalid movie start {}", kind)),
+        };
+
+        Ok(Self {
+            rom_checksum,
+            start,
+            inputs: r.vec()?,
+        })
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Self::decode(&fs::read(path)?)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        fs::write(path, self.encode())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{joypad, mmu::ROM_BANK_0_SIZE};
+
+    // Logs the direction buttons to 0xC000 onwards, forever.
+    #[rustfmt::skip]
+    const PROGRAM: [u8; 12] = [
+        0x21, 0x00, 0xC0, // LD HL, 0xC000
+        0x3E, 0x20,       // LD A, 0x20
+        0xE0, 0x00,       // LD (FF00+00), A
+        0xF0, 0x00,       // LD A, (FF00+00)
+        0x22,             // LD (HL+), A
+        0x18, 0xF7,       // JR -9
+    ];
+
+    fn rom() -> Vec<u8> {
+        let mut rom = vec![0; ROM_BANK_0_SIZE * 2];
+        rom[0x100..0x100 + PROGRAM.len()].copy_from_slice(&PROGRAM);
+
+        rom
+    }
+
+    fn movie() -> Movie {
+        let mut movie = Movie::power_on(&rom());
+
+        for frame in 0..4 {
+            movie.record(if frame % 2 == 0 { joypad::UP } else { 0 });
+        }
+
+        movie
+    }
+
+    #[test]
+    fn it_replays_deterministically() {
+        let movie = movie();
+
+        let mut first = movie.start(rom()).unwrap();
+        movie.play(&mut first).unwrap();
+
+        let mut second = movie.start(rom()).unwrap();
+        movie.play(&mut second).unwrap();
+
+        assert_eq!(first.save_state(), second.save_state());
+        assert_eq!(first.bus.peek_byte(0xC000), Some(0xEB));
+    }
+
+    #[test]
+    fn it_starts_from_embedded_states() {
+        let mut cpu = Cpu::post_boot(rom());
+        movie().play(&mut cpu).unwrap();
+
+        let mut movie = Movie::from_state(&rom(), &cpu);
+        movie.record(joypad::DOWN);
+
+        let decoded = Movie::decode(&movie.encode()).unwrap();
+        assert_eq!(decoded, movie);
+
+        let mut replay = decoded.start(rom()).unwrap();
+        assert_eq!(replay.save_state(), cpu.save_state());
+
+        decoded.play(&mut replay).unwrap();
+        assert_eq!(replay.bus.joypad.pressed(), joypad::DOWN);
+    }
+
+    #[test]
+    fn it_rejects_other_roms() {
+        let mut other = rom();
+        other[0x150] = 1;
+
+        assert!(movie().start(other).is_err());
+    }
+}
@@ -0,0 +1,321 @@
+//! The OAM inspector: the 40 sprite entries decoded, and which ones the PPU
+//! selects for a scanline and which it drops past the tenth.
+
+use crate::{
+    gpu::{
+        Gpu, OBJ_BANK, OBJ_BEHIND_BG, OBJ_CGB_PALETTE, OBJ_PALETTE, OBJ_X_FLIP,
+        OBJ_Y_FLIP, SPRITES_PER_LINE,
+    },
+    mmu::OAM_SIZE,
+    png::Image,
+    screenshot,
+};
+
+pub const SPRITES: usize = OAM_SIZE / 4;
+
+// The window's grid of thumbnails, each scaled up and framed
+const COLUMNS: usize = 8;
+const SCALE: usize = 2;
+const BORDER: usize = 2;
+const CELL_WIDTH: usize = 8 * SCALE + BORDER * 2;
+const CELL_HEIGHT: usize = 16 * SCALE + BORDER * 2;
+
+const TRANSPARENT: u32 = 0x808080;
+const SELECTED: u32 = 0x00C000;
+const DROPPED: u32 = 0xFF0000;
+const UNUSED: u32 = 0x404040;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Entry {
+    pub index: usize,
+    /// Screen Y + 16.
+    pub y: u8,
+    /// Screen X + 8.
+    pub x: u8,
+    pub tile: u8,
+    pub flags: u8,
+}
+
+impl Entry {
+    pub fn behind_bg(&self) -> bool {
+        self.flags & OBJ_BEHIND_BG != 0
+    }
+
+    pub fn y_flip(&self) -> bool {
+        self.flags & OBJ_Y_FLIP != 0
+    }
+
+    pub fn x_flip(&self) -> bool {
+        self.flags & OBJ_X_FLIP != 0
+    }
+
+    /// OBP0 or OBP1, on the DMG.
+    pub fn dmg_palette(&self) -> u8 {
+        (self.flags & OBJ_PALETTE != 0) as u8
+    }
+
+    /// VRAM bank, in CGB mode.
+    pub fn bank(&self) -> u8 {
+        (self.flags & OBJ_BANK != 0) as u8
+    }
+
+    /// Colour palette, in CGB mode.
+    pub fn cgb_palette(&self) -> u8 {
+        self.flags & OBJ_CGB_PALETTE
+    }
+}
+
+pub fn entries(gpu: &Gpu) -> Vec<Entry> {
+    gpu.oam
+        .chunks(4)
+        .enumerate()
+        .map(|(index, entry)| Entry {
+            index,
+            y: entry[0],
+            x: entry[1],
+            tile: entry[2],
+            flags: entry[3],
+        })
+        .collect()
+}
+
+/// The sprites overlapping a scanline.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Selection {
+    pub line: u8,
+    /// OAM indices in drawing priority order.
+    pub selected: Vec<usize>,
+    /// OAM indices past the first ten, which aren't drawn.
+    pub dropped: Vec<usize>,
+}
+
+pub fn select(gpu: &Gpu, line: u8) -> Selection {
+    let overlapping = gpu.sprites_overlapping(line);
+
+    Selection {
+        line,
+        selected: gpu.sprites_on_line(line),
+        dropped: overlapping.into_iter().skip(SPRITES_PER_LINE).collect(),
+    }
+}
+
+/// The sprite as drawn, flips applied; colour 0 is `TRANSPARENT`.
+pub fn thumbnail(gpu: &Gpu, entry: &Entry) -> Image {
+    let height = gpu.sprite_height();
+    let bank = if gpu.is_cgb() { entry.bank() } else { 0 } as usize;
+
+    let tile = if height == 16 {
+        entry.tile & 0xFE
+    } else {
+        entry.tile
+    } as usize;
+
+    let mut pixels = Vec::with_capacity(8 * height);
+
+    for y in 0..height {
+        let row = if entry.y_flip() { height - 1 - y } else { y };
+
+        for x in 0..8 {
+            let x = if entry.x_flip() { 7 - x } else { x };
+            let color = gpu.tile_color(bank, tile + row / 8, x, row % 8);
+
+            pixels.push(match color {
+                0 => TRANSPARENT,
+                color => gpu.obj_rgb(color, entry.flags),
+            });
+        }
+    }
+
+    Image {
+        width: 8,
+        height,
+        pixels,
+    }
+}
+
+/// The OAM table, marking the sprites selected and dropped on `line`.
+pub fn dump(gpu: &Gpu, line: Option<u8>) -> String {
+    let selection = line.map(|line| select(gpu, line));
+
+    let mut lines = vec![format!(
+        " #  Y  X  screen    tile attr prio flip palette{}",
+        match line {
+            Some(line) => format!("   line {}", line),
+            None => String::new(),
+        }
+    )];
+
+    for entry in entries(gpu) {
+        let flip = match (entry.x_flip(), entry.y_flip()) {
+            (false, false) => "-",
+            (true, false) => "X",
+            (false, true) => "Y",
+            (true, true) => "XY",
+        };
+
+        let palette = if gpu.is_cgb() {
+            format!("{} bank {}", entry.cgb_palette(), entry.bank())
+        } else {
+            format!("OBP{}", entry.dmg_palette())
+        };
+
+        let status = match &selection {
+            Some(selection) => {
+                match selection.selected.iter().position(|i| *i == entry.index)
+                {
+                    Some(order) => format!("selected {}", order),
+                    None if selection.dropped.contains(&entry.index) => {
+                        "dropped".to_string()
+                    }
+                    None => String::new(),
+                }
+            }
+            None => String::new(),
+        };
+
+        let position = format!(
+            "{:2} {:02X} {:02X} {:>4},{:<4}",
+            entry.index,
+            entry.y,
+            entry.x,
+            entry.x as i16 - 8,
+            entry.y as i16 - 16
+        );
+
+        let line = format!(
+            "{}  {:02X}   {:02X}   {:<4} {:<4} {:<10} {}",
+            position,
+            entry.tile,
+            entry.flags,
+            if entry.behind_bg() { "BG" } else { "OBJ" },
+            flip,
+            palette,
+            status
+        );
+
+        lines.push(line.trim_end().to_string());
+    }
+
+    lines.join("\n")
+}
+
+/// Every sprite's thumbnail in a grid, framed green if selected for `line`,
+/// red if dropped.
+pub fn view(gpu: &Gpu, line: u8) -> Image {
+    let selection = select(gpu, line);
+    let width = COLUMNS * CELL_WIDTH;
+    let height = (SPRITES / COLUMNS) * CELL_HEIGHT;
+    let mut pixels = vec![UNUSED; width * height];
+
+    for entry in entries(gpu) {
+        let frame = if selection.selected.contains(&entry.index) {
+            SELECTED
+        } else if selection.dropped.contains(&entry.index) {
+            DROPPED
+        } else {
+            UNUSED
+        };
+
+        let left = (entry.index % COLUMNS) * CELL_WIDTH;
+        let top = (entry.index / COLUMNS) * CELL_HEIGHT;
+
+        for y in 0..CELL_HEIGHT {
+            for x in 0..CELL_WIDTH {
+                pixels[(top + y) * width + left + x] = frame;
+            }
+        }
+
+        let thumbnail = thumbnail(gpu, &entry);
+        let scaled = screenshot::scale(&thumbnail.pixels, 8, SCALE);
+
+        for (y, row) in scaled.chunks(8 * SCALE).enumerate() {
+            let start = (top + BORDER + y) * width + left + BORDER;
+
+            pixels[start..start + row.len()].copy_from_slice(row);
+        }
+    }
+
+    Image {
+        width,
+        height,
+        pixels,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::palette::Palettes;
+
+    const SHADES: [u32; 4] = [0xFFFFFF, 0xAAAAAA, 0x555555, 0x000000];
+
+    fn gpu() -> Gpu {
+        let mut gpu = Gpu::new();
+        gpu.set_palettes(Palettes::uniform(SHADES));
+        // Identity OBP0, OBP1 inverted
+        gpu.write_reg(0xFF48, 0b1110_0100);
+        gpu.write_reg(0xFF49, 0b0001_1011);
+
+        gpu
+    }
+
+    #[test]
+    fn it_selects_and_drops_sprites() {
+        let mut gpu = gpu();
+
+        // Twelve sprites on line 0, the last two dropped
+        for i in 0..12 {
+            gpu.oam[i * 4..i * 4 + 4].copy_from_slice(&[
+                16,
+                100 - i as u8,
+                1,
+                0,
+            ]);
+        }
+
+        let selection = select(&gpu, 0);
+        assert_eq!(selection.selected.len(), 10);
+        assert_eq!(selection.selected[0], 9);
+        assert_eq!(selection.dropped, vec![10, 11]);
+
+        assert!(select(&gpu, 8).selected.is_empty());
+
+        let dump = dump(&gpu, Some(0));
+        assert!(dump.lines().nth(1).unwrap().ends_with("selected 9"));
+        assert!(dump.lines().nth(11).unwrap().ends_with("dropped"));
+        assert_eq!(dump.lines().count(), 1 + SPRITES);
+
+        let view = view(&gpu, 0);
+        assert_eq!(view.pixels[0], SELECTED);
+        let second_row = CELL_HEIGHT * view.width;
+        assert_eq!(view.pixels[second_row + CELL_WIDTH * 2], DROPPED);
+        assert_eq!(view.pixels[second_row + CELL_WIDTH * 4], UNUSED);
+    }
+
+    #[test]
+    fn it_draws_flipped_thumbnails() {
+        let mut gpu = gpu();
+        // Tile 1's first row: colour 1, then 3 at the right end
+        gpu.v_ram[16] = 0b1000_0001;
+        gpu.v_ram[17] = 0b0000_0001;
+
+        let mut entry = Entry {
+            index: 0,
+            y: 16,
+            x: 8,
+            tile: 1,
+            flags: 0,
+        };
+
+        let image = thumbnail(&gpu, &entry);
+        assert_eq!((image.width, image.height), (8, 8));
+        assert_eq!(image.pixels[0], SHADES[1]);
+        assert_eq!(image.pixels[1], TRANSPARENT);
+        assert_eq!(image.pixels[7], SHADES[3]);
+
+        entry.flags = OBJ_X_FLIP | OBJ_Y_FLIP | OBJ_PALETTE;
+        let image = thumbnail(&gpu, &entry);
+        assert_eq!(image.pixels[7 * 8], SHADES[0]);
+        assert_eq!(image.pixels[7 * 8 + 7], SHADES[2]);
+    }
+}
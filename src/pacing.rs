@@ -0,0 +1,130 @@
+//! Frame pacing for the frontend: one frame of `headless::CYCLES_PER_FRAME`
+//! every 70224 / 4194304 seconds (59.73 Hz), scaled by a speed multiplier.
+
+use crate::headless::CYCLES_PER_FRAME;
+use std::{
+    thread,
+    time::{Duration, Instant},
+};
+
+pub const FRAME_DURATION: Duration =
+    Duration::from_nanos(CYCLES_PER_FRAME * 1_000_000_000 / 4194304);
+
+pub const MIN_SPEED: f64 = 0.125;
+pub const MAX_SPEED: f64 = 8.0;
+
+// Past this much lag (e.g. after a debugger stop) the schedule restarts
+// instead of running frames back to back to catch up.
+const MAX_LAG: Duration = Duration::from_millis(100);
+
+// The OS sleep can overshoot by about this much; the rest is spun.
+const SPIN: Duration = Duration::from_millis(1);
+
+// TODO: Pace off the audio buffer once there is sound output
+pub struct Pacer {
+    speed: f64,
+    deadline: Option<Instant>,
+}
+
+impl Default for Pacer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Pacer {
+    pub fn new() -> Self {
+        Self {
+            speed: 1.0,
+            deadline: None,
+        }
+    }
+
+    pub fn speed(&self) -> f64 {
+        self.speed
+    }
+
+    pub fn set_speed(&mut self, speed: f64) {
+        self.speed = speed.clamp(MIN_SPEED, MAX_SPEED);
+    }
+
+    pub fn frame_duration(&self) -> Duration {
+        FRAME_DURATION.div_f64(self.speed)
+    }
+
+    /// Forgets the schedule, e.g. after pausing or fast-forwarding.
+    pub fn reset(&mut self) {
+        self.deadline = None;
+    }
+
+    /// Schedules the next frame and returns how long to wait for it.
+    pub fn next_frame(&mut self, now: Instant) -> Duration {
+        let deadline = match self.deadline {
+            Some(deadline) if now <= deadline + MAX_LAG => {
+                deadline + self.frame_duration()
+            }
+            _ => now + self.frame_duration(),
+        };
+
+        self.deadline = Some(deadline);
+
+        deadline.saturating_duration_since(now)
+    }
+
+    /// Blocks until the next frame is due.
+    pub fn wait(&mut self) {
+        let now = Instant::now();
+        let delay = self.next_frame(now);
+        let deadline = now + delay;
+
+        if delay > SPIN {
+            thread::sleep(delay - SPIN);
+        }
+
+        while Instant::now() < deadline {
+            thread::yield_now();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_keeps_a_fixed_schedule() {
+        let mut pacer = Pacer::new();
+        let start = Instant::now();
+
+        assert_eq!(pacer.next_frame(start), FRAME_DURATION);
+
+        // Running late is made up for by the next frame
+        let late = start + FRAME_DURATION + Duration::from_millis(5);
+        assert_eq!(
+            pacer.next_frame(late),
+            FRAME_DURATION - Duration::from_millis(5)
+        );
+    }
+
+    #[test]
+    fn it_scales_with_the_speed() {
+        let mut pacer = Pacer::new();
+
+        pacer.set_speed(2.0);
+        assert_eq!(pacer.frame_duration(), FRAME_DURATION / 2);
+
+        pacer.set_speed(100.0);
+        assert_eq!(pacer.speed(), MAX_SPEED);
+    }
+
+    #[test]
+    fn it_restarts_after_long_stalls() {
+        let mut pacer = Pacer::new();
+        let start = Instant::now();
+
+        pacer.next_frame(start);
+
+        let stalled = start + Duration::from_secs(1);
+        assert_eq!(pacer.next_frame(stalled), FRAME_DURATION);
+    }
+}
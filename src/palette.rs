@@ -0,0 +1,194 @@
+//! Colours for the four DMG shades, per layer: background and window, and
+//! sprites through OBP0 or OBP1.
+//!
+//! Palette files list one palette per section, as `RRGGBB` colours from
+//! lightest to darkest. Sprite palettes default to the background's.
+//!
+//! ```text
+//! [ice]
+//! bg = FFFFFF A0D0F0 4060A0 102040
+//! obp0 = FFFFFF F0C0C0 A04040 400000
+//! ```
+
+use crate::ini;
+use std::{fs, io, path::Path};
+
+pub type Palette = [u32; 4];
+
+// Which palette a pixel goes through, see `Palettes::get`.
+pub const LAYER_BG: u8 = 0;
+pub const LAYER_OBP0: u8 = 1;
+pub const LAYER_OBP1: u8 = 2;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Palettes {
+    pub bg: Palette,
+    pub obp0: Palette,
+    pub obp1: Palette,
+}
+
+impl Palettes {
+    /// The same colours for every layer.
+    pub const fn uniform(palette: Palette) -> Self {
+        Self {
+            bg: palette,
+            obp0: palette,
+            obp1: palette,
+        }
+    }
+
+    pub fn get(&self, layer: u8) -> &Palette {
+        match layer {
+            LAYER_OBP0 => &self.obp0,
+            LAYER_OBP1 => &self.obp1,
+            _ => &self.bg,
+        }
+    }
+}
+
+impl Default for Palettes {
+    fn default() -> Self {
+        BUILTIN[0].1
+    }
+}
+
+pub const BUILTIN: [(&str, Palettes); 4] = [
+    (
+        "grayscale",
+        Palettes::uniform([0xFFFFFF, 0xAAAAAA, 0x555555, 0x000000]),
+    ),
+    (
+        "classic",
+        Palettes::uniform([0x9BBC0F, 0x8BAC0F, 0x306230, 0x0F380F]),
+    ),
+    (
+        "pocket",
+        Palettes::uniform([0xC4CFA1, 0x8B956D, 0x4D533C, 0x1F1F1F]),
+    ),
+    // What the GBC boot ROM picks for DMG games it doesn't know
+    (
+        "gbc",
+        Palettes {
+            bg: [0xFFFFFF, 0x7BFF31, 0x0063C5, 0x000000],
+            obp0: [0xFFFFFF, 0xFF8484, 0x943A3A, 0x000000],
+            obp1: [0xFFFFFF, 0xFF8484, 0x943A3A, 0x000000],
+        },
+    ),
+];
+
+pub fn builtin(name: &str) -> Option<Palettes> {
+    BUILTIN
+        .iter()
+        .find(|(builtin, _)| builtin.eq_ignore_ascii_case(name))
+        .map(|(_, palettes)| *palettes)
+}
+
+fn parse_palette(value: &str) -> Option<Palette> {
+    let colors: Vec<u32> = value
+        .split_whitespace()
+        .map(|c| u32::from_str_radix(c.trim_start_matches('#'), 16).ok())
+        .collect::<Option<_>>()?;
+
+    match colors.as_slice() {
+        [a, b, c, d] if colors.iter().all(|c| *c <= 0xFFFFFF) => {
+            Some([*a, *b, *c, *d])
+        }
+        _ => None,
+    }
+}
+
+/// Named palettes from a palette file, in file order.
+pub fn parse(text: &str) -> Result<Vec<(String, Palettes)>, String> {
+    let mut palettes = Vec::new();
+
+    for section in ini::parse(text)? {
+        let mut layers = [None; 3];
+
+        for entry in &section.entries {
+            let layer = match entry.key.as_str() {
+                "bg" => LAYER_BG,
+                "obp0" => LAYER_OBP0,
+                "obp1" => LAYER_OBP1,
+                key => {
+                    return Err(format!(
+                        "line {}: unknown key {:?}",
+                        entry.line, key
+                    ))
+                }
+            };
+
+            if section.name.is_empty() {
+                return Err(format!("line {}: not in a section", entry.line));
+            }
+
+            layers[layer as usize] =
+                Some(parse_palette(&entry.value).ok_or_else(|| {
+                    format!("line {}: expected four RRGGBB colours", entry.line)
+                })?);
+        }
+
+        match layers {
+            [Some(bg), obp0, obp1] => palettes.push((
+                section.name,
+                Palettes {
+                    bg,
+                    obp0: obp0.unwrap_or(bg),
+                    obp1: obp1.unwrap_or(bg),
+                },
+            )),
+            // Nothing before the first palette
+            [None, None, None] if section.name.is_empty() => {}
+            _ => return Err(format!("palette {} has no bg", section.name)),
+        }
+    }
+
+    Ok(palettes)
+}
+
+pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Vec<(String, Palettes)>> {
+    parse(&fs::read_to_string(path)?)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_parses_palette_files() {
+        let palettes = parse(
+            "# Custom palettes\n\
+             [ice]\n\
+             bg = FFFFFF A0D0F0 4060A0 102040\n\
+             obp1 = #FFFFFF #F0C0C0 #A04040 #400000\n\
+             \n\
+             [mono]\n\
+             bg = FFFFFF FFFFFF 000000 000000\n",
+        )
+        .unwrap();
+
+        assert_eq!(palettes.len(), 2);
+        assert_eq!(palettes[0].0, "ice");
+        assert_eq!(palettes[0].1.obp0, palettes[0].1.bg);
+        assert_eq!(palettes[0].1.obp1[2], 0xA04040);
+        assert_eq!(
+            palettes[1].1,
+            Palettes::uniform([0xFFFFFF, 0xFFFFFF, 0, 0])
+        );
+    }
+
+    #[test]
+    fn it_rejects_invalid_palettes() {
+        assert!(parse("bg = FFFFFF AAAAAA 555555 000000").is_err());
+        assert!(parse("[x]\nbg = FFFFFF AAAAAA 555555").is_err());
+        assert!(parse("[x]\nbg = 1000000 AAAAAA 555555 000000").is_err());
+        assert!(parse("[x]\nobp0 = FFFFFF AAAAAA 555555 000000").is_err());
+        assert!(parse("[x]\nwindow = FFFFFF AAAAAA 555555 000000").is_err());
+    }
+
+    #[test]
+    fn it_finds_builtins_by_name() {
+        assert_eq!(builtin("Classic").unwrap().bg[0], 0x9BBC0F);
+        assert!(builtin("sepia").is_none());
+    }
+}
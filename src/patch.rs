@@ -0,0 +1,413 @@
+//! IPS, BPS and UPS patches, applied to a copy of the ROM at load time.
+//! BPS and UPS carry CRC-32s of the source, target and patch, which are all
+//! checked.
+
+use crate::checksum::crc32;
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+const IPS_MAGIC: &[u8] = b"PATCH";
+const IPS_EOF: &[u8] = b"EOF";
+const BPS_MAGIC: &[u8] = b"BPS1";
+const UPS_MAGIC: &[u8] = b"UPS1";
+
+// Source, target and patch CRC-32s
+const FOOTER_SIZE: usize = 12;
+
+pub const EXTENSIONS: [&str; 3] = ["ips", "bps", "ups"];
+
+/// A patch named after the ROM, e.g. `game.ips` for `game.gb`.
+pub fn for_rom<P: AsRef<Path>>(rom_path: P) -> Option<PathBuf> {
+    EXTENSIONS
+        .iter()
+        .map(|extension| rom_path.as_ref().with_extension(extension))
+        .find(|path| path.exists())
+}
+
+pub fn apply_file<P: AsRef<Path>>(
+    rom: &[u8],
+    path: P,
+) -> Result<Vec<u8>, String> {
+    let path = path.as_ref();
+
+    fs::read(path)
+        .map_err(|e| e.to_string())
+        .and_then(|patch| apply(rom, &patch))
+        .map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+/// Patches a copy of `rom`, telling the format by its magic.
+pub fn apply(rom: &[u8], patch: &[u8]) -> Result<Vec<u8>, String> {
+    if patch.starts_with(IPS_MAGIC) {
+        ips(rom, patch)
+    } else if patch.starts_with(BPS_MAGIC) {
+        bps(rom, patch)
+    } else if patch.starts_with(UPS_MAGIC) {
+        ups(rom, patch)
+    } else {
+        Err("not an IPS, BPS or UPS patch".to_string())
+    }
+}
+
+/// Reads patch bytes, failing past the end.
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn bytes(&mut self, n: usize) -> Result<&'a [u8], String> {
+        let bytes = self
+            .data
+            .get(self.pos..self.pos + n)
+            .ok_or_else(|| "patch is truncated".to_string())?;
+
+        self.pos += n;
+
+        Ok(bytes)
+    }
+
+    fn u8(&mut self) -> Result<u8, String> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    fn be(&mut self, n: usize) -> Result<usize, String> {
+        Ok(self
+            .bytes(n)?
+            .iter()
+            .fold(0, |value, byte| value << 8 | *byte as usize))
+    }
+
+    /// The variable-length numbers of BPS and UPS.
+    fn number(&mut self) -> Result<usize, String> {
+        let mut value = 0;
+        let mut shift = 1;
+
+        loop {
+            let byte = self.u8()? as usize;
+
+            value += (byte & 0x7F) * shift;
+
+            if byte & 0x80 != 0 {
+                return Ok(value);
+            }
+
+            shift <<= 7;
+            value += shift;
+        }
+    }
+}
+
+fn ips(rom: &[u8], patch: &[u8]) -> Result<Vec<u8>, String> {
+    let mut out = rom.to_vec();
+    let mut r = Reader {
+        data: patch,
+        pos: IPS_MAGIC.len(),
+    };
+
+    loop {
+        let offset = r.bytes(3)?;
+
+        if offset == IPS_EOF {
+            break;
+        }
+
+        let offset = offset.iter().fold(0, |v, b| v << 8 | *b as usize);
+        let size = r.be(2)?;
+
+        // Size 0 marks a run of one byte
+        let (size, run) = if size == 0 {
+            (r.be(2)?, Some(r.u8()?))
+        } else {
+            (size, None)
+        };
+
+        if out.len() < offset + size {
+            out.resize(offset + size, 0);
+        }
+
+        match run {
+            Some(byte) => {
+                for b in &mut out[offset..offset + size] {
+                    *b = byte;
+                }
+            }
+            None => out[offset..offset + size].copy_from_slice(r.bytes(size)?),
+        }
+    }
+
+    // An extension: the size to truncate to
+    if let Ok(size) = r.be(3) {
+        out.truncate(size);
+    }
+
+    Ok(out)
+}
+
+/// Checks the footer CRC-32s other than the target's, which is returned.
+fn check_footer(rom: &[u8], patch: &[u8]) -> Result<u32, String> {
+    if patch.len() < FOOTER_SIZE + 4 {
+        return Err("patch is truncated".to_string());
+    }
+
+    let footer = &patch[patch.len() - FOOTER_SIZE..];
+    let crc = |i: usize| {
+        u32::from_le_bytes([
+            footer[i],
+            footer[i + 1],
+            footer[i + 2],
+            footer[i + 3],
+        ])
+    };
+
+    if crc32(&patch[..patch.len() - 4]) != crc(8) {
+        return Err("patch is corrupt (CRC-32 mismatch)".to_string());
+    }
+
+    if crc32(rom) != crc(0) {
+        return Err(format!(
+            "patch is for a different ROM (CRC-32 {:08X}, not {:08X})",
+            crc(0),
+            crc32(rom)
+        ));
+    }
+
+    Ok(crc(4))
+}
+
+fn check_target(out: &[u8], crc: u32) -> Result<(), String> {
+    if crc32(out) != crc {
+        return Err("patched ROM doesn't match (CRC-32 mismatch)".to_string());
+    }
+
+    Ok(())
+}
+
+fn ups(rom: &[u8], patch: &[u8]) -> Result<Vec<u8>, String> {
+    let target_crc = check_footer(rom, patch)?;
+
+    let mut r = Reader {
+        data: &patch[..patch.len() - FOOTER_SIZE],
+        pos: UPS_MAGIC.len(),
+    };
+
+    let _source_size = r.number()?;
+    let target_size = r.number()?;
+
+    let mut out = rom.to_vec();
+    out.resize(target_size, 0);
+
+    let mut pos = 0;
+
+    // Runs of bytes XORed into the ROM, each ended by a 0
+    while r.pos < r.data.len() {
+        pos += r.number()?;
+
+        loop {
+            let byte = r.u8()?;
+
+            if byte == 0 {
+                pos += 1;
+                break;
+            }
+
+            if pos < out.len() {
+                out[pos] ^= byte;
+            }
+
+            pos += 1;
+        }
+    }
+
+    check_target(&out, target_crc)?;
+
+    Ok(out)
+}
+
+fn bps(rom: &[u8], patch: &[u8]) -> Result<Vec<u8>, String> {
+    let target_crc = check_footer(rom, patch)?;
+
+    let mut r = Reader {
+        data: &patch[..patch.len() - FOOTER_SIZE],
+        pos: BPS_MAGIC.len(),
+    };
+
+    let _source_size = r.number()?;
+    let target_size = r.number()?;
+    let metadata_size = r.number()?;
+
+    r.bytes(metadata_size)?;
+
+    let mut out = Vec::with_capacity(target_size);
+    let (mut source_offset, mut target_offset) = (0usize, 0usize);
+
+    let invalid = || "patch copies out of range".to_string();
+
+    while r.pos < r.data.len() {
+        let data = r.number()?;
+        let length = (data >> 2) + 1;
+
+        match data & 0b11 {
+            // SourceRead
+            0 => {
+                let start = out.len();
+
+                out.extend_from_slice(
+                    rom.get(start..start + length).ok_or_else(invalid)?,
+                );
+            }
+            // TargetRead
+            1 => out.extend_from_slice(r.bytes(length)?),
+            // SourceCopy and TargetCopy, from a signed relative offset
+            command => {
+                let offset = r.number()?;
+                let negative = offset & 1 != 0;
+                let offset = offset >> 1;
+
+                let base = if command == 2 {
+                    &mut source_offset
+                } else {
+                    &mut target_offset
+                };
+
+                *base = if negative {
+                    base.checked_sub(offset)
+                } else {
+                    base.checked_add(offset)
+                }
+                .ok_or_else(invalid)?;
+
+                let start = *base;
+
+                for i in start..start + length {
+                    let byte = if command == 2 {
+                        rom.get(i)
+                    } else {
+                        // May overlap what's being written
+                        out.get(i)
+                    };
+
+                    let byte = *byte.ok_or_else(invalid)?;
+
+                    out.push(byte);
+                }
+
+                *base += length;
+            }
+        }
+    }
+
+    if out.len() != target_size {
+        return Err("patched ROM has the wrong size".to_string());
+    }
+
+    check_target(&out, target_crc)?;
+
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn number(mut value: usize) -> Vec<u8> {
+        let mut out = Vec::new();
+
+        loop {
+            let byte = (value & 0x7F) as u8;
+            value >>= 7;
+
+            if value == 0 {
+                out.push(byte | 0x80);
+
+                return out;
+            }
+
+            out.push(byte);
+            value -= 1;
+        }
+    }
+
+    fn with_footer(
+        mut patch: Vec<u8>,
+        source: &[u8],
+        target: &[u8],
+    ) -> Vec<u8> {
+        patch.extend_from_slice(&crc32(source).to_le_bytes());
+        patch.extend_from_slice(&crc32(target).to_le_bytes());
+        patch.extend_from_slice(&crc32(&patch).to_le_bytes());
+
+        patch
+    }
+
+    #[test]
+    fn it_applies_ips_patches() {
+        let rom = [0u8; 8];
+
+        let mut patch = b"PATCH".to_vec();
+        // Two bytes at 2, then a run of three 0xAA at 7
+        patch.extend_from_slice(&[0, 0, 2, 0, 2, 0x12, 0x34]);
+        patch.extend_from_slice(&[0, 0, 7, 0, 0, 0, 3, 0xAA]);
+        patch.extend_from_slice(b"EOF");
+
+        assert_eq!(
+            apply(&rom, &patch),
+            Ok(vec![0, 0, 0x12, 0x34, 0, 0, 0, 0xAA, 0xAA, 0xAA])
+        );
+
+        patch.pop();
+        assert!(apply(&rom, &patch).is_err());
+    }
+
+    #[test]
+    fn it_applies_ups_patches() {
+        let source = [1u8, 2, 3, 4];
+        let target = [1u8, 2, 7, 4, 9];
+
+        let mut patch = b"UPS1".to_vec();
+        patch.extend(number(4));
+        patch.extend(number(5));
+        // Skip 2, XOR 3 ^ 7, end (which covers 4 too); XOR 0 ^ 9, end
+        patch.extend(number(2));
+        patch.extend_from_slice(&[3 ^ 7, 0]);
+        patch.extend(number(0));
+        patch.extend_from_slice(&[9, 0]);
+
+        let patch = with_footer(patch, &source, &target);
+
+        assert_eq!(apply(&source, &patch), Ok(target.to_vec()));
+        assert!(apply(&target, &patch)
+            .unwrap_err()
+            .contains("different ROM"));
+    }
+
+    #[test]
+    fn it_applies_bps_patches() {
+        let source = b"ABCDEF";
+        let target = b"ABxyxyxyEF";
+
+        let mut patch = b"BPS1".to_vec();
+        patch.extend(number(6));
+        patch.extend(number(10));
+        patch.extend(number(0));
+        // SourceRead 2, TargetRead "xy", TargetCopy 4 from 2,
+        // SourceCopy 2 from 4
+        patch.extend(number(1 << 2));
+        patch.extend(number((1 << 2) | 1));
+        patch.extend_from_slice(b"xy");
+        patch.extend(number((3 << 2) | 3));
+        patch.extend(number(2 << 1));
+        patch.extend(number((1 << 2) | 2));
+        patch.extend(number(4 << 1));
+
+        let mut patch = with_footer(patch, source, target);
+
+        assert_eq!(apply(source, &patch), Ok(target.to_vec()));
+
+        // A flipped bit fails the patch's own CRC-32
+        patch[6] ^= 1;
+        assert!(apply(source, &patch).unwrap_err().contains("corrupt"));
+    }
+}
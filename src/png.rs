@@ -0,0 +1,378 @@
+use crate::{
+    checksum::{adler32, crc32},
+    inflate,
+};
+
+const SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A];
+
+// Largest payload of a stored deflate block.
+const STORED_BLOCK: usize = 0xFFFF;
+
+pub struct Image {
+    pub width: usize,
+    pub height: usize,
+    /// `0xRRGGBB`, row by row; alpha is dropped.
+    pub pixels: Vec<u32>,
+}
+
+/// Encodes `0xRRGGBB` pixels as an 8-bit RGB PNG. The image data is stored
+/// uncompressed, which keeps the encoder tiny; frames are small anyway.
+pub fn encode(width: usize, height: usize, pixels: &[u32]) -> Vec<u8> {
+    assert_eq!(pixels.len(), width * height);
+
+    let mut raw = Vec::with_capacity(height * (width * 3 + 1));
+
+    for row in pixels.chunks(width) {
+        // Filter type: none
+        raw.push(0);
+
+        for pixel in row {
+            raw.extend_from_slice(&pixel.to_be_bytes()[1..]);
+        }
+    }
+
+    let mut header = Vec::with_capacity(13);
+    header.extend_from_slice(&(width as u32).to_be_bytes());
+    header.extend_from_slice(&(height as u32).to_be_bytes());
+    // 8 bits per channel, RGB, deflate, adaptive filtering, no interlace
+    header.extend_from_slice(&[8, 2, 0, 0, 0]);
+
+    let mut png = SIGNATURE.to_vec();
+
+    chunk(&mut png, b"IHDR", &header);
+    chunk(&mut png, b"IDAT", &zlib_stored(&raw));
+    chunk(&mut png, b"IEND", &[]);
+
+    png
+}
+
+/// Decodes a non-interlaced PNG of any colour type. 16-bit samples are
+/// truncated to 8 bits.
+pub fn decode(data: &[u8]) -> Result<Image, String> {
+    if !data.starts_with(&SIGNATURE) {
+        return Err("not a PNG file".to_string());
+    }
+
+    let mut pos = SIGNATURE.len();
+    let mut header = None;
+    let mut palette = Vec::new();
+    let mut idat = Vec::new();
+
+    loop {
+        let len = data
+            .get(pos..pos + 4)
+            .map(|b| u32::from_be_bytes([b[0], b[1], b[2], b[3]]) as usize)
+            .ok_or("truncated PNG")?;
+
+        let body = data.get(pos + 4..pos + 8 + len).ok_or("truncated PNG")?;
+        let crc = data
+            .get(pos + 8 + len..pos + 12 + len)
+            .ok_or("truncated PNG")?;
+
+        if crc32(body).to_be_bytes() != crc {
+            return Err("PNG chunk checksum mismatch".to_string());
+        }
+
+        pos += 12 + len;
+
+        let (kind, body) = body.split_at(4);
+
+        match kind {
+            b"IHDR" if body.len() == 13 => header = Some(body.to_vec()),
+            b"PLTE" => palette = body.to_vec(),
+            b"IDAT" => idat.extend_from_slice(body),
+            b"IEND" => break,
+            _ => {}
+        }
+    }
+
+    let header = header.ok_or("missing IHDR")?;
+
+    let width = u32::from_be_bytes([header[0], header[1], header[2], header[3]])
+        as usize;
+    let height =
+        u32::from_be_bytes([header[4], header[5], header[6], header[7]])
+            as usize;
+    let depth = header[8] as usize;
+    let color = header[9];
+
+    if width == 0 || height == 0 {
+        return Err(format!("invalid PNG size {}x{}", width, height));
+    }
+
+    if header[12] != 0 {
+        return Err("interlaced PNGs are unsupported".to_string());
+    }
+
+    let (channels, depths): (usize, &[usize]) = match color {
+        0 => (1, &[1, 2, 4, 8, 16]),
+        3 => (1, &[1, 2, 4, 8]),
+        2 => (3, &[8, 16]),
+        4 => (2, &[8, 16]),
+        6 => (4, &[8, 16]),
+        _ => return Err(format!("invalid PNG colour type {}", color)),
+    };
+
+    if !depths.contains(&depth) {
+        return Err(format!(
+            "invalid PNG bit depth {} for colour type {}",
+            depth, color
+        ));
+    }
+
+    let bits = channels * depth;
+    let stride = width
+        .checked_mul(bits)
+        .ok_or("PNG image too large")?
+        .div_ceil(8);
+    // Distance to the corresponding byte of the previous pixel
+    let bpp = (bits / 8).max(1);
+    // Every row starts with its filter type
+    let size = height
+        .checked_mul(stride + 1)
+        .ok_or("PNG image too large")?;
+
+    let raw = inflate::zlib(&idat)?;
+
+    if raw.len() < size {
+        return Err("PNG image data too short".to_string());
+    }
+
+    let mut rows = vec![0u8; height * stride];
+
+    for y in 0..height {
+        let filter = raw[y * (stride + 1)];
+        let line = &raw[y * (stride + 1) + 1..(y + 1) * (stride + 1)];
+
+        let (done, rest) = rows.split_at_mut(y * stride);
+        let prev = if y > 0 {
+            &done[(y - 1) * stride..]
+        } else {
+            &[][..]
+        };
+        let row = &mut rest[..stride];
+
+        for x in 0..stride {
+            let a = if x >= bpp { row[x - bpp] } else { 0 };
+            let b = prev.get(x).copied().unwrap_or(0);
+            let c = if x >= bpp {
+                prev.get(x - bpp).copied().unwrap_or(0)
+            } else {
+                0
+            };
+
+            let predictor = match filter {
+                0 => 0,
+                1 => a,
+                2 => b,
+                3 => ((a as u16 + b as u16) / 2) as u8,
+                4 => paeth(a, b, c),
+                _ => return Err(format!("invalid PNG filter {}", filter)),
+            };
+
+            row[x] = line[x].wrapping_add(predictor);
+        }
+    }
+
+    let sample = |row: &[u8], index: usize| -> u8 {
+        match depth {
+            16 => row[index * 2],
+            8 => row[index],
+            _ => {
+                let per_byte = 8 / depth;
+                let shift = 8 - depth * (index % per_byte + 1);
+                let val = (row[index / per_byte] >> shift) & ((1 << depth) - 1);
+
+                // Palette indices stay as they are; grey levels are scaled up.
+                if color == 3 {
+                    val
+                } else {
+                    (val as usize * 255 / ((1 << depth) - 1)) as u8
+                }
+            }
+        }
+    };
+
+    let mut pixels = Vec::with_capacity(width * height);
+
+    for row in rows.chunks(stride) {
+        for x in 0..width {
+            let (r, g, b) = match color {
+                0 | 4 => {
+                    let v = sample(row, x * channels);
+
+                    (v, v, v)
+                }
+                3 => {
+                    let i = sample(row, x) as usize * 3;
+                    let entry =
+                        palette.get(i..i + 3).ok_or("bad palette index")?;
+
+                    (entry[0], entry[1], entry[2])
+                }
+                _ => (
+                    sample(row, x * channels),
+                    sample(row, x * channels + 1),
+                    sample(row, x * channels + 2),
+                ),
+            };
+
+            pixels.push((r as u32) << 16 | (g as u32) << 8 | b as u32);
+        }
+    }
+
+    Ok(Image {
+        width,
+        height,
+        pixels,
+    })
+}
+
+fn paeth(a: u8, b: u8, c: u8) -> u8 {
+    let p = a as i16 + b as i16 - c as i16;
+    let pa = (p - a as i16).abs();
+    let pb = (p - b as i16).abs();
+    let pc = (p - c as i16).abs();
+
+    if pa <= pb && pa <= pc {
+        a
+    } else if pb <= pc {
+        b
+    } else {
+        c
+    }
+}
+
+fn chunk(png: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    let mut body = kind.to_vec();
+    body.extend_from_slice(data);
+
+    png.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    png.extend_from_slice(&body);
+    png.extend_from_slice(&crc32(&body).to_be_bytes());
+}
+
+fn zlib_stored(data: &[u8]) -> Vec<u8> {
+    let mut out = vec![0x78, 0x01];
+    let mut blocks = data.chunks(STORED_BLOCK).peekable();
+
+    if blocks.peek().is_none() {
+        out.extend_from_slice(&[1, 0, 0, 0xFF, 0xFF]);
+    }
+
+    while let Some(block) = blocks.next() {
+        let last = blocks.peek().is_none();
+        let len = block.len() as u16;
+
+        out.push(last as u8);
+        out.extend_from_slice(&len.to_le_bytes());
+        out.extend_from_slice(&(!len).to_le_bytes());
+        out.extend_from_slice(block);
+    }
+
+    out.extend_from_slice(&adler32(data).to_be_bytes());
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_writes_a_well_formed_png() {
+        let png = encode(2, 1, &[0xFF0000, 0x00FF00]);
+
+        assert_eq!(&png[..8], &SIGNATURE);
+        assert_eq!(&png[12..16], b"IHDR");
+        assert_eq!(&png[16..24], &[0, 0, 0, 2, 0, 0, 0, 1]);
+
+        let idat = &png[33..];
+        assert_eq!(&idat[4..8], b"IDAT");
+
+        // zlib header, one final stored block of 7 bytes
+        assert_eq!(&idat[8..15], &[0x78, 0x01, 1, 7, 0, 0xF8, 0xFF]);
+        assert_eq!(&idat[15..22], &[0, 0xFF, 0, 0, 0, 0xFF, 0]);
+
+        assert_eq!(&png[png.len() - 8..png.len() - 4], b"IEND");
+    }
+
+    #[test]
+    fn it_decodes_what_it_encodes() {
+        let pixels = [0x123456, 0xFFFFFF, 0x000000, 0xABCDEF, 0x010203, 0x0];
+        let image = decode(&encode(3, 2, &pixels)).unwrap();
+
+        assert_eq!((image.width, image.height), (3, 2));
+        assert_eq!(image.pixels, pixels);
+    }
+
+    #[test]
+    fn it_decodes_filtered_rgba() {
+        // 3x2 RGBA, rows filtered with Sub and Paeth, compressed by zlib.
+        const PNG: [u8; 88] = [
+            0x89, 0x50, 0x4E, 0x47, 0x0D, 0x0A, 0x1A, 0x0A, 0x00, 0x00, 0x00,
+            0x0D, 0x49, 0x48, 0x44, 0x52, 0x00, 0x00, 0x00, 0x03, 0x00, 0x00,
+            0x00, 0x02, 0x08, 0x06, 0x00, 0x00, 0x00, 0x9D, 0x74, 0x66, 0x1A,
+            0x00, 0x00, 0x00, 0x1F, 0x49, 0x44, 0x41, 0x54, 0x78, 0xDA, 0x63,
+            0xE4, 0x12, 0x91, 0xFB, 0x2F, 0x27, 0x27, 0xD7, 0x08, 0xC4, 0x0D,
+            0x2C, 0xAC, 0xAC, 0xAC, 0x0C, 0x40, 0x5C, 0xDF, 0x24, 0x72, 0xA3,
+            0x1E, 0x00, 0x40, 0x94, 0x05, 0x80, 0x1A, 0x74, 0x93, 0x34, 0x00,
+            0x00, 0x00, 0x00, 0x49, 0x45, 0x4E, 0x44, 0xAE, 0x42, 0x60, 0x82,
+        ];
+
+        let image = decode(&PNG).unwrap();
+
+        assert_eq!(
+            image.pixels,
+            [0x0A141E, 0x28323C, 0x46505A, 0x0F1923, 0x2D3741, 0xC86432]
+        );
+    }
+
+    #[test]
+    fn it_rejects_unsupported_bit_depths() {
+        let mut png = encode(1, 1, &[0]);
+
+        for (depth, color) in [(0, 2), (4, 2), (16, 3), (3, 0)] {
+            png[24] = depth;
+            png[25] = color;
+
+            let crc = crc32(&png[12..29]).to_be_bytes();
+            png[29..33].copy_from_slice(&crc);
+
+            assert_eq!(
+                decode(&png).err(),
+                Some(format!(
+                    "invalid PNG bit depth {} for colour type {}",
+                    depth, color
+                ))
+            );
+        }
+    }
+
+    #[test]
+    fn it_rejects_empty_and_oversized_images() {
+        let mut png = encode(1, 1, &[0]);
+
+        for (width, height, err) in [
+            (0, 1, "invalid PNG size 0x1"),
+            (1, 0, "invalid PNG size 1x0"),
+            (u32::MAX, u32::MAX, "PNG image too large"),
+        ] {
+            png[16..20].copy_from_slice(&width.to_be_bytes());
+            png[20..24].copy_from_slice(&height.to_be_bytes());
+
+            let crc = crc32(&png[12..29]).to_be_bytes();
+            png[29..33].copy_from_slice(&crc);
+
+            assert_eq!(decode(&png).err(), Some(err.to_string()));
+        }
+    }
+
+    #[test]
+    fn it_splits_large_images_into_stored_blocks() {
+        let stored = zlib_stored(&vec![0; STORED_BLOCK + 1]);
+
+        assert_eq!(stored[2], 0);
+        assert_eq!(stored[2 + 5 + STORED_BLOCK], 1);
+    }
+}
@@ -0,0 +1,122 @@
+use crate::{
+    gpu::{Gpu, SCREEN_HEIGHT, SCREEN_WIDTH},
+    png,
+};
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// Repeats every pixel `factor` times in both directions.
+pub fn scale(pixels: &[u32], width: usize, factor: usize) -> Vec<u32> {
+    let mut scaled = Vec::with_capacity(pixels.len() * factor * factor);
+
+    for row in pixels.chunks(width) {
+        let line: Vec<u32> = row
+            .iter()
+            .flat_map(|p| std::iter::repeat_n(*p, factor))
+            .collect();
+
+        for _ in 0..factor {
+            scaled.extend_from_slice(&line);
+        }
+    }
+
+    scaled
+}
+
+/// The current frame as PNG, with the palette applied and scaled up
+/// `factor` times.
+pub fn encode(gpu: &Gpu, factor: usize) -> Vec<u8> {
+    let factor = factor.max(1);
+    let pixels = scale(&gpu.frame_rgb(), SCREEN_WIDTH, factor);
+
+    png::encode(SCREEN_WIDTH * factor, SCREEN_HEIGHT * factor, &pixels)
+}
+
+/// `dir/rboy-YYYYMMDD-HHMMSS-mmm.<extension>`, from the current UTC time.
+pub fn timestamped_path<P: AsRef<Path>>(dir: P, extension: &str) -> PathBuf {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+
+    dir.as_ref().join(format!(
+        "rboy-{}-{:03}.{}",
+        timestamp(now.as_secs()),
+        now.subsec_millis(),
+        extension
+    ))
+}
+
+/// Writes the current frame to a timestamped PNG in `dir` and returns its
+/// path.
+pub fn save<P: AsRef<Path>>(
+    gpu: &Gpu,
+    dir: P,
+    factor: usize,
+) -> io::Result<PathBuf> {
+    let path = timestamped_path(dir, "png");
+
+    fs::write(&path, encode(gpu, factor))?;
+
+    Ok(path)
+}
+
+/// `YYYYMMDD-HHMMSS` for seconds since the Unix epoch.
+fn timestamp(secs: u64) -> String {
+    let days = (secs / 86400) as i64;
+    let time = secs % 86400;
+
+    // Civil date from days since 1970-01-01 (Howard Hinnant's algorithm).
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    format!(
+        "{:04}{:02}{:02}-{:02}{:02}{:02}",
+        year,
+        month,
+        day,
+        time / 3600,
+        time / 60 % 60,
+        time % 60
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_formats_timestamps() {
+        assert_eq!(timestamp(0), "19700101-000000");
+        assert_eq!(timestamp(951782400), "20000229-000000");
+        assert_eq!(timestamp(1792332245), "20261018-140405");
+    }
+
+    #[test]
+    fn it_scales_by_whole_factors() {
+        let scaled = scale(&[1, 2, 3, 4], 2, 2);
+
+        assert_eq!(
+            scaled,
+            vec![1, 1, 2, 2, 1, 1, 2, 2, 3, 3, 4, 4, 3, 3, 4, 4]
+        );
+    }
+
+    #[test]
+    fn it_encodes_at_the_selected_scale() {
+        let png = encode(&Gpu::new(), 3);
+
+        // IHDR width and height
+        assert_eq!(&png[16..20], &(SCREEN_WIDTH as u32 * 3).to_be_bytes());
+        assert_eq!(&png[20..24], &(SCREEN_HEIGHT as u32 * 3).to_be_bytes());
+    }
+}
@@ -0,0 +1,200 @@
+//! RAM search for finding where a game keeps a value: snapshot external RAM,
+//! WRAM and HRAM, then narrow the candidates down by comparing each address
+//! with its value at the previous step.
+
+use crate::mmu::Mmu;
+use std::ops::RangeInclusive;
+
+const REGIONS: [RangeInclusive<u16>; 3] =
+    [0xA000..=0xBFFF, 0xC000..=0xDFFF, 0xFF80..=0xFFFE];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Width {
+    Byte,
+    /// Little-endian, starting at the candidate address.
+    Word,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Comparison {
+    /// Unchanged since the previous step.
+    Equal,
+    /// Changed since the previous step.
+    NotEqual,
+    Greater,
+    Less,
+    /// Equal to a constant.
+    Value(u16),
+}
+
+impl Comparison {
+    /// `eq`/`unchanged`, `ne`/`changed`, `gt`, `lt`, or `eq N`.
+    pub fn parse(op: &str, value: Option<&str>) -> Option<Self> {
+        match (op, value) {
+            ("eq", Some(value)) | ("=", Some(value)) => {
+                parse_value(value).map(Comparison::Value)
+            }
+            ("eq", None) | ("=", None) | ("unchanged", None) => {
+                Some(Comparison::Equal)
+            }
+            ("ne", None) | ("!=", None) | ("changed", None) => {
+                Some(Comparison::NotEqual)
+            }
+            ("gt", None) | (">", None) => Some(Comparison::Greater),
+            ("lt", None) | ("<", None) => Some(Comparison::Less),
+            _ => None,
+        }
+    }
+
+    fn matches(self, previous: u16, value: u16) -> bool {
+        match self {
+            Comparison::Equal => value == previous,
+            Comparison::NotEqual => value != previous,
+            Comparison::Greater => value > previous,
+            Comparison::Less => value < previous,
+            Comparison::Value(constant) => value == constant,
+        }
+    }
+}
+
+/// Decimal, or hex with `0x` or `$`.
+fn parse_value(value: &str) -> Option<u16> {
+    if let Some(hex) =
+        value.strip_prefix("0x").or_else(|| value.strip_prefix('$'))
+    {
+        u16::from_str_radix(hex, 16).ok()
+    } else {
+        value.parse().ok()
+    }
+}
+
+pub struct Search {
+    width: Width,
+    // Candidate addresses and their values at the last step
+    candidates: Vec<(u16, u16)>,
+}
+
+impl Search {
+    /// Starts with every address in RAM as a candidate.
+    pub fn new(mmu: &Mmu, width: Width) -> Self {
+        let mut search = Self {
+            width,
+            candidates: Vec::new(),
+        };
+
+        for region in REGIONS.iter() {
+            let end = match width {
+                Width::Byte => *region.end(),
+                // Words don't straddle regions
+                Width::Word => region.end() - 1,
+            };
+
+            for address in *region.start()..=end {
+                let value = read(mmu, width, address);
+
+                search.candidates.push((address, value));
+            }
+        }
+
+        search
+    }
+
+    pub fn width(&self) -> Width {
+        self.width
+    }
+
+    pub fn len(&self) -> usize {
+        self.candidates.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.candidates.is_empty()
+    }
+
+    /// Addresses left, with their values at the last step.
+    pub fn candidates(&self) -> &[(u16, u16)] {
+        &self.candidates
+    }
+
+    /// Keeps the candidates whose value now compares as asked, and makes
+    /// the current values the ones to compare against next.
+    pub fn filter(&mut self, mmu: &Mmu, comparison: Comparison) -> usize {
+        let width = self.width;
+
+        self.candidates = self
+            .candidates
+            .iter()
+            .filter_map(|(address, previous)| {
+                let value = read(mmu, width, *address);
+
+                if comparison.matches(*previous, value) {
+                    Some((*address, value))
+                } else {
+                    None
+                }
+            })
+            .collect();
+
+        self.candidates.len()
+    }
+}
+
+fn read(mmu: &Mmu, width: Width, address: u16) -> u16 {
+    let byte = |address: u16| mmu.peek_byte(address).unwrap_or(0);
+
+    match width {
+        Width::Byte => byte(address) as u16,
+        Width::Word => u16::from_le_bytes([byte(address), byte(address + 1)]),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{bus::Bus, mmu::ROM_BANK_0_SIZE};
+
+    #[test]
+    fn it_narrows_down_candidates() {
+        let mut mmu = Mmu::new(Vec::new(), vec![0; ROM_BANK_0_SIZE]).unwrap();
+        mmu.write_byte(0xC100, 3);
+        mmu.write_byte(0xFF90, 3);
+
+        let mut search = Search::new(&mmu, Width::Byte);
+        assert_eq!(search.len(), 0x2000 + 0x2000 + 0x7F);
+
+        assert_eq!(search.filter(&mmu, Comparison::Value(3)), 2);
+
+        mmu.write_byte(0xC100, 2);
+        mmu.write_byte(0xFF90, 4);
+
+        assert_eq!(search.filter(&mmu, Comparison::Less), 1);
+        assert_eq!(search.candidates(), &[(0xC100, 2)]);
+
+        assert_eq!(search.filter(&mmu, Comparison::NotEqual), 0);
+    }
+
+    #[test]
+    fn it_searches_little_endian_words() {
+        let mut mmu = Mmu::new(Vec::new(), vec![0; ROM_BANK_0_SIZE]).unwrap();
+        mmu.write_byte(0xD000, 0x34);
+        mmu.write_byte(0xD001, 0x12);
+
+        let mut search = Search::new(&mmu, Width::Word);
+        assert_eq!(search.len(), 0x1FFF + 0x1FFF + 0x7E);
+
+        let value = Comparison::parse("eq", Some("0x1234")).unwrap();
+        assert_eq!(search.filter(&mmu, value), 1);
+
+        mmu.write_byte(0xD001, 0x13);
+
+        let greater = Comparison::parse("gt", None).unwrap();
+        assert_eq!(search.filter(&mmu, greater), 1);
+        assert_eq!(search.candidates(), &[(0xD000, 0x1334)]);
+
+        assert_eq!(
+            Comparison::parse("unchanged", None),
+            Some(Comparison::Equal)
+        );
+        assert_eq!(Comparison::parse("lt", Some("1")), None);
+    }
+}
@@ -0,0 +1,611 @@
+//! Super Game Boy support: command packets sent through P1, colourising the
+//! DMG's four shades per 8x8 cell and the border around the screen.
+//!
+//! VRAM transfers (CHR_TRN, PCT_TRN) take the 4 KiB the game displays in the
+//! top 20x13 tiles of the next frame, as the SGB captures them off the LCD.
+
+use crate::{
+    gpu::{rgb555_to_rgb, SCREEN_HEIGHT, SCREEN_WIDTH},
+    state::{Reader, Writer},
+};
+
+pub const BORDER_WIDTH: usize = 256;
+pub const BORDER_HEIGHT: usize = 224;
+
+// Where the game screen goes in the border
+const SCREEN_X: usize = 48;
+const SCREEN_Y: usize = 40;
+
+const CELLS_X: usize = SCREEN_WIDTH / 8;
+const CELLS_Y: usize = SCREEN_HEIGHT / 8;
+
+const PACKET_SIZE: usize = 16;
+const TRANSFER_SIZE: usize = 0x1000;
+const TILES_SIZE: usize = 0x2000;
+// 32x32 map entries, then border palettes 4-7
+const PICTURE_SIZE: usize = 0x880;
+const MAP_SIZE: usize = 0x800;
+
+// P1 select lines
+const P14: u8 = 1 << 4;
+const P15: u8 = 1 << 5;
+
+const PAL01: u8 = 0x00;
+const PAL23: u8 = 0x01;
+const PAL03: u8 = 0x02;
+const PAL12: u8 = 0x03;
+const ATTR_BLK: u8 = 0x04;
+const ATTR_LIN: u8 = 0x05;
+const ATTR_DIV: u8 = 0x06;
+const ATTR_CHR: u8 = 0x07;
+const MLT_REQ: u8 = 0x11;
+const CHR_TRN: u8 = 0x13;
+const PCT_TRN: u8 = 0x14;
+const MASK_EN: u8 = 0x17;
+
+// MASK_EN modes
+const MASK_CANCEL: u8 = 0;
+const MASK_FREEZE: u8 = 1;
+const MASK_BLACK: u8 = 2;
+const MASK_COLOR_0: u8 = 3;
+
+const GRAYSCALE: [u16; 4] = [0x7FFF, 0x56B5, 0x294A, 0x0000];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Transfer {
+    /// Border tiles 0x00-0x7F or 0x80-0xFF.
+    Chr(usize),
+    Pct,
+}
+
+// TODO: PAL_SET/PAL_TRN, ATTR_TRN/ATTR_SET and sound commands
+#[derive(Clone)]
+pub struct Sgb {
+    receiving: bool,
+    bits: usize,
+    packet: [u8; PACKET_SIZE],
+    packets: Vec<[u8; PACKET_SIZE]>,
+    lines: u8,
+    palettes: [[u16; 4]; 4],
+    // Palette per 8x8 cell
+    attributes: [u8; CELLS_X * CELLS_Y],
+    mask: u8,
+    // The last frame, shown while masked with MASK_FREEZE
+    frozen: Vec<u8>,
+    transfer: Option<Transfer>,
+    tiles: [u8; TILES_SIZE],
+    picture: [u8; PICTURE_SIZE],
+    players: u8,
+    player: u8,
+}
+
+impl Default for Sgb {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Sgb {
+    pub fn new() -> Self {
+        Self {
+            receiving: false,
+            bits: 0,
+            packet: [0; PACKET_SIZE],
+            packets: Vec::new(),
+            lines: P14 | P15,
+            palettes: [GRAYSCALE; 4],
+            attributes: [0; CELLS_X * CELLS_Y],
+            mask: MASK_CANCEL,
+            frozen: vec![0; SCREEN_WIDTH * SCREEN_HEIGHT],
+            transfer: None,
+            tiles: [0; TILES_SIZE],
+            picture: [0; PICTURE_SIZE],
+            players: 1,
+            player: 0,
+        }
+    }
+
+    /// Players MLT_REQ enabled: 1, 2 or 4.
+    pub fn players(&self) -> u8 {
+        self.players
+    }
+
+    /// Follows a write to P1. Packets start with both lines low, then send
+    /// each bit as a pulse on P14 (0) or P15 (1), LSB first.
+    pub fn write(&mut self, byte: u8) {
+        let lines = byte & (P14 | P15);
+        let idle = self.lines == P14 | P15;
+
+        // Each pulse on P15 selects the next controller
+        if self.players > 1 && lines & P15 == 0 && self.lines & P15 != 0 {
+            self.player = (self.player + 1) % self.players;
+        }
+
+        match lines {
+            0 => {
+                self.receiving = true;
+                self.bits = 0;
+                self.packet = [0; PACKET_SIZE];
+            }
+            P14 | P15 if self.receiving && idle => {
+                let bit = (lines == P14) as u8;
+
+                if self.bits == PACKET_SIZE * 8 {
+                    // The stop bit
+                    self.receiving = false;
+
+                    if bit == 0 {
+                        self.receive(self.packet);
+                    }
+                } else {
+                    self.packet[self.bits / 8] |= bit << (self.bits % 8);
+                    self.bits += 1;
+                }
+            }
+            _ => {}
+        }
+
+        self.lines = lines;
+    }
+
+    /// P1 as the selected controller sees it. With both lines high in
+    /// multiplayer mode, the low nibble is 0xF minus the controller.
+    pub fn read(&self, p1: u8) -> u8 {
+        if self.players > 1 && p1 & (P14 | P15) == P14 | P15 {
+            (p1 & 0xF0) | (0x0F - self.player)
+        } else if self.player != 0 {
+            // Only the first controller is connected
+            p1 | 0x0F
+        } else {
+            p1
+        }
+    }
+
+    fn receive(&mut self, packet: [u8; PACKET_SIZE]) {
+        self.packets.push(packet);
+
+        let length = (self.packets[0][0] & 0b111).max(1) as usize;
+
+        if self.packets.len() < length {
+            return;
+        }
+
+        let data: Vec<u8> = self.packets.drain(..).flatten().collect();
+
+        self.command(data[0] >> 3, &data);
+    }
+
+    fn command(&mut self, command: u8, data: &[u8]) {
+        match command {
+            PAL01 => self.set_palettes(0, 1, data),
+            PAL23 => self.set_palettes(2, 3, data),
+            PAL03 => self.set_palettes(0, 3, data),
+            PAL12 => self.set_palettes(1, 2, data),
+            ATTR_BLK => self.attr_blk(data),
+            ATTR_LIN => self.attr_lin(data),
+            ATTR_DIV => self.attr_div(data),
+            ATTR_CHR => self.attr_chr(data),
+            MLT_REQ => {
+                self.players = match data[1] & 0b11 {
+                    1 => 2,
+                    3 => 4,
+                    _ => 1,
+                };
+
+                self.player = 0;
+            }
+            CHR_TRN => {
+                self.transfer = Some(Transfer::Chr(data[1] as usize & 1))
+            }
+            PCT_TRN => self.transfer = Some(Transfer::Pct),
+            MASK_EN => self.mask = data[1] & 0b11,
+            _ => {}
+        }
+    }
+
+    /// Colour 0 is shared by all four palettes.
+    fn set_palettes(&mut self, p: usize, q: usize, data: &[u8]) {
+        let color = |i: usize| u16::from_le_bytes([data[i], data[i + 1]]);
+
+        for palette in self.palettes.iter_mut() {
+            palette[0] = color(1);
+        }
+
+        for i in 0..3 {
+            self.palettes[p][i + 1] = color(3 + i * 2);
+            self.palettes[q][i + 1] = color(9 + i * 2);
+        }
+    }
+
+    fn attr_blk(&mut self, data: &[u8]) {
+        let sets = (data[1] as usize).min((data.len() - 2) / 6);
+
+        for set in data[2..2 + sets * 6].chunks(6) {
+            let (control, palettes) = (set[0] & 0b111, set[1]);
+            let (x1, y1, x2, y2) = (set[2], set[3], set[4], set[5]);
+
+            let inside = palettes & 0b11;
+            let outside = (palettes >> 4) & 0b11;
+
+            // With only one side set, the border goes with it
+            let border = match control {
+                0b001 => Some(inside),
+                0b100 => Some(outside),
+                _ if control & 0b010 != 0 => Some((palettes >> 2) & 0b11),
+                _ => None,
+            };
+
+            for (i, cell) in self.attributes.iter_mut().enumerate() {
+                let (x, y) = ((i % CELLS_X) as u8, (i / CELLS_X) as u8);
+
+                let within = x >= x1 && x <= x2 && y >= y1 && y <= y2;
+                let on_edge = x == x1 || x == x2 || y == y1 || y == y2;
+
+                let palette = if within && on_edge {
+                    border
+                } else if within && control & 0b001 != 0 {
+                    Some(inside)
+                } else if !within && control & 0b100 != 0 {
+                    Some(outside)
+                } else {
+                    None
+                };
+
+                if let Some(palette) = palette {
+                    *cell = palette;
+                }
+            }
+        }
+    }
+
+    fn attr_lin(&mut self, data: &[u8]) {
+        let lines = (data[1] as usize).min(data.len() - 2);
+
+        for line in &data[2..2 + lines] {
+            let (n, palette) = ((line & 0x1F) as usize, (line >> 5) & 0b11);
+
+            for (i, cell) in self.attributes.iter_mut().enumerate() {
+                let (x, y) = (i % CELLS_X, i / CELLS_X);
+
+                // Bit 7 picks a row rather than a column
+                if (line & 0x80 != 0 && y == n) || (line & 0x80 == 0 && x == n)
+                {
+                    *cell = palette;
+                }
+            }
+        }
+    }
+
+    fn attr_div(&mut self, data: &[u8]) {
+        let control = data[1];
+        let at = data[2] as usize;
+
+        for (i, cell) in self.attributes.iter_mut().enumerate() {
+            // Bit 6 divides by rows rather than columns
+            let n = if control & 0x40 != 0 {
+                i / CELLS_X
+            } else {
+                i % CELLS_X
+            };
+
+            let shift = match n {
+                n if n < at => 2,
+                n if n == at => 4,
+                _ => 0,
+            };
+
+            *cell = (control >> shift) & 0b11;
+        }
+    }
+
+    fn attr_chr(&mut self, data: &[u8]) {
+        let (mut x, mut y) = (data[1] as usize, data[2] as usize);
+        let count = u16::from_le_bytes([data[3], data[4]]) as usize;
+        let vertical = data[5] & 1 != 0;
+
+        for n in 0..count.min((data.len() - 6) * 4) {
+            if x >= CELLS_X || y >= CELLS_Y {
+                break;
+            }
+
+            let palette = (data[6 + n / 4] >> (6 - (n % 4) * 2)) & 0b11;
+            self.attributes[y * CELLS_X + x] = palette;
+
+            if vertical {
+                y += 1;
+
+                if y == CELLS_Y {
+                    y = 0;
+                    x += 1;
+                }
+            } else {
+                x += 1;
+
+                if x == CELLS_X {
+                    x = 0;
+                    y += 1;
+                }
+            }
+        }
+    }
+
+    /// Called with every completed frame's shades, for VRAM transfers.
+    pub fn end_frame(&mut self, screen: &[u8]) {
+        if self.mask != MASK_FREEZE {
+            self.frozen.copy_from_slice(screen);
+        }
+
+        let transfer = match self.transfer.take() {
+            Some(transfer) => transfer,
+            None => return,
+        };
+
+        let data = capture(screen);
+
+        match transfer {
+            Transfer::Chr(half) => self.tiles
+                [half * TRANSFER_SIZE..(half + 1) * TRANSFER_SIZE]
+                .copy_from_slice(&data),
+            Transfer::Pct => {
+                self.picture.copy_from_slice(&data[..PICTURE_SIZE])
+            }
+        }
+    }
+
+    /// The 160x144 screen in `0xRRGGBB`, coloured per cell.
+    pub fn colorize(&self, screen: &[u8]) -> Vec<u32> {
+        let screen = if self.mask == MASK_FREEZE {
+            &self.frozen
+        } else {
+            screen
+        };
+
+        screen
+            .iter()
+            .enumerate()
+            .map(|(i, shade)| {
+                let (x, y) = (i % SCREEN_WIDTH, i / SCREEN_WIDTH);
+                let palette = self.attributes[(y / 8) * CELLS_X + x / 8];
+
+                let color = match self.mask {
+                    MASK_BLACK => 0,
+                    MASK_COLOR_0 => self.palettes[0][0],
+                    _ => self.palettes[palette as usize][*shade as usize],
+                };
+
+                rgb555_to_rgb(color)
+            })
+            .collect()
+    }
+
+    /// The screen in its 256x224 border, in `0xRRGGBB`.
+    pub fn frame(&self, screen: &[u8]) -> Vec<u32> {
+        let backdrop = rgb555_to_rgb(self.palettes[0][0]);
+        let mut out = vec![backdrop; BORDER_WIDTH * BORDER_HEIGHT];
+
+        for (i, pixel) in self.colorize(screen).into_iter().enumerate() {
+            let (x, y) = (i % SCREEN_WIDTH, i / SCREEN_WIDTH);
+
+            out[(SCREEN_Y + y) * BORDER_WIDTH + SCREEN_X + x] = pixel;
+        }
+
+        for (i, pixel) in out.iter_mut().enumerate() {
+            let (x, y) = (i % BORDER_WIDTH, i / BORDER_WIDTH);
+
+            if let Some(color) = self.border_pixel(x, y) {
+                *pixel = rgb555_to_rgb(color);
+            }
+        }
+
+        out
+    }
+
+    /// Border colour at `x`, `y`, or `None` where it's transparent.
+    fn border_pixel(&self, x: usize, y: usize) -> Option<u16> {
+        let i = ((y / 8) * 32 + x / 8) * 2;
+        let entry = u16::from_le_bytes([self.picture[i], self.picture[i + 1]]);
+
+        let tile = &self.tiles[(entry as usize & 0xFF) * 32..][..32];
+        // Palettes 4-7 are the border's
+        let palette = (entry >> 10) as usize & 0b11;
+
+        let (mut x, mut y) = (x % 8, y % 8);
+
+        if entry & 0x4000 != 0 {
+            x = 7 - x;
+        }
+
+        if entry & 0x8000 != 0 {
+            y = 7 - y;
+        }
+
+        // SNES 4bpp: planes 0 and 1 interleaved, then planes 2 and 3
+        let bit = 7 - x;
+        let color = [
+            tile[y * 2],
+            tile[y * 2 + 1],
+            tile[16 + y * 2],
+            tile[17 + y * 2],
+        ]
+        .iter()
+        .enumerate()
+        .fold(0, |color, (plane, byte)| {
+            color | ((byte >> bit) & 1) << plane
+        }) as usize;
+
+        if color == 0 {
+            return None;
+        }
+
+        let offset = MAP_SIZE + (palette * 16 + color) * 2;
+
+        Some(u16::from_le_bytes([
+            self.picture[offset],
+            self.picture[offset + 1],
+        ]))
+    }
+
+    pub fn save(&self, w: &mut Writer) {
+        for palette in self.palettes.iter() {
+            for color in palette.iter() {
+                w.u16(*color);
+            }
+        }
+
+        w.bytes(&self.attributes);
+        w.u8(self.mask);
+        w.bytes(&self.frozen);
+        w.bytes(&self.tiles);
+        w.bytes(&self.picture);
+        w.u8(self.players);
+        w.u8(self.player);
+    }
+
+    /// Packets in flight and pending transfers are dropped.
+    pub fn load(&mut self, r: &mut Reader) -> Result<(), String> {
+        for palette in self.palettes.iter_mut() {
+            for color in palette.iter_mut() {
+                *color = r.u16()?;
+            }
+        }
+
+        r.fill(&mut self.attributes)?;
+        self.mask = r.u8()?;
+        r.fill(&mut self.frozen)?;
+        r.fill(&mut self.tiles)?;
+        r.fill(&mut self.picture)?;
+        self.players = r.u8()?;
+        self.player = r.u8()?;
+
+        self.receiving = false;
+        self.packets.clear();
+        self.transfer = None;
+
+        Ok(())
+    }
+}
+
+/// The 4 KiB of tile data shown in the top 20x13 tiles of `screen`.
+fn capture(screen: &[u8]) -> Vec<u8> {
+    let mut data = vec![0; TRANSFER_SIZE];
+
+    for (tile, bytes) in data.chunks_mut(16).enumerate() {
+        let (tx, ty) = (tile % CELLS_X, tile / CELLS_X);
+
+        for row in 0..8 {
+            let start = (ty * 8 + row) * SCREEN_WIDTH + tx * 8;
+
+            for (x, shade) in screen[start..start + 8].iter().enumerate() {
+                bytes[row * 2] |= (shade & 1) << (7 - x);
+                bytes[row * 2 + 1] |= ((shade >> 1) & 1) << (7 - x);
+            }
+        }
+    }
+
+    data
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn send(sgb: &mut Sgb, packet: &[u8]) {
+        sgb.write(0);
+        sgb.write(P14 | P15);
+
+        for i in 0..PACKET_SIZE * 8 {
+            let bit = packet.get(i / 8).map_or(0, |b| (b >> (i % 8)) & 1);
+
+            sgb.write(if bit == 1 { P14 } else { P15 });
+            sgb.write(P14 | P15);
+        }
+
+        sgb.write(P15);
+        sgb.write(P14 | P15);
+    }
+
+    #[test]
+    fn it_colours_cells_from_packets() {
+        let mut sgb = Sgb::new();
+
+        // PAL01: red and blue as colour 3
+        send(
+            &mut sgb,
+            &[
+                PAL01 << 3 | 1,
+                0xFF,
+                0x7F,
+                0,
+                0,
+                0,
+                0,
+                0x1F,
+                0,
+                0,
+                0,
+                0,
+                0,
+                0,
+                0x7C,
+            ],
+        );
+
+        // ATTR_BLK: palette 1 inside and on the edge of cells (1,0)-(2,1)
+        send(&mut sgb, &[ATTR_BLK << 3 | 1, 1, 0b011, 0b0101, 1, 0, 2, 1]);
+
+        let screen = vec![3; SCREEN_WIDTH * SCREEN_HEIGHT];
+        let frame = sgb.colorize(&screen);
+
+        assert_eq!(frame[0], 0xFF0000);
+        assert_eq!(frame[8], 0x0000FF);
+        assert_eq!(frame[SCREEN_WIDTH * 15 + 23], 0x0000FF);
+        assert_eq!(frame[SCREEN_WIDTH * 16 + 8], 0xFF0000);
+
+        // MASK_EN colour 0 blanks the screen
+        send(&mut sgb, &[MASK_EN << 3 | 1, MASK_COLOR_0]);
+        assert_eq!(sgb.colorize(&screen)[8], 0xFFFFFF);
+    }
+
+    #[test]
+    fn it_switches_controllers_in_multiplayer_mode() {
+        let mut sgb = Sgb::new();
+
+        send(&mut sgb, &[MLT_REQ << 3 | 1, 1]);
+        assert_eq!(sgb.players(), 2);
+
+        assert_eq!(sgb.read(0xFF), 0xFF);
+
+        sgb.write(P14);
+        sgb.write(P14 | P15);
+        assert_eq!(sgb.read(0xFF), 0xFE);
+        assert_eq!(sgb.read(0xEF), 0xEF | 0x0F);
+    }
+
+    #[test]
+    fn it_draws_the_border_from_transfers() {
+        let mut sgb = Sgb::new();
+
+        // Border tile 0, row 0 in colour 1: the top row of the first tile
+        let mut screen = vec![0; SCREEN_WIDTH * SCREEN_HEIGHT];
+        screen[..8].copy_from_slice(&[1; 8]);
+
+        send(&mut sgb, &[CHR_TRN << 3 | 1, 0]);
+        sgb.end_frame(&screen);
+
+        // Every map entry is tile 0 in palette 4, whose colour 1 (at 0x802,
+        // row 1 of tile 128) is 0x03E0, green
+        let mut screen = vec![0; SCREEN_WIDTH * SCREEN_HEIGHT];
+        let start = (128 / CELLS_X * 8 + 1) * SCREEN_WIDTH + 128 % CELLS_X * 8;
+        screen[start..start + 8].copy_from_slice(&[1, 1, 1, 0, 0, 0, 2, 2]);
+
+        send(&mut sgb, &[PCT_TRN << 3 | 1]);
+        sgb.end_frame(&screen);
+
+        let frame = sgb.frame(&screen);
+
+        assert_eq!(frame[0], 0x00FF00);
+        assert_eq!(frame[BORDER_WIDTH - 1], 0x00FF00);
+        // Colour 0 is transparent
+        assert_eq!(frame[BORDER_WIDTH], 0xFFFFFF);
+    }
+}
@@ -0,0 +1,141 @@
+//! Save states: everything the emulation depends on, as a flat little-endian
+//! dump. ROM and boot ROM contents are not included.
+
+use std::convert::TryInto;
+
+pub struct Writer {
+    data: Vec<u8>,
+}
+
+impl Default for Writer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Writer {
+    pub fn new() -> Self {
+        Self { data: Vec::new() }
+    }
+
+    pub fn u8(&mut self, val: u8) {
+        self.data.push(val);
+    }
+
+    pub fn bool(&mut self, val: bool) {
+        self.data.push(val as u8);
+    }
+
+    pub fn u16(&mut self, val: u16) {
+        self.data.extend_from_slice(&val.to_le_bytes());
+    }
+
+    pub fn u32(&mut self, val: u32) {
+        self.data.extend_from_slice(&val.to_le_bytes());
+    }
+
+    pub fn u64(&mut self, val: u64) {
+        self.data.extend_from_slice(&val.to_le_bytes());
+    }
+
+    /// Fixed-size data; the reader has to know the length.
+    pub fn bytes(&mut self, val: &[u8]) {
+        self.data.extend_from_slice(val);
+    }
+
+    /// Variable-size data, prefixed with its length.
+    pub fn vec(&mut self, val: &[u8]) {
+        self.u32(val.len() as u32);
+        self.bytes(val);
+    }
+
+    pub fn finish(self) -> Vec<u8> {
+        self.data
+    }
+}
+
+pub struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    pub fn new(data: &'a [u8]) -> Self {
+        Self { data, pos: 0 }
+    }
+
+    pub fn bytes(&mut self, len: usize) -> Result<&'a [u8], String> {
+        let bytes = self
+            .data
+            .get(self.pos..self.pos + len)
+            .ok_or("truncated save state")?;
+
+        self.pos += len;
+
+        Ok(bytes)
+    }
+
+    pub fn fill(&mut self, dest: &mut [u8]) -> Result<(), String> {
+        dest.copy_from_slice(self.bytes(dest.len())?);
+
+        Ok(())
+    }
+
+    pub fn u8(&mut self) -> Result<u8, String> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    pub fn bool(&mut self) -> Result<bool, String> {
+        Ok(self.u8()? != 0)
+    }
+
+    pub fn u16(&mut self) -> Result<u16, String> {
+        Ok(u16::from_le_bytes(self.bytes(2)?.try_into().unwrap()))
+    }
+
+    pub fn u32(&mut self) -> Result<u32, String> {
+        Ok(u32::from_le_bytes(self.bytes(4)?.try_into().unwrap()))
+    }
+
+    pub fn u64(&mut self) -> Result<u64, String> {
+        Ok(u64::from_le_bytes(self.bytes(8)?.try_into().unwrap()))
+    }
+
+    pub fn vec(&mut self) -> Result<Vec<u8>, String> {
+        let len = self.u32()? as usize;
+
+        Ok(self.bytes(len)?.to_vec())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pos == self.data.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_reads_back_what_was_written() {
+        let mut writer = Writer::new();
+        writer.u8(1);
+        writer.bool(true);
+        writer.u16(0x1234);
+        writer.u32(0xDEADBEEF);
+        writer.u64(u64::MAX);
+        writer.vec(b"rboy");
+
+        let data = writer.finish();
+        let mut reader = Reader::new(&data);
+
+        assert_eq!(reader.u8(), Ok(1));
+        assert_eq!(reader.bool(), Ok(true));
+        assert_eq!(reader.u16(), Ok(0x1234));
+        assert_eq!(reader.u32(), Ok(0xDEADBEEF));
+        assert_eq!(reader.u64(), Ok(u64::MAX));
+        assert_eq!(reader.vec(), Ok(b"rboy".to_vec()));
+        assert!(reader.is_empty());
+        assert!(reader.u8().is_err());
+    }
+}
@@ -0,0 +1,203 @@
+use crate::bus::Bus;
+use std::{
+    collections::{BTreeMap, HashMap},
+    io,
+    path::Path,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Location {
+    pub bank: u16,
+    pub address: u16,
+}
+
+impl Location {
+    pub fn at<B: Bus>(bus: &B, address: u16) -> Self {
+        Self {
+            bank: bus.bank_at(address),
+            address,
+        }
+    }
+}
+
+/// Labels from an RGBDS `.sym` file (`bank:addr label` per line).
+pub struct Symbols {
+    by_location: BTreeMap<Location, String>,
+    by_name: HashMap<String, Location>,
+}
+
+impl Default for Symbols {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Symbols {
+    pub fn new() -> Self {
+        Self {
+            by_location: BTreeMap::new(),
+            by_name: HashMap::new(),
+        }
+    }
+
+    /// Unparsable lines are skipped; WLA-DX section headers and comments
+    /// share the format.
+    pub fn parse(source: &str) -> Self {
+        let mut symbols = Self::new();
+
+        for line in source.lines() {
+            let line = line.split(';').next().unwrap_or("");
+            let mut words = line.split_whitespace();
+
+            let (location, name) = match (words.next(), words.next()) {
+                (Some(location), Some(name)) => (location, name),
+                _ => continue,
+            };
+
+            let mut parts = location.splitn(2, ':');
+
+            let bank =
+                parts.next().and_then(|b| u16::from_str_radix(b, 16).ok());
+            let address =
+                parts.next().and_then(|a| u16::from_str_radix(a, 16).ok());
+
+            if let (Some(bank), Some(address)) = (bank, address) {
+                symbols.insert(Location { bank, address }, name);
+            }
+        }
+
+        symbols
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Ok(Self::parse(&std::fs::read_to_string(path)?))
+    }
+
+    /// Loads the `.sym` file next to the ROM, if there is one.
+    pub fn for_rom<P: AsRef<Path>>(rom_path: P) -> Self {
+        Self::load(rom_path.as_ref().with_extension("sym"))
+            .unwrap_or_else(|_| Self::new())
+    }
+
+    pub fn insert(&mut self, location: Location, name: &str) {
+        self.by_location.insert(location, name.to_string());
+        self.by_name.insert(name.to_string(), location);
+    }
+
+    pub fn lookup(&self, name: &str) -> Option<Location> {
+        self.by_name.get(name).copied()
+    }
+
+    /// Nearest label at or below `location` within the same bank and memory
+    /// region, as `label` or `label+0x12`.
+    pub fn describe(&self, location: Location) -> Option<String> {
+        let start = Location {
+            bank: location.bank,
+            address: region_start(location.address),
+        };
+
+        let (found, name) =
+            self.by_location.range(start..=location).next_back()?;
+
+        match location.address - found.address {
+            0 => Some(name.clone()),
+            offset => Some(format!("{}+0x{:X}", name, offset)),
+        }
+    }
+
+    /// `label+offset` for the bank currently mapped at `address`, falling back
+    /// to the plain address.
+    pub fn format_address<B: Bus>(&self, bus: &B, address: u16) -> String {
+        self.describe(Location::at(bus, address))
+            .unwrap_or_else(|| format!("0x{:04X}", address))
+    }
+}
+
+fn region_start(address: u16) -> u16 {
+    match address {
+        0x0000..=0x3FFF => 0x0000,
+        0x4000..=0x7FFF => 0x4000,
+        0x8000..=0x9FFF => 0x8000,
+        0xA000..=0xBFFF => 0xA000,
+        0xC000..=0xCFFF => 0xC000,
+        0xD000..=0xDFFF => 0xD000,
+        0xE000..=0xFDFF => 0xE000,
+        0xFE00..=0xFEFF => 0xFE00,
+        0xFF00..=0xFF7F => 0xFF00,
+        _ => 0xFF80,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SYM: &str = "; File generated by rgblink
+00:0150 Main
+00:0160 Main.loop
+01:4000 BankedRoutine
+02:4000 OtherBankRoutine
+00:C000 wCounter
+[labels]
+garbage line
+";
+
+    fn location(bank: u16, address: u16) -> Location {
+        Location { bank, address }
+    }
+
+    #[test]
+    fn it_looks_up_labels_by_name() {
+        let symbols = Symbols::parse(SYM);
+
+        assert_eq!(symbols.lookup("Main.loop"), Some(location(0, 0x160)));
+        assert_eq!(
+            symbols.lookup("OtherBankRoutine"),
+            Some(location(2, 0x4000))
+        );
+        assert_eq!(symbols.lookup("garbage"), None);
+    }
+
+    #[test]
+    fn it_describes_addresses_with_offsets() {
+        let symbols = Symbols::parse(SYM);
+
+        assert_eq!(symbols.describe(location(0, 0x150)).unwrap(), "Main");
+        assert_eq!(symbols.describe(location(0, 0x15A)).unwrap(), "Main+0xA");
+        assert_eq!(
+            symbols.describe(location(0, 0x164)).unwrap(),
+            "Main.loop+0x4"
+        );
+        assert_eq!(symbols.describe(location(0, 0x100)), None);
+    }
+
+    #[test]
+    fn it_resolves_the_same_address_per_bank() {
+        let symbols = Symbols::parse(SYM);
+
+        assert_eq!(
+            symbols.describe(location(1, 0x4002)).unwrap(),
+            "BankedRoutine+0x2"
+        );
+        assert_eq!(
+            symbols.describe(location(2, 0x4002)).unwrap(),
+            "OtherBankRoutine+0x2"
+        );
+        assert_eq!(symbols.describe(location(3, 0x4002)), None);
+    }
+
+    #[test]
+    fn it_does_not_cross_memory_regions() {
+        let symbols = Symbols::parse(SYM);
+
+        assert_eq!(
+            symbols.describe(location(0, 0x3FFF)).unwrap(),
+            "Main.loop+0x3E9F"
+        );
+        assert_eq!(
+            symbols.describe(location(0, 0xC010)).unwrap(),
+            "wCounter+0x10"
+        );
+        assert_eq!(symbols.describe(location(0, 0xFF80)), None);
+    }
+}
@@ -0,0 +1,110 @@
+//! Video recording as an uncompressed YUV4MPEG2 (Y4M) stream, one frame per
+//! `headless::run_frame`, so the frame rate is exactly 4194304 / 70224 Hz.
+//! Most players and encoders read Y4M directly; the sound goes to a WAV file
+//! next to it, see `wav::Dump`.
+
+use crate::gpu::{SCREEN_HEIGHT, SCREEN_WIDTH};
+use std::{
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::Path,
+};
+
+/// Frame rate as a fraction: the clock speed over cycles per frame.
+const FRAME_RATE: (u64, u64) = (4194304, 70224);
+
+pub struct Recorder<W: Write> {
+    out: W,
+    frames: u64,
+}
+
+impl Recorder<BufWriter<File>> {
+    pub fn create<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Self::new(BufWriter::new(File::create(path)?))
+    }
+}
+
+impl<W: Write> Recorder<W> {
+    pub fn new(mut out: W) -> io::Result<Self> {
+        // Full resolution chroma, so no colour bleeds between pixels
+        writeln!(
+            out,
+            "YUV4MPEG2 W{} H{} F{}:{} Ip A1:1 C444",
+            SCREEN_WIDTH, SCREEN_HEIGHT, FRAME_RATE.0, FRAME_RATE.1
+        )?;
+
+        Ok(Self { out, frames: 0 })
+    }
+
+    pub fn frames(&self) -> u64 {
+        self.frames
+    }
+
+    /// Appends a frame of `0x00RRGGBB` pixels, e.g. `Gpu::frame_rgb`.
+    pub fn frame(&mut self, pixels: &[u32]) -> io::Result<()> {
+        let mut planes = vec![0; pixels.len() * 3];
+        let (y, uv) = planes.split_at_mut(pixels.len());
+        let (u, v) = uv.split_at_mut(pixels.len());
+
+        for (i, pixel) in pixels.iter().enumerate() {
+            let (luma, cb, cr) = to_yuv(*pixel);
+
+            y[i] = luma;
+            u[i] = cb;
+            v[i] = cr;
+        }
+
+        self.out.write_all(b"FRAME\n")?;
+        self.out.write_all(&planes)?;
+
+        self.frames += 1;
+
+        Ok(())
+    }
+
+    pub fn finish(mut self) -> io::Result<W> {
+        self.out.flush()?;
+
+        Ok(self.out)
+    }
+}
+
+/// BT.601 studio range, which Y4M assumes.
+fn to_yuv(pixel: u32) -> (u8, u8, u8) {
+    let r = (pixel >> 16 & 0xFF) as i32;
+    let g = (pixel >> 8 & 0xFF) as i32;
+    let b = (pixel & 0xFF) as i32;
+
+    let y = ((66 * r + 129 * g + 25 * b + 128) >> 8) + 16;
+    let u = ((-38 * r - 74 * g + 112 * b + 128) >> 8) + 128;
+    let v = ((112 * r - 94 * g - 18 * b + 128) >> 8) + 128;
+
+    (y as u8, u as u8, v as u8)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_writes_y4m_frames() {
+        let mut recorder = Recorder::new(Vec::new()).unwrap();
+        let pixels = [0xFFFFFF; SCREEN_WIDTH * SCREEN_HEIGHT];
+
+        recorder.frame(&pixels).unwrap();
+        recorder.frame(&[0; SCREEN_WIDTH * SCREEN_HEIGHT]).unwrap();
+        assert_eq!(recorder.frames(), 2);
+
+        let data = recorder.finish().unwrap();
+        let header = b"YUV4MPEG2 W160 H144 F4194304:70224 Ip A1:1 C444\n";
+        let frame = 6 + pixels.len() * 3;
+
+        assert_eq!(&data[..header.len()], &header[..]);
+        assert_eq!(data.len(), header.len() + frame * 2);
+
+        // White and black map to the ends of the studio range
+        let first = &data[header.len() + 6..];
+        assert_eq!((first[0], first[pixels.len()]), (235, 128));
+        assert_eq!(data[header.len() + frame + 6], 16);
+    }
+}
@@ -0,0 +1,201 @@
+//! Views of VRAM for debugging: the tile data as a sheet, and the two BG
+//! maps with the SCX/SCY viewport drawn over them.
+
+use crate::{
+    gpu::{Gpu, BG_MAPS, SCREEN_HEIGHT, SCREEN_WIDTH, TILES},
+    png::{self, Image},
+};
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+// The tile sheet is 16 tiles wide, per VRAM bank
+const SHEET_TILES: usize = 16;
+pub const SHEET_WIDTH: usize = SHEET_TILES * 8;
+pub const SHEET_HEIGHT: usize = TILES / SHEET_TILES * 8;
+
+pub const MAP_SIZE: usize = 256;
+
+const VIEWPORT: u32 = 0xFF0000;
+const BACKGROUND: u32 = 0x404040;
+
+/// Every tile in BG palette 0, bank 1 to the right of bank 0 in CGB mode.
+pub fn tiles(gpu: &Gpu) -> Image {
+    let banks = if gpu.is_cgb() { 2 } else { 1 };
+    let width = SHEET_WIDTH * banks;
+    let mut pixels = vec![0; width * SHEET_HEIGHT];
+
+    for bank in 0..banks {
+        for tile in 0..TILES {
+            let left = bank * SHEET_WIDTH + (tile % SHEET_TILES) * 8;
+            let top = (tile / SHEET_TILES) * 8;
+
+            for y in 0..8 {
+                for x in 0..8 {
+                    let color = gpu.tile_color(bank, tile, x, y);
+
+                    pixels[(top + y) * width + left + x] = gpu.bg_rgb(color, 0);
+                }
+            }
+        }
+    }
+
+    Image {
+        width,
+        height: SHEET_HEIGHT,
+        pixels,
+    }
+}
+
+/// BG map `map` (0 at 0x9800, 1 at 0x9C00) as the current LCDC addresses
+/// its tiles, with the viewport outlined.
+pub fn map(gpu: &Gpu, map: usize) -> Image {
+    let mut pixels = Vec::with_capacity(MAP_SIZE * MAP_SIZE);
+
+    for y in 0..MAP_SIZE {
+        for x in 0..MAP_SIZE {
+            let (color, attributes) = gpu.tile_pixel(BG_MAPS[map], x, y);
+
+            pixels.push(gpu.bg_rgb(color, attributes));
+        }
+    }
+
+    // The viewport wraps around the map's edges
+    let (scx, scy) = gpu.scroll();
+    let mut plot = |x: usize, y: usize| {
+        let x = (scx as usize + x) % MAP_SIZE;
+        let y = (scy as usize + y) % MAP_SIZE;
+
+        pixels[y * MAP_SIZE + x] = VIEWPORT;
+    };
+
+    for x in 0..SCREEN_WIDTH {
+        plot(x, 0);
+        plot(x, SCREEN_HEIGHT - 1);
+    }
+
+    for y in 0..SCREEN_HEIGHT {
+        plot(0, y);
+        plot(SCREEN_WIDTH - 1, y);
+    }
+
+    Image {
+        width: MAP_SIZE,
+        height: MAP_SIZE,
+        pixels,
+    }
+}
+
+/// The tile sheet and both maps side by side, for the debug window.
+pub fn overview(gpu: &Gpu) -> Image {
+    let images = [tiles(gpu), map(gpu, 0), map(gpu, 1)];
+
+    let width = images.iter().map(|image| image.width).sum();
+    let mut pixels = vec![BACKGROUND; width * MAP_SIZE];
+    let mut left = 0;
+
+    for image in &images {
+        for (y, row) in image.pixels.chunks(image.width).enumerate() {
+            let start = y * width + left;
+
+            pixels[start..start + image.width].copy_from_slice(row);
+        }
+
+        left += image.width;
+    }
+
+    Image {
+        width,
+        height: MAP_SIZE,
+        pixels,
+    }
+}
+
+/// Writes `<prefix>-tiles.png`, `<prefix>-map0.png` and `<prefix>-map1.png`
+/// and returns their paths.
+pub fn save<P: AsRef<Path>>(gpu: &Gpu, prefix: P) -> io::Result<Vec<PathBuf>> {
+    let prefix = prefix.as_ref().to_string_lossy().to_string();
+
+    let images = [
+        ("tiles", tiles(gpu)),
+        ("map0", map(gpu, 0)),
+        ("map1", map(gpu, 1)),
+    ];
+
+    let mut paths = Vec::new();
+
+    for (name, image) in &images {
+        let path = PathBuf::from(format!("{}-{}.png", prefix, name));
+
+        fs::write(
+            &path,
+            png::encode(image.width, image.height, &image.pixels),
+        )?;
+
+        paths.push(path);
+    }
+
+    Ok(paths)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::palette::Palettes;
+
+    const SHADES: [u32; 4] = [0xFFFFFF, 0xAAAAAA, 0x555555, 0x000000];
+
+    fn gpu() -> Gpu {
+        let mut gpu = Gpu::new();
+        gpu.set_palettes(Palettes::uniform(SHADES));
+        // Identity BGP, tile data at 0x8000
+        gpu.write_reg(0xFF47, 0b1110_0100);
+        gpu.write_reg(0xFF40, 0x91);
+
+        gpu
+    }
+
+    #[test]
+    fn it_draws_the_tile_sheet() {
+        let mut gpu = gpu();
+        // Tile 17's first row: colours 3, 0, 1, 2, then 0
+        gpu.v_ram[17 * 16] = 0b1010_0000;
+        gpu.v_ram[17 * 16 + 1] = 0b1001_0000;
+
+        let image = tiles(&gpu);
+        assert_eq!((image.width, image.height), (128, 192));
+
+        let row = &image.pixels[8 * 128 + 8..8 * 128 + 13];
+        assert_eq!(
+            row,
+            &[SHADES[3], SHADES[0], SHADES[1], SHADES[2], SHADES[0]]
+        );
+
+        gpu.set_cgb(true);
+        assert_eq!(tiles(&gpu).width, 256);
+    }
+
+    #[test]
+    fn it_outlines_the_viewport_on_maps() {
+        let mut gpu = gpu();
+        gpu.v_ram[0x10] = 0xFF;
+        gpu.v_ram[BG_MAPS[1]] = 1;
+        gpu.write_reg(0xFF42, 200);
+        gpu.write_reg(0xFF43, 180);
+
+        let image = map(&gpu, 1);
+        assert_eq!(image.pixels[1], SHADES[1]);
+        assert_eq!(image.pixels[0x100 + 1], SHADES[0]);
+        assert_eq!(map(&gpu, 0).pixels[1], SHADES[0]);
+
+        // Corners, wrapping past the right and bottom edges
+        let at = |x: usize, y: usize| image.pixels[y * MAP_SIZE + x];
+        assert_eq!(at(180, 200), VIEWPORT);
+        assert_eq!(at((180 + 159) % 256, (200 + 143) % 256), VIEWPORT);
+        assert_eq!(at(181, 201), SHADES[0]);
+
+        let overview = overview(&gpu);
+        assert_eq!((overview.width, overview.height), (128 + 512, 256));
+    }
+}
@@ -0,0 +1,171 @@
+//! 16-bit PCM WAV files, written as samples come in. The chunk sizes are
+//! patched in by `Writer::finish`.
+
+use crate::apu::{Sample, CHANNELS, SAMPLE_RATE};
+use std::{
+    fs::File,
+    io::{self, BufWriter, Seek, SeekFrom, Write},
+    path::Path,
+};
+
+const HEADER_SIZE: u32 = 44;
+
+pub struct Writer<W: Write + Seek> {
+    out: W,
+    channels: u16,
+    samples: u32,
+}
+
+impl Writer<BufWriter<File>> {
+    pub fn create<P: AsRef<Path>>(path: P, channels: u16) -> io::Result<Self> {
+        Self::new(BufWriter::new(File::create(path)?), channels)
+    }
+}
+
+impl<W: Write + Seek> Writer<W> {
+    /// `channels` interleaved channels at `apu::SAMPLE_RATE`.
+    pub fn new(mut out: W, channels: u16) -> io::Result<Self> {
+        let block_align = channels * 2;
+
+        out.write_all(b"RIFF")?;
+        out.write_all(&(HEADER_SIZE - 8).to_le_bytes())?;
+        out.write_all(b"WAVEfmt ")?;
+        out.write_all(&16u32.to_le_bytes())?;
+        // PCM
+        out.write_all(&1u16.to_le_bytes())?;
+        out.write_all(&channels.to_le_bytes())?;
+        out.write_all(&SAMPLE_RATE.to_le_bytes())?;
+        out.write_all(&(SAMPLE_RATE * block_align as u32).to_le_bytes())?;
+        out.write_all(&block_align.to_le_bytes())?;
+        out.write_all(&16u16.to_le_bytes())?;
+        out.write_all(b"data")?;
+        out.write_all(&0u32.to_le_bytes())?;
+
+        Ok(Self {
+            out,
+            channels,
+            samples: 0,
+        })
+    }
+
+    /// Appends interleaved samples.
+    pub fn write(&mut self, samples: &[i16]) -> io::Result<()> {
+        let bytes: Vec<u8> = samples
+            .iter()
+            .flat_map(|s| s.to_le_bytes().to_vec())
+            .collect();
+
+        self.out.write_all(&bytes)?;
+        self.samples += samples.len() as u32;
+
+        Ok(())
+    }
+
+    /// Sample frames written so far, i.e. one per channel.
+    pub fn frames(&self) -> u32 {
+        self.samples / self.channels as u32
+    }
+
+    pub fn finish(mut self) -> io::Result<W> {
+        let data = self.samples * 2;
+
+        self.out.seek(SeekFrom::Start(4))?;
+        self.out
+            .write_all(&(HEADER_SIZE - 8 + data).to_le_bytes())?;
+        self.out.seek(SeekFrom::Start(HEADER_SIZE as u64 - 4))?;
+        self.out.write_all(&data.to_le_bytes())?;
+        self.out.seek(SeekFrom::End(0))?;
+        self.out.flush()?;
+
+        Ok(self.out)
+    }
+}
+
+/// APU output as a stereo mix, optionally with a mono stem per channel
+/// next to it (`out.wav`, `out.square1.wav`, ...).
+pub struct Dump {
+    mix: Writer<BufWriter<File>>,
+    stems: Vec<Writer<BufWriter<File>>>,
+    // The first write error, reported by `finish`.
+    error: Option<io::Error>,
+}
+
+impl Dump {
+    pub fn create<P: AsRef<Path>>(path: P, stems: bool) -> io::Result<Self> {
+        let path = path.as_ref();
+        let mut writers = Vec::new();
+
+        if stems {
+            for name in &CHANNELS {
+                let stem = path.with_extension(format!("{}.wav", name));
+
+                writers.push(Writer::create(stem, 1)?);
+            }
+        }
+
+        Ok(Self {
+            mix: Writer::create(path, 2)?,
+            stems: writers,
+            error: None,
+        })
+    }
+
+    pub fn write(&mut self, samples: &[Sample]) {
+        if self.error.is_some() || samples.is_empty() {
+            return;
+        }
+
+        let mix: Vec<i16> =
+            samples.iter().flat_map(|s| vec![s.left, s.right]).collect();
+
+        let mut result = self.mix.write(&mix);
+
+        for (channel, stem) in self.stems.iter_mut().enumerate() {
+            let mono: Vec<i16> =
+                samples.iter().map(|s| s.channels[channel]).collect();
+
+            result = result.and_then(|_| stem.write(&mono));
+        }
+
+        self.error = result.err();
+    }
+
+    /// Finishes every file and returns the number of samples per channel.
+    pub fn finish(self) -> io::Result<u32> {
+        if let Some(e) = self.error {
+            return Err(e);
+        }
+
+        let frames = self.mix.frames();
+
+        self.mix.finish()?;
+
+        for stem in self.stems {
+            stem.finish()?;
+        }
+
+        Ok(frames)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn it_patches_the_sizes_on_finish() {
+        let mut wav = Writer::new(Cursor::new(Vec::new()), 2).unwrap();
+        wav.write(&[1, -1, 0x1234, -0x1234]).unwrap();
+        assert_eq!(wav.frames(), 2);
+
+        let data = wav.finish().unwrap().into_inner();
+
+        assert_eq!(data.len(), 44 + 8);
+        assert_eq!(&data[4..8], &44u32.to_le_bytes());
+        assert_eq!(&data[22..24], &2u16.to_le_bytes());
+        assert_eq!(&data[24..28], &SAMPLE_RATE.to_le_bytes());
+        assert_eq!(&data[40..44], &8u32.to_le_bytes());
+        assert_eq!(&data[44..48], &[1, 0, 0xFF, 0xFF]);
+    }
+}
@@ -0,0 +1,207 @@
+//! Blargg and Mooneye test ROMs, run when present.
+//!
+//! ROMs are discovered under `tests/roms/blargg` and `tests/roms/mooneye` (or
+//! the same subdirectories of `$RBOY_TEST_ROMS`). A suite fails on any ROM
+//! that does not pass, unless the ROM is listed in `known_failures.txt` next
+//! to the suite's ROMs, one path (relative to the suite directory) per line.
+//! Run with `--nocapture` to see the table for passing suites too.
+
+use rboy::{
+    headless::{Headless, Outcome},
+    Cpu,
+};
+use std::{
+    collections::HashSet,
+    env, fs,
+    panic::{self, AssertUnwindSafe},
+    path::{Path, PathBuf},
+};
+
+const BLARGG_FRAMES: u64 = 60 * 60;
+const MOONEYE_FRAMES: u64 = 60 * 20;
+
+const FIBONACCI: [u8; 6] = [3, 5, 8, 13, 21, 34];
+const MOONEYE_FAILURE: u8 = 0x42;
+
+enum Verdict {
+    Pass,
+    Fail(String),
+}
+
+fn suite_dir(suite: &str) -> PathBuf {
+    env::var_os("RBOY_TEST_ROMS")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| {
+            Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/roms")
+        })
+        .join(suite)
+}
+
+fn discover(dir: &Path, roms: &mut Vec<PathBuf>) {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(_) => return,
+    };
+
+    for path in entries.filter_map(|e| e.ok()).map(|e| e.path()) {
+        if path.is_dir() {
+            discover(&path, roms);
+        } else if path.extension().is_some_and(|e| e == "gb" || e == "gbc") {
+            roms.push(path);
+        }
+    }
+}
+
+fn known_failures(dir: &Path) -> HashSet<String> {
+    fs::read_to_string(dir.join("known_failures.txt"))
+        .unwrap_or_default()
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(str::to_string)
+        .collect()
+}
+
+fn last_line(output: &[u8]) -> String {
+    let text = String::from_utf8_lossy(output);
+
+    text.lines()
+        .rev()
+        .find(|line| !line.trim().is_empty())
+        .unwrap_or("no serial output")
+        .trim()
+        .to_string()
+}
+
+/// Blargg ROMs print their result over serial and then loop forever.
+fn blargg(rom: Vec<u8>) -> Verdict {
+    let mut cpu = Cpu::post_boot(rom);
+    let mut headless = Headless::new(1);
+
+    for _ in 0..BLARGG_FRAMES {
+        if let Err(e) = headless.run(&mut cpu) {
+            return Verdict::Fail(e);
+        }
+
+        let output = String::from_utf8_lossy(cpu.bus.serial_output());
+
+        if output.contains("Passed") {
+            return Verdict::Pass;
+        }
+
+        if output.contains("Failed") {
+            return Verdict::Fail(last_line(cpu.bus.serial_output()));
+        }
+    }
+
+    Verdict::Fail(format!("timed out: {}", last_line(cpu.bus.serial_output())))
+}
+
+/// Mooneye ROMs execute `LD B, B` once done, with the Fibonacci numbers in
+/// B-L on success and 0x42 in every register on failure.
+fn mooneye(rom: Vec<u8>) -> Verdict {
+    let mut cpu = Cpu::post_boot(rom);
+    let mut headless = Headless::new(MOONEYE_FRAMES);
+    headless.ld_b_b = true;
+
+    match headless.run(&mut cpu) {
+        Ok(Outcome::Timeout) => return Verdict::Fail("timed out".to_string()),
+        Err(e) => return Verdict::Fail(e),
+        Ok(_) => {}
+    }
+
+    let r = &cpu.registers;
+    let signature = [r.b, r.c, r.d, r.e, r.h, r.l];
+
+    if signature == FIBONACCI {
+        Verdict::Pass
+    } else if signature.iter().all(|v| *v == MOONEYE_FAILURE) {
+        Verdict::Fail("failure signature".to_string())
+    } else {
+        Verdict::Fail(format!("registers {:02X?}", signature))
+    }
+}
+
+fn run_suite(suite: &str, judge: fn(Vec<u8>) -> Verdict) {
+    let dir = suite_dir(suite);
+
+    let mut roms = Vec::new();
+    discover(&dir, &mut roms);
+    roms.sort();
+
+    if roms.is_empty() {
+        println!("no {} ROMs in {}, skipping", suite, dir.display());
+
+        return;
+    }
+
+    let known = known_failures(&dir);
+    let mut passed = 0;
+    let mut regressions = Vec::new();
+
+    println!("{} ({})", suite, dir.display());
+
+    for path in &roms {
+        let name = path
+            .strip_prefix(&dir)
+            .unwrap_or(path)
+            .to_string_lossy()
+            .replace('\\', "/");
+
+        let rom = fs::read(path).unwrap();
+
+        // Unsupported operands and registers still panic; count those as
+        // failures.
+        let verdict = panic::catch_unwind(AssertUnwindSafe(|| judge(rom)))
+            .unwrap_or_else(|payload| {
+                let message = payload
+                    .downcast_ref::<String>()
+                    .cloned()
+                    .or_else(|| {
+                        payload.downcast_ref::<&str>().map(|s| s.to_string())
+                    })
+                    .unwrap_or_default();
+
+                Verdict::Fail(format!("panicked: {}", message))
+            });
+
+        if let Verdict::Pass = verdict {
+            passed += 1;
+        }
+
+        let (status, detail) = match verdict {
+            Verdict::Pass if known.contains(&name) => {
+                ("PASS", "listed as known failure".to_string())
+            }
+            Verdict::Pass => ("PASS", String::new()),
+            Verdict::Fail(detail) if known.contains(&name) => ("fail", detail),
+            Verdict::Fail(detail) => {
+                regressions.push(name.clone());
+
+                ("FAIL", detail)
+            }
+        };
+
+        println!("  {:<48} {} {}", name, status, detail);
+    }
+
+    println!("{} of {} passed", passed, roms.len());
+
+    assert!(
+        regressions.is_empty(),
+        "{} {} ROMs failed: {:?}",
+        regressions.len(),
+        suite,
+        regressions
+    );
+}
+
+#[test]
+fn blargg_roms() {
+    run_suite("blargg", blargg);
+}
+
+#[test]
+fn mooneye_roms() {
+    run_suite("mooneye", mooneye);
+}
@@ -0,0 +1,118 @@
+//! Golden-frame regression tests, run when the ROMs are present.
+//!
+//! `tests/golden/golden.txt` (or `golden.txt` in `$RBOY_GOLDEN`) lists one
+//! scene per line as `<rom> <frames> <reference>`, where the reference is a
+//! PNG next to the ROM or `crc32:XXXXXXXX`. On a mismatch the rendered frame
+//! and, for PNG references, a diff image are written to `target/golden`.
+//!
+//!     dmg-acid2.gb 60 dmg-acid2.png
+
+use rboy::{
+    golden::{self, Reference},
+    gpu::{SCREEN_HEIGHT, SCREEN_WIDTH},
+    png, Cpu,
+};
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+};
+
+fn golden_dir() -> PathBuf {
+    env::var_os("RBOY_GOLDEN")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| {
+            Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/golden")
+        })
+}
+
+fn write_png(path: &Path, pixels: &[u32]) {
+    fs::write(path, png::encode(SCREEN_WIDTH, SCREEN_HEIGHT, pixels)).unwrap();
+}
+
+#[test]
+fn golden_frames() {
+    let dir = golden_dir();
+
+    let manifest = match fs::read_to_string(dir.join("golden.txt")) {
+        Ok(manifest) => manifest,
+        Err(_) => {
+            println!("no golden.txt in {}, skipping", dir.display());
+
+            return;
+        }
+    };
+
+    let out = Path::new(env!("CARGO_MANIFEST_DIR")).join("target/golden");
+    let mut failures = Vec::new();
+
+    for line in manifest.lines().map(str::trim) {
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let fields: Vec<&str> = line.split_whitespace().collect();
+
+        let (rom, frames, reference) = match fields.as_slice() {
+            [rom, frames, reference] => (rom, frames, reference),
+            _ => panic!("invalid golden.txt line: {}", line),
+        };
+
+        let frames: u64 = frames.parse().expect("invalid frame count");
+        let reference = Reference::parse(reference, &dir).unwrap();
+
+        let mut cpu = Cpu::post_boot(fs::read(dir.join(rom)).unwrap());
+        let frame = match golden::render(&mut cpu, frames) {
+            Ok(frame) => frame,
+            Err(e) => {
+                println!("  {:<32} FAIL {}", rom, e);
+                failures.push(rom.to_string());
+
+                continue;
+            }
+        };
+
+        match golden::compare(&frame, &reference) {
+            Ok(()) => println!("  {:<32} PASS", rom),
+            Err(golden::Mismatch {
+                size: Some((width, height)),
+                ..
+            }) => {
+                println!(
+                    "  {:<32} FAIL size mismatch, reference is {}x{}",
+                    rom, width, height
+                );
+
+                failures.push(rom.to_string());
+            }
+            Err(mismatch) => {
+                let stem =
+                    Path::new(rom).file_stem().unwrap().to_string_lossy();
+
+                fs::create_dir_all(&out).unwrap();
+                write_png(&out.join(format!("{}.actual.png", stem)), &frame);
+
+                if let Some(diff) = &mismatch.diff {
+                    write_png(&out.join(format!("{}.diff.png", stem)), diff);
+                }
+
+                println!(
+                    "  {:<32} FAIL crc32:{:08X}, {} pixels differ",
+                    rom,
+                    mismatch.hash,
+                    mismatch
+                        .differing
+                        .map_or("unknown".to_string(), |n| n.to_string())
+                );
+
+                failures.push(rom.to_string());
+            }
+        }
+    }
+
+    assert!(
+        failures.is_empty(),
+        "frames differ for {:?}, see {}",
+        failures,
+        out.display()
+    );
+}
@@ -0,0 +1,273 @@
+//! SM83 single-step test vectors, run when present.
+//!
+//! Reads the `*.json` files of the SingleStepTests SM83 suite from
+//! `tests/sm83` (or `$RBOY_SM83_TESTS`). Each vector is run as one
+//! `Cpu::step` on a flat 64 KiB bus, then registers, memory, the cycle count
+//! and the bus accesses are compared. Accesses are compared as sets, since
+//! the CPU doesn't model individual M-cycles. Files listed in
+//! `known_failures.txt` in the same directory don't fail the test.
+
+use rboy::{
+    bus::{Access, FlatBus},
+    registers::{FlagsRegister, Registers},
+    Cpu,
+};
+use serde_json::Value;
+use std::{
+    collections::{BTreeSet, HashSet},
+    env, fs,
+    panic::{self, AssertUnwindSafe},
+    path::{Path, PathBuf},
+};
+
+fn tests_dir() -> PathBuf {
+    env::var_os("RBOY_SM83_TESTS")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| {
+            Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/sm83")
+        })
+}
+
+fn known_failures(dir: &Path) -> HashSet<String> {
+    fs::read_to_string(dir.join("known_failures.txt"))
+        .unwrap_or_default()
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(str::to_string)
+        .collect()
+}
+
+fn field(state: &Value, name: &str) -> u16 {
+    state[name]
+        .as_u64()
+        .unwrap_or_else(|| panic!("missing field {}", name)) as u16
+}
+
+fn ram(state: &Value) -> Vec<(u16, u8)> {
+    state["ram"]
+        .as_array()
+        .map(|entries| {
+            entries
+                .iter()
+                .map(|e| {
+                    (
+                        e[0].as_u64().unwrap() as u16,
+                        e[1].as_u64().unwrap() as u8,
+                    )
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn setup(initial: &Value) -> Cpu<FlatBus> {
+    let mut registers = Registers::new();
+
+    registers.a = field(initial, "a") as u8;
+    registers.b = field(initial, "b") as u8;
+    registers.c = field(initial, "c") as u8;
+    registers.d = field(initial, "d") as u8;
+    registers.e = field(initial, "e") as u8;
+    registers.f = FlagsRegister::from(field(initial, "f") as u8);
+    registers.h = field(initial, "h") as u8;
+    registers.l = field(initial, "l") as u8;
+
+    let mut bus = FlatBus::new();
+
+    for (address, byte) in ram(initial) {
+        bus.memory[address as usize] = byte;
+    }
+
+    let mut cpu = Cpu::with_bus(bus, Some(registers));
+
+    cpu.pc.set(field(initial, "pc"));
+    cpu.sp = field(initial, "sp");
+
+    cpu
+}
+
+/// Describes the first mismatch against `vector`, if any.
+// TODO: Compare IME and IE once interrupts are implemented
+fn check(vector: &Value) -> Option<String> {
+    let mut cpu = setup(&vector["initial"]);
+
+    cpu.bus.clear_accesses();
+
+    let ticks = match cpu.step() {
+        Ok(ticks) => ticks as usize,
+        Err(e) => return Some(e),
+    };
+
+    let expected = &vector["final"];
+    let r = &cpu.registers;
+
+    let actual = [
+        ("a", r.a as u16),
+        ("b", r.b as u16),
+        ("c", r.c as u16),
+        ("d", r.d as u16),
+        ("e", r.e as u16),
+        ("f", u8::from(r.f) as u16),
+        ("h", r.h as u16),
+        ("l", r.l as u16),
+        ("pc", cpu.pc.get()),
+        ("sp", cpu.sp),
+    ];
+
+    for (name, value) in actual.iter() {
+        if field(expected, name) != *value {
+            return Some(format!(
+                "{} is 0x{:X}, expected 0x{:X}",
+                name,
+                value,
+                field(expected, name)
+            ));
+        }
+    }
+
+    for (address, byte) in ram(expected) {
+        let value = cpu.bus.memory[address as usize];
+
+        if value != byte {
+            return Some(format!(
+                "(0x{:04X}) is 0x{:02X}, expected 0x{:02X}",
+                address, value, byte
+            ));
+        }
+    }
+
+    let cycles = vector["cycles"].as_array().cloned().unwrap_or_default();
+
+    if ticks != cycles.len() * 4 {
+        return Some(format!(
+            "took {} ticks, expected {}",
+            ticks,
+            cycles.len() * 4
+        ));
+    }
+
+    let expected: BTreeSet<Access> = cycles
+        .iter()
+        .filter_map(|c| {
+            let address = c[0].as_u64()? as u16;
+            let byte = c[1].as_u64()? as u8;
+
+            match c[2].as_str()? {
+                kind if kind.contains('w') => {
+                    Some(Access::Write(address, byte))
+                }
+                kind if kind.contains('r') => Some(Access::Read(address, byte)),
+                _ => None,
+            }
+        })
+        .collect();
+
+    let actual: BTreeSet<Access> = cpu.bus.accesses().into_iter().collect();
+
+    if actual != expected {
+        return Some(format!(
+            "accessed {:X?}, expected {:X?}",
+            actual, expected
+        ));
+    }
+
+    None
+}
+
+/// Runs every vector in `path`; returns how many passed and the first
+/// failure. A panic ends the file, since the rest would panic the same way.
+fn run_file(path: &Path) -> (usize, usize, Option<String>) {
+    let source = fs::read_to_string(path).unwrap();
+    let vectors: Vec<Value> = serde_json::from_str(&source).unwrap();
+
+    let mut passed = 0;
+    let mut first_failure = None;
+
+    for vector in &vectors {
+        let name = vector["name"].as_str().unwrap_or("?");
+
+        match panic::catch_unwind(AssertUnwindSafe(|| check(vector))) {
+            Ok(None) => passed += 1,
+            Ok(Some(mismatch)) => {
+                first_failure
+                    .get_or_insert_with(|| format!("{}: {}", name, mismatch));
+            }
+            Err(payload) => {
+                let message = payload
+                    .downcast_ref::<String>()
+                    .cloned()
+                    .or_else(|| {
+                        payload.downcast_ref::<&str>().map(|s| s.to_string())
+                    })
+                    .unwrap_or_default();
+
+                first_failure.get_or_insert_with(|| {
+                    format!("{}: panicked: {}", name, message)
+                });
+
+                break;
+            }
+        }
+    }
+
+    (passed, vectors.len(), first_failure)
+}
+
+#[test]
+fn sm83_single_step() {
+    let dir = tests_dir();
+
+    let mut files: Vec<PathBuf> = fs::read_dir(&dir)
+        .map(|entries| {
+            entries
+                .filter_map(|e| e.ok())
+                .map(|e| e.path())
+                .filter(|p| p.extension().is_some_and(|e| e == "json"))
+                .collect()
+        })
+        .unwrap_or_default();
+
+    files.sort();
+
+    if files.is_empty() {
+        println!("no SM83 test vectors in {}, skipping", dir.display());
+
+        return;
+    }
+
+    let known = known_failures(&dir);
+    let mut regressions = Vec::new();
+
+    for path in &files {
+        let name = path.file_name().unwrap().to_string_lossy().to_string();
+
+        let (passed, total, failure) = run_file(path);
+
+        let status = match &failure {
+            None => "PASS",
+            Some(_) if known.contains(&name) => "fail",
+            Some(_) => {
+                regressions.push(name.clone());
+
+                "FAIL"
+            }
+        };
+
+        println!(
+            "  {:<16} {} {:>5}/{:<5} {}",
+            name,
+            status,
+            passed,
+            total,
+            failure.unwrap_or_default()
+        );
+    }
+
+    assert!(
+        regressions.is_empty(),
+        "{} opcode files failed: {:?}",
+        regressions.len(),
+        regressions
+    );
+}